@@ -0,0 +1,712 @@
+//! Procedural macros for the [`thin_trait_object`] crate.
+//!
+//! Don't depend on this crate directly, instead enable the `macros` feature of
+//! the [`thin_trait_object`] crate and use the macros that it re-exports.
+//!
+//! [`thin_trait_object`]: https://docs.rs/thin_trait_object
+
+#![warn(clippy::all)]
+#![warn(missing_debug_implementations, missing_docs, rust_2018_idioms)]
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote, ToTokens};
+use syn::{
+    parse::{Parse, ParseStream},
+    parse_macro_input,
+    punctuated::Punctuated,
+    Abi, FnArg, GenericParam, ItemTrait, Meta, NestedMeta, Pat, Path, ReturnType, Signature, Token,
+    TraitBound, TraitBoundModifier, TraitItem, TraitItemMethod, TraitItemType, Type,
+    TypeParamBound, WherePredicate,
+};
+
+/// Define a vtable for a trait so that it can be used with thin pointers such as
+/// `ThinBox`.
+///
+/// This generates the same code as the `define_v_table` macro but since the
+/// trait is parsed with a real Rust parser it supports more trait definitions:
+///
+/// - Argument patterns such as `(a, b): (u32, u32)` or `mut value: u32`.
+/// - Methods with `#[cfg]` attributes, the attributes are forwarded to the
+///   generated code for the method.
+/// - Generic parameters and supertraits with several bounds.
+/// - Generic parameters bounded by traits such as `Fn(u32) -> u32` or
+///   `Iterator<Item = u8>`, in the parameter list or in the where clause.
+/// - Where clauses with several bounds per type.
+/// - Attributes, such as doc comments, anywhere in the trait.
+/// - Methods with a `Self: Sized` bound anywhere in their where clause.
+///
+/// Supertraits whose methods should be callable on the thin pointers are
+/// listed in a `#[thin_supertraits(...)]` attribute placed below this
+/// attribute, just like with the `define_v_table` macro.
+///
+/// Anything that can't be supported causes a compile error that points at the
+/// unsupported part of the trait.
+///
+/// Use `#[thin_trait_object(extern "C")]` to store function pointers with the
+/// C ABI in the vtable, just like `extern "C" trait` does with the
+/// `define_v_table` macro.
+///
+/// The macro expects to find the `thin_trait_object` crate at
+/// `::thin_trait_object`, use `#[thin_trait_object(crate = path::to::it)]`
+/// if it has been renamed.
+#[proc_macro_attribute]
+pub fn thin_trait_object(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(attr as Args);
+    let mut item = parse_macro_input!(item as ItemTrait);
+    // Always emit the trait as is so that errors don't cascade into the code
+    // that uses it:
+    let generated = expand(&args, &item).unwrap_or_else(|error| error.to_compile_error());
+    // Except for `#[thin_supertraits]` which isn't a real attribute:
+    item.attrs
+        .retain(|attr| !attr.path.is_ident("thin_supertraits"));
+    quote!(#item #generated).into()
+}
+
+/// Arguments for the [`thin_trait_object`] attribute.
+struct Args {
+    krate: Path,
+    /// The ABI of the vtable's function pointers.
+    abi: Option<Abi>,
+}
+impl Parse for Args {
+    fn parse(input: ParseStream<'_>) -> syn::Result<Self> {
+        let mut args = Self {
+            krate: syn::parse_quote!(::thin_trait_object),
+            abi: None,
+        };
+        while !input.is_empty() {
+            if input.peek(Token![extern]) {
+                args.abi = Some(input.parse()?);
+            } else {
+                input.parse::<Token![crate]>()?;
+                input.parse::<Token![=]>()?;
+                args.krate = Path::parse_mod_style(input)?;
+            }
+            if !input.is_empty() {
+                input.parse::<Token![,]>()?;
+            }
+        }
+        Ok(args)
+    }
+}
+
+/// Collects all errors so that they can be reported at the same time.
+#[derive(Default)]
+struct Errors(Option<syn::Error>);
+impl Errors {
+    fn push(&mut self, tokens: impl ToTokens, message: &str) {
+        let error = syn::Error::new_spanned(tokens, message);
+        match &mut self.0 {
+            Some(errors) => errors.combine(error),
+            None => self.0 = Some(error),
+        }
+    }
+    fn append(&mut self, other: Errors) {
+        if let Some(error) = other.0 {
+            match &mut self.0 {
+                Some(errors) => errors.combine(error),
+                None => self.0 = Some(error),
+            }
+        }
+    }
+    fn finish(self) -> syn::Result<()> {
+        self.0.map_or(Ok(()), Err)
+    }
+}
+
+/// A trait item that has been rewritten to a form that the `define_v_table`
+/// macro can parse.
+struct NormalizedItem {
+    /// The `#[cfg]` predicate of the item.
+    cfg: Option<TokenStream2>,
+    /// `true` for methods with a `Self: Sized` bound, `tokens` is then the
+    /// signature that the generated trait implementations must provide.
+    sized_fn: bool,
+    tokens: TokenStream2,
+}
+
+fn expand(args: &Args, item: &ItemTrait) -> syn::Result<TokenStream2> {
+    let mut errors = Errors::default();
+
+    if let Some(auto_token) = &item.auto_token {
+        errors.push(auto_token, "auto traits are not supported");
+    }
+    let trait_cfg = cfg_predicate(&item.attrs, &mut errors);
+
+    // Generic parameters, all bounds are moved to the where clause:
+    let mut lifetimes = Vec::new();
+    let mut generics = Vec::new();
+    let mut where_predicates = Vec::new();
+    for param in &item.generics.params {
+        match param {
+            GenericParam::Lifetime(def) => {
+                if let Some(extra) = def.bounds.iter().nth(1) {
+                    errors.push(
+                        extra,
+                        "lifetime parameters can only have a single lifetime bound",
+                    );
+                }
+                let lifetime = &def.lifetime;
+                let bound = def.bounds.first().map(|bound| quote!(: #bound));
+                lifetimes.push((lifetime.clone(), bound));
+            }
+            GenericParam::Type(param) => {
+                let ident = &param.ident;
+                for bound in &param.bounds {
+                    where_predicates.extend(predicate(ident, bound, &mut errors));
+                }
+                let default = param.default.as_ref().map(|default| quote!(= #default));
+                generics.push(quote!(#ident #default));
+            }
+            GenericParam::Const(param) => {
+                errors.push(param, "const generic parameters are not supported");
+            }
+        }
+    }
+    if let Some(where_clause) = &item.generics.where_clause {
+        for where_predicate in &where_clause.predicates {
+            match where_predicate {
+                WherePredicate::Type(predicate_type) => {
+                    if let Some(bound_lifetimes) = &predicate_type.lifetimes {
+                        errors.push(
+                            bound_lifetimes,
+                            "higher-ranked trait bounds are not supported",
+                        );
+                    }
+                    let ty = &predicate_type.bounded_ty;
+                    for bound in &predicate_type.bounds {
+                        where_predicates.extend(predicate(ty, bound, &mut errors));
+                    }
+                }
+                WherePredicate::Lifetime(predicate_lifetime) => {
+                    // Move the bound to the lifetime parameter:
+                    let param = lifetimes
+                        .iter_mut()
+                        .find(|(lifetime, _)| *lifetime == predicate_lifetime.lifetime);
+                    match (param, predicate_lifetime.bounds.len()) {
+                        (Some((_, bound @ None)), 1) => {
+                            let lifetime = &predicate_lifetime.bounds[0];
+                            *bound = Some(quote!(: #lifetime));
+                        }
+                        _ => errors.push(
+                            predicate_lifetime,
+                            "lifetime parameters can only have a single lifetime bound",
+                        ),
+                    }
+                }
+                WherePredicate::Eq(predicate_eq) => {
+                    errors.push(predicate_eq, "equality constraints are not supported");
+                }
+            }
+        }
+    }
+
+    // Supertraits, lifetimes must come before traits:
+    let mut super_lifetimes = Vec::new();
+    let mut super_traits = Vec::new();
+    let mut thin_clone = false;
+    for bound in &item.supertraits {
+        match bound {
+            TypeParamBound::Lifetime(lifetime) => super_lifetimes.push(lifetime),
+            TypeParamBound::Trait(trait_bound) => {
+                check_trait_bound(trait_bound, &mut errors);
+                if let TraitBoundModifier::Maybe(question) = &trait_bound.modifier {
+                    errors.push(question, "`?Trait` is not supported for supertraits");
+                }
+                let path = &trait_bound.path;
+                // Same check as the `define_v_table` macro, the trait can't be renamed:
+                if matches!(path.segments.last(), Some(segment) if segment.ident == "ThinClone") {
+                    thin_clone = true;
+                }
+                super_traits.push(path);
+            }
+        }
+    }
+
+    // Supertraits whose methods are forwarded through the vtable:
+    let mut forwarded_supertraits = Vec::new();
+    for attr in item
+        .attrs
+        .iter()
+        .filter(|attr| attr.path.is_ident("thin_supertraits"))
+    {
+        match attr.parse_args_with(Punctuated::<Path, Token![,]>::parse_terminated) {
+            Ok(paths) => forwarded_supertraits.extend(paths),
+            Err(error) => errors.push(attr, &error.to_string()),
+        }
+    }
+    for path in &forwarded_supertraits {
+        let tokens = path.to_token_stream().to_string();
+        if !super_traits
+            .iter()
+            .any(|super_trait| super_trait.to_token_stream().to_string() == tokens)
+        {
+            errors.push(
+                path,
+                "traits listed in `#[thin_supertraits]` must also be supertraits of the trait",
+            );
+        }
+    }
+
+    let items = item
+        .items
+        .iter()
+        .filter_map(|item| normalize_item(item, &mut errors))
+        .collect::<Vec<_>>();
+
+    if let Some(abi) = &args.abi {
+        if abi.name.is_none() {
+            errors.push(abi, "the ABI must be specified, for example `extern \"C\"`");
+        }
+    }
+
+    errors.finish()?;
+
+    let krate = &args.krate;
+    let abi = &args.abi;
+    let vis = &item.vis;
+    let unsafety = &item.unsafety;
+    let ident = &item.ident;
+    let generic_params = lifetimes
+        .iter()
+        .map(|(lifetime, bound)| quote!(#lifetime #bound))
+        .chain(generics)
+        .collect::<Vec<_>>();
+    let supertraits = if super_lifetimes.is_empty() && super_traits.is_empty() {
+        None
+    } else {
+        let super_lifetimes = super_lifetimes
+            .iter()
+            .map(|lifetime| lifetime.to_token_stream());
+        let super_traits = super_traits.iter().map(|path| path.to_token_stream());
+        let bounds = super_lifetimes.chain(super_traits);
+        // Every bound is preceded by `+` since a `where` clause could otherwise
+        // be parsed as a supertrait by the `define_v_table` macro:
+        Some(quote!(: #(+ #bounds)*))
+    };
+    let where_clause = if where_predicates.is_empty() {
+        None
+    } else {
+        Some(quote!(where #(#where_predicates,)*))
+    };
+    let thin_clone = if thin_clone { Some(quote!(true)) } else { None };
+
+    let cfg = trait_cfg.map(|predicate| quote!(#[cfg(#predicate)]));
+    let mut trait_items = Vec::new();
+    let mut sized_fns = Vec::new();
+    for item in &items {
+        // The `define_v_table` macro forwards `#[cfg]` attributes of methods:
+        let item_cfg = item
+            .cfg
+            .as_ref()
+            .map(|predicate| quote!(#[cfg(#predicate)]));
+        let tokens = &item.tokens;
+        if item.sized_fn {
+            sized_fns.push(quote!({ cfg = { #item_cfg }, signature = { #tokens }, }));
+        } else {
+            trait_items.push(quote!(#item_cfg #tokens));
+        }
+    }
+    Ok(quote! {
+        #cfg
+        #krate::__define_v_table_internal! {@parse_items
+            trait_def = {
+                #vis #unsafety trait #ident <#(#generic_params,)*> #supertraits #where_clause {}
+            },
+            thin_clone = { #thin_clone },
+            supertraits = { #(#forwarded_supertraits,)* },
+            abi = { #abi },
+            unparsed_items = { #(#trait_items)* },
+            item_cfg = {},
+            sized_fns = { #(#sized_fns)* },
+            parsed_fns = {},
+            parsed_associated_types = {},
+        }
+    })
+}
+
+/// Split a bound into a where clause predicate with a single bound.
+fn predicate(
+    ty: &impl ToTokens,
+    bound: &TypeParamBound,
+    errors: &mut Errors,
+) -> Option<TokenStream2> {
+    match bound {
+        TypeParamBound::Lifetime(lifetime) => Some(quote!(#ty: #lifetime)),
+        TypeParamBound::Trait(trait_bound) => {
+            check_trait_bound(trait_bound, errors);
+            let path = &trait_bound.path;
+            match trait_bound.modifier {
+                TraitBoundModifier::None => Some(quote!(#ty: #path)),
+                TraitBoundModifier::Maybe(_) => Some(quote!(#ty: ?#path)),
+            }
+        }
+    }
+}
+
+fn check_trait_bound(trait_bound: &TraitBound, errors: &mut Errors) {
+    if let Some(bound_lifetimes) = &trait_bound.lifetimes {
+        errors.push(
+            bound_lifetimes,
+            "higher-ranked trait bounds are not supported",
+        );
+    }
+}
+
+fn item_attrs(item: &TraitItem) -> &[syn::Attribute] {
+    match item {
+        TraitItem::Const(item) => &item.attrs,
+        TraitItem::Method(item) => &item.attrs,
+        TraitItem::Type(item) => &item.attrs,
+        TraitItem::Macro(item) => &item.attrs,
+        _ => &[],
+    }
+}
+
+fn cfg_attrs(attrs: &[syn::Attribute]) -> Vec<&syn::Attribute> {
+    attrs
+        .iter()
+        .filter(|attr| attr.path.is_ident("cfg"))
+        .collect()
+}
+
+/// Combine all `#[cfg]` attributes into a single predicate.
+fn cfg_predicate(attrs: &[syn::Attribute], errors: &mut Errors) -> Option<TokenStream2> {
+    for attr in attrs.iter().filter(|attr| attr.path.is_ident("cfg_attr")) {
+        // `#[cfg_attr(predicate, cfg(...))]` can't be evaluated by us:
+        if let Ok(Meta::List(list)) = attr.parse_meta() {
+            let has_cfg = list.nested.iter().skip(1).any(|nested| match nested {
+                NestedMeta::Meta(meta) => meta.path().is_ident("cfg"),
+                NestedMeta::Lit(_) => false,
+            });
+            if has_cfg {
+                errors.push(attr, "`cfg` inside of `cfg_attr` is not supported");
+            }
+        }
+    }
+    let predicates = cfg_attrs(attrs)
+        .into_iter()
+        .filter_map(|attr| match attr.parse_args::<NestedMeta>() {
+            Ok(predicate) => Some(predicate.into_token_stream()),
+            Err(error) => {
+                errors.push(attr, &error.to_string());
+                None
+            }
+        })
+        .collect::<Vec<_>>();
+    match predicates.len() {
+        0 => None,
+        1 => predicates.into_iter().next(),
+        _ => Some(quote!(all(#(#predicates),*))),
+    }
+}
+
+fn normalize_item(item: &TraitItem, errors: &mut Errors) -> Option<NormalizedItem> {
+    let mut sized_fn = false;
+    let tokens = match item {
+        // Methods that require `Self: Sized` aren't part of the vtable, the
+        // generated trait implementations only need to provide the ones
+        // without a default implementation:
+        TraitItem::Method(method) if requires_sized(&method.sig) => {
+            if method.default.is_some() {
+                return None;
+            }
+            sized_fn = true;
+            method.sig.to_token_stream()
+        }
+        TraitItem::Method(method) => normalize_method(method, errors)?,
+        TraitItem::Type(ty) => {
+            // Associated types are used by all of the generated code:
+            if let Some(attr) = cfg_attrs(&ty.attrs).first() {
+                errors.push(attr, "`#[cfg]` attributes are only supported on methods");
+            }
+            normalize_type(ty, errors)?
+        }
+        TraitItem::Const(item) => {
+            errors.push(
+                item,
+                "associated constants are not supported since they prevent the trait from being used as a trait object",
+            );
+            return None;
+        }
+        TraitItem::Macro(item) => {
+            errors.push(item, "macro invocations inside the trait are not supported");
+            return None;
+        }
+        item => {
+            errors.push(item, "unsupported trait item");
+            return None;
+        }
+    };
+    Some(NormalizedItem {
+        cfg: cfg_predicate(item_attrs(item), errors),
+        sized_fn,
+        tokens,
+    })
+}
+
+fn normalize_method(method: &TraitItemMethod, all_errors: &mut Errors) -> Option<TokenStream2> {
+    let mut errors = Errors::default();
+    let tokens = normalize_method_signature(method, &mut errors);
+    let failed = errors.0.is_some();
+    all_errors.append(errors);
+    if failed {
+        None
+    } else {
+        tokens
+    }
+}
+
+fn normalize_method_signature(
+    method: &TraitItemMethod,
+    errors: &mut Errors,
+) -> Option<TokenStream2> {
+    let Signature {
+        constness,
+        asyncness,
+        unsafety,
+        abi,
+        ident,
+        generics,
+        inputs,
+        variadic,
+        output,
+        ..
+    } = &method.sig;
+
+    if let Some(constness) = constness {
+        errors.push(constness, "const methods are not supported");
+    }
+    if let Some(asyncness) = asyncness {
+        errors.push(asyncness, "async methods are not supported");
+    }
+    if let Some(abi) = abi {
+        errors.push(abi, "methods with an explicit ABI are not supported");
+    }
+    if let Some(variadic) = variadic {
+        errors.push(variadic, "variadic methods are not supported");
+    }
+
+    // Lifetime parameters, bounds are moved to the where clause:
+    let mut lifetimes = Vec::new();
+    let mut where_lifetimes = Vec::new();
+    let mut where_predicates = Vec::new();
+    for param in &generics.params {
+        match param {
+            GenericParam::Lifetime(def) => {
+                let lifetime = &def.lifetime;
+                lifetimes.push(lifetime);
+                if !def.bounds.is_empty() {
+                    let bounds = def.bounds.iter();
+                    where_lifetimes.push(quote!(#lifetime: #(#bounds)+*));
+                }
+            }
+            GenericParam::Type(_) | GenericParam::Const(_) => {
+                errors.push(
+                    param,
+                    "generic methods are not supported since they prevent the trait from being used as a trait object",
+                );
+            }
+        }
+    }
+    if let Some(where_clause) = &generics.where_clause {
+        for where_predicate in &where_clause.predicates {
+            match where_predicate {
+                WherePredicate::Lifetime(predicate_lifetime) => {
+                    where_lifetimes.push(predicate_lifetime.into_token_stream());
+                }
+                WherePredicate::Type(predicate_type) => {
+                    if let Some(bound_lifetimes) = &predicate_type.lifetimes {
+                        errors.push(
+                            bound_lifetimes,
+                            "higher-ranked trait bounds are not supported",
+                        );
+                    }
+                    let ty = &predicate_type.bounded_ty;
+                    if is_self(ty) {
+                        errors.push(ty, "bounds on `Self` are not supported for methods");
+                        continue;
+                    }
+                    match ty {
+                        Type::Path(type_path) if type_path.qself.is_none() => {}
+                        _ => {
+                            errors.push(
+                                ty,
+                                "only bounds on type parameters and paths are supported in the where clause of methods",
+                            );
+                            continue;
+                        }
+                    }
+                    for bound in &predicate_type.bounds {
+                        where_predicates.extend(predicate(ty, bound, errors));
+                    }
+                }
+                WherePredicate::Eq(predicate_eq) => {
+                    errors.push(predicate_eq, "equality constraints are not supported");
+                }
+            }
+        }
+    }
+
+    // The receiver:
+    let mut inputs_iter = inputs.iter();
+    let receiver = match inputs_iter.next() {
+        Some(FnArg::Receiver(receiver)) => match &receiver.reference {
+            Some((_, lifetime)) => {
+                let mutability = &receiver.mutability;
+                Some(quote!(& #lifetime #mutability self))
+            }
+            // The binding mode of `mut self` doesn't affect the signature:
+            None => Some(quote!(self)),
+        },
+        Some(FnArg::Typed(pat_type)) if is_self_pat(&pat_type.pat) => match &*pat_type.ty {
+            ty if is_self(ty) => Some(quote!(self)),
+            Type::Reference(reference) if is_self(&reference.elem) => {
+                let lifetime = &reference.lifetime;
+                let mutability = &reference.mutability;
+                Some(quote!(& #lifetime #mutability self))
+            }
+            ty => {
+                errors.push(
+                    ty,
+                    "unsupported receiver type, only `self`, `&self` and `&mut self` are supported",
+                );
+                None
+            }
+        },
+        _ => {
+            errors.push(
+                &method.sig,
+                "methods without a `self` receiver are not supported",
+            );
+            None
+        }
+    };
+
+    // Other arguments, patterns are replaced with generated names:
+    let mut arguments = Vec::new();
+    for (index, input) in inputs_iter.enumerate() {
+        if let FnArg::Typed(pat_type) = input {
+            let name = match &*pat_type.pat {
+                Pat::Ident(pat_ident)
+                    if pat_ident.by_ref.is_none() && pat_ident.subpat.is_none() =>
+                {
+                    pat_ident.ident.clone()
+                }
+                _ => format_ident!("__arg{}", index),
+            };
+            let ty = &pat_type.ty;
+            if let Type::ImplTrait(_) = &**ty {
+                errors.push(ty, "`impl Trait` arguments are not supported");
+            }
+            arguments.push(quote!(#name: #ty));
+        }
+    }
+
+    if let ReturnType::Type(_, ty) = output {
+        if let Type::ImplTrait(_) = &**ty {
+            errors.push(ty, "`impl Trait` return types are not supported");
+        }
+    }
+
+    let receiver = receiver?;
+    let where_clause = if where_lifetimes.is_empty() && where_predicates.is_empty() {
+        None
+    } else {
+        Some(quote!(where #(#where_lifetimes,)* #(#where_predicates),*))
+    };
+    Some(quote! {
+        #unsafety fn #ident <#(#lifetimes),*> (#receiver #(, #arguments)*) #output #where_clause;
+    })
+}
+
+fn normalize_type(ty: &TraitItemType, errors: &mut Errors) -> Option<TokenStream2> {
+    if !ty.generics.params.is_empty() {
+        errors.push(&ty.generics, "generic associated types are not supported");
+    }
+    if let Some(where_clause) = &ty.generics.where_clause {
+        errors.push(
+            where_clause,
+            "where clauses on associated types are not supported",
+        );
+    }
+    if let Some((eq_token, default)) = &ty.default {
+        errors.push(
+            quote!(#eq_token #default),
+            "associated type defaults are not supported",
+        );
+    }
+
+    let mut lifetime_bounds = Vec::new();
+    let mut trait_bounds = Vec::new();
+    for bound in &ty.bounds {
+        match bound {
+            TypeParamBound::Lifetime(lifetime) => lifetime_bounds.push(lifetime),
+            TypeParamBound::Trait(trait_bound) => {
+                check_trait_bound(trait_bound, errors);
+                if let TraitBoundModifier::Maybe(question) = &trait_bound.modifier {
+                    errors.push(
+                        question,
+                        "`?Trait` bounds are not supported for associated types",
+                    );
+                }
+                trait_bounds.push(&trait_bound.path);
+            }
+        }
+    }
+
+    let ident = &ty.ident;
+    let bounds = if ty.bounds.is_empty() {
+        None
+    } else {
+        let lifetime_bounds = lifetime_bounds
+            .iter()
+            .map(|lifetime| lifetime.to_token_stream());
+        let trait_bounds = trait_bounds.iter().map(|path| path.to_token_stream());
+        let bounds = lifetime_bounds.chain(trait_bounds);
+        Some(quote!(: #(#bounds)+*))
+    };
+    Some(quote!(type #ident #bounds;))
+}
+
+/// Check if a method has a `Self: Sized` bound.
+fn requires_sized(sig: &Signature) -> bool {
+    let predicates = sig
+        .generics
+        .where_clause
+        .iter()
+        .flat_map(|where_clause| &where_clause.predicates);
+    for predicate in predicates {
+        if let WherePredicate::Type(predicate_type) = predicate {
+            if !is_self(&predicate_type.bounded_ty) {
+                continue;
+            }
+            let is_sized = predicate_type.bounds.iter().any(|bound| match bound {
+                TypeParamBound::Trait(trait_bound) => {
+                    matches!(trait_bound.modifier, TraitBoundModifier::None)
+                        && matches!(trait_bound.path.segments.last(), Some(segment) if segment.ident == "Sized")
+                }
+                TypeParamBound::Lifetime(_) => false,
+            });
+            if is_sized {
+                return true;
+            }
+        }
+    }
+    false
+}
+
+fn is_self(ty: &Type) -> bool {
+    match ty {
+        Type::Path(type_path) => type_path.qself.is_none() && type_path.path.is_ident("Self"),
+        _ => false,
+    }
+}
+
+fn is_self_pat(pat: &Pat) -> bool {
+    match pat {
+        Pat::Ident(pat_ident) => pat_ident.ident == "self",
+        _ => false,
+    }
+}
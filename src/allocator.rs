@@ -0,0 +1,104 @@
+//! Allocators that can be used for the heap allocations of thin boxes.
+//!
+//! [`RawThinBox`](crate::RawThinBox), [`ThinBox`](crate::ThinBox) and
+//! [`ThinBoxWithoutCommon`](crate::ThinBoxWithoutCommon) have an extra type
+//! parameter for the [`Allocator`] that manages their memory, which defaults to
+//! the [`Global`] allocator. The allocator is stored inside the heap allocation
+//! right before the vtable pointer so the thin pointers stay one word large.
+//!
+//! The trait is modeled after the unstable `core::alloc::Allocator` trait so
+//! that it can be implemented on stable Rust, for example by a bump or pool
+//! allocator:
+//!
+//! ```
+//! use core::{alloc::Layout, cell::Cell, ptr::NonNull};
+//! use thin_trait_object::allocator::{AllocError, Allocator, Global};
+//!
+//! /// Counts the number of live allocations.
+//! struct Counting<'a>(&'a Cell<usize>);
+//!
+//! unsafe impl Allocator for Counting<'_> {
+//!     fn allocate(&self, layout: Layout) -> Result<NonNull<u8>, AllocError> {
+//!         self.0.set(self.0.get() + 1);
+//!         Global.allocate(layout)
+//!     }
+//!     unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+//!         self.0.set(self.0.get() - 1);
+//!         Global.deallocate(ptr, layout)
+//!     }
+//! }
+//!
+//! let live = Cell::new(0);
+//! let layout = Layout::new::<u64>();
+//! let ptr = Counting(&live).allocate(layout).unwrap();
+//! assert_eq!(live.get(), 1);
+//! unsafe { Counting(&live).deallocate(ptr, layout) };
+//! assert_eq!(live.get(), 0);
+//! ```
+
+use core::{alloc::Layout, fmt, ptr::NonNull};
+
+/// An allocator that can allocate and free the memory of thin boxes.
+///
+/// # Safety
+///
+/// - Memory returned by [`allocate`](Allocator::allocate) must be valid for
+///   reads and writes of `layout.size()` bytes and aligned to
+///   `layout.align()`. It must stay valid until it is passed to
+///   [`deallocate`](Allocator::deallocate), even if the allocator is moved.
+/// - Memory that was allocated by an allocator can be freed by any value that
+///   is equal to it, for example a clone or a value that was moved.
+pub unsafe trait Allocator {
+    /// Allocate a memory block with the given layout. The layouts that are used
+    /// by this crate always have a non-zero size.
+    fn allocate(&self, layout: Layout) -> Result<NonNull<u8>, AllocError>;
+    /// Free a memory block.
+    ///
+    /// # Safety
+    ///
+    /// The memory block must have been allocated by this allocator with the
+    /// same layout.
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout);
+}
+unsafe impl<A> Allocator for &A
+where
+    A: Allocator + ?Sized,
+{
+    fn allocate(&self, layout: Layout) -> Result<NonNull<u8>, AllocError> {
+        (**self).allocate(layout)
+    }
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        (**self).deallocate(ptr, layout)
+    }
+}
+
+/// The global memory allocator, this allocates memory in the same way as
+/// `Box`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Global;
+unsafe impl Allocator for Global {
+    fn allocate(&self, layout: Layout) -> Result<NonNull<u8>, AllocError> {
+        if layout.size() == 0 {
+            // Safety: the alignment is never zero.
+            return Ok(unsafe { NonNull::new_unchecked(layout.align() as *mut u8) });
+        }
+        // Safety: the layout has a non-zero size.
+        NonNull::new(unsafe { alloc::alloc::alloc(layout) }).ok_or(AllocError)
+    }
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        if layout.size() != 0 {
+            alloc::alloc::dealloc(ptr.as_ptr(), layout);
+        }
+    }
+}
+
+/// The error that is returned when an [`Allocator`] fails to allocate memory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AllocError;
+impl fmt::Display for AllocError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("memory allocation failed")
+    }
+}
+#[cfg(feature = "std")]
+impl std::error::Error for AllocError {}
@@ -0,0 +1,233 @@
+//! An arena that stores type erased objects in large chunks of memory.
+//!
+//! Allocating an object in a [`ThinArena`] only bumps a pointer most of the
+//! time and the objects are never moved, so the returned references can be
+//! used for scratch data that has to stay at a fixed address. All objects are
+//! dropped via their vtables when the arena is dropped.
+
+use super::{
+    auto_traits, raw_thin_layout_with_header, GetThinTraitVTable, RawThin, Thin, ThinTrait,
+};
+
+use alloc::vec::Vec;
+use core::{
+    alloc::Layout,
+    cell::{Cell, RefCell},
+    cmp, fmt,
+    marker::PhantomData,
+    mem,
+    ptr::{self, NonNull},
+};
+
+/// The size of the first chunk if no capacity was specified.
+const DEFAULT_CHUNK_SIZE: usize = 4096;
+/// Chunks are doubled in size until they reach this size.
+const MAX_CHUNK_SIZE: usize = 1024 * 1024;
+
+/// Stored right before objects that need to be dropped. The entries form a
+/// linked list that is used to drop the objects in reverse allocation order.
+struct DropEntry {
+    next: Option<NonNull<DropEntry>>,
+    drop: unsafe fn(NonNull<u8>),
+}
+
+/// Drop a type erased object via its vtable.
+///
+/// # Safety
+///
+/// The pointer must point to a valid [`Thin`] value with the trait object
+/// type `V` and the common data `C` that won't be used again.
+unsafe fn drop_thin<V, C>(raw: NonNull<u8>)
+where
+    V: ThinTrait<C> + ?Sized,
+{
+    Thin::drop_in_place(raw.cast::<Thin<'_, V, C>>().as_ptr());
+}
+
+/// An arena that bump allocates type erased objects.
+///
+/// The objects can have any trait object type and common data and they can
+/// borrow data that outlives `'erased_data`.
+///
+/// # Examples
+///
+/// ```
+/// use thin_trait_object::{arena::ThinArena, define_v_table};
+///
+/// define_v_table!(
+///     trait Greet {
+///         fn greet(&self) -> String;
+///     }
+/// );
+/// impl Greet for &str {
+///     fn greet(&self) -> String {
+///         format!("Hello {}!", self)
+///     }
+/// }
+///
+/// let name = String::from("arena");
+/// let arena = ThinArena::new();
+/// let greeter = arena.alloc::<dyn Greet, _, _>(name.as_str(), ());
+/// assert_eq!(greeter.greet(), "Hello arena!");
+/// ```
+pub struct ThinArena<'erased_data> {
+    /// The next free byte in the current chunk.
+    next: Cell<*mut u8>,
+    /// The end of the current chunk.
+    end: Cell<*mut u8>,
+    /// The size of the next chunk that is allocated.
+    next_chunk_size: Cell<usize>,
+    /// All chunks with their layouts.
+    chunks: RefCell<Vec<(NonNull<u8>, Layout)>>,
+    /// The most recently allocated object that needs to be dropped.
+    drop_list: Cell<Option<NonNull<DropEntry>>>,
+    /// The arena owns objects that can borrow data for `'erased_data`.
+    _erased_data: PhantomData<fn(&'erased_data ()) -> &'erased_data ()>,
+}
+impl<'erased_data> ThinArena<'erased_data> {
+    /// Create an empty arena. No memory is allocated until an object is stored.
+    pub fn new() -> Self {
+        Self::with_capacity(DEFAULT_CHUNK_SIZE)
+    }
+    /// Create an empty arena whose first chunk will have room for `bytes`
+    /// bytes. No memory is allocated until an object is stored.
+    pub fn with_capacity(bytes: usize) -> Self {
+        Self {
+            next: Cell::new(ptr::null_mut()),
+            end: Cell::new(ptr::null_mut()),
+            next_chunk_size: Cell::new(cmp::max(bytes, 1)),
+            chunks: RefCell::new(Vec::new()),
+            drop_list: Cell::new(None),
+            _erased_data: PhantomData,
+        }
+    }
+
+    /// Move an object and its common data into the arena. The object is
+    /// dropped when the arena is dropped.
+    #[allow(clippy::mut_from_ref)]
+    pub fn alloc<V, C, T>(&self, x: T, common: C) -> &mut Thin<'erased_data, V, C>
+    where
+        V: ThinTrait<C> + ?Sized,
+        T: auto_traits::HasAutoTraits<V::AutoTraitConfig> + 'erased_data,
+        V::VTable: GetThinTraitVTable<T>,
+        C: 'erased_data,
+    {
+        let raw = RawThin::<'erased_data, V::VTable, C, _, T> {
+            vtable: <V::VTable as GetThinTraitVTable<T>>::get_vtable().vtable,
+            common,
+            // Safety: the `HasAutoTraits` bound ensures that the object
+            // implements the auto traits of the marker type.
+            _not_send_or_sync: PhantomData::<super::ThinTraitAutoTraitsMarker<V, C>>,
+            _lifetime: PhantomData,
+            _object: x,
+        };
+        let needs_drop = mem::needs_drop::<T>() || mem::needs_drop::<C>();
+        let raw_layout = Layout::new::<RawThin<'erased_data, V::VTable, C, (), T>>();
+        let (layout, offset) = if needs_drop {
+            raw_thin_layout_with_header::<DropEntry>(raw_layout)
+        } else {
+            (raw_layout, 0)
+        };
+
+        let start = self.bump(layout);
+        // Safety: `bump` returned memory that fits the layout and the offset is
+        // inside it. `raw_thin_layout_with_header` ensures that the drop entry
+        // and the `RawThin` value are aligned.
+        unsafe {
+            let raw_ptr = start.as_ptr().add(offset);
+            ptr::write(
+                raw_ptr as *mut RawThin<'erased_data, V::VTable, C, _, T>,
+                raw,
+            );
+            let raw_ptr = NonNull::new_unchecked(raw_ptr);
+            if needs_drop {
+                let entry = (raw_ptr.as_ptr() as *mut DropEntry).sub(1);
+                ptr::write(
+                    entry,
+                    DropEntry {
+                        next: self.drop_list.get(),
+                        drop: drop_thin::<V, C>,
+                    },
+                );
+                self.drop_list.set(Some(NonNull::new_unchecked(entry)));
+            }
+            // Safety: `Thin` is a `repr(transparent)` struct around `RawThin`
+            // with the same vtable, common data and marker types. The object
+            // is never moved and lives as long as the arena.
+            &mut *raw_ptr.cast::<Thin<'erased_data, V, C>>().as_ptr()
+        }
+    }
+
+    /// The total size of the chunks that the arena has allocated.
+    pub fn allocated_bytes(&self) -> usize {
+        self.chunks
+            .borrow()
+            .iter()
+            .map(|(_, layout)| layout.size())
+            .sum()
+    }
+
+    /// Get memory for the layout from the current chunk or a new one.
+    fn bump(&self, layout: Layout) -> NonNull<u8> {
+        let next = self.next.get();
+        let offset = next.align_offset(layout.align());
+        let available = self.end.get() as usize - next as usize;
+        if !next.is_null() && offset <= available && layout.size() <= available - offset {
+            let start = next.wrapping_add(offset);
+            self.next.set(start.wrapping_add(layout.size()));
+            // Safety: the pointer is inside a chunk that isn't null.
+            return unsafe { NonNull::new_unchecked(start) };
+        }
+        self.bump_new_chunk(layout)
+    }
+    /// Allocate a new chunk that fits the layout and use it as the current chunk.
+    #[cold]
+    fn bump_new_chunk(&self, layout: Layout) -> NonNull<u8> {
+        let chunk_size = cmp::max(self.next_chunk_size.get(), layout.size());
+        let chunk_layout = Layout::from_size_align(chunk_size, layout.align())
+            .expect("the chunk size should be valid");
+        // Safety: the chunk size is never zero.
+        let start = unsafe { alloc::alloc::alloc(chunk_layout) };
+        let start = match NonNull::new(start) {
+            Some(start) => start,
+            None => alloc::alloc::handle_alloc_error(chunk_layout),
+        };
+        self.chunks.borrow_mut().push((start, chunk_layout));
+        self.next_chunk_size
+            .set(cmp::min(chunk_size.saturating_mul(2), MAX_CHUNK_SIZE));
+
+        self.next.set(start.as_ptr().wrapping_add(layout.size()));
+        self.end.set(start.as_ptr().wrapping_add(chunk_size));
+        start
+    }
+}
+impl Default for ThinArena<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl fmt::Debug for ThinArena<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct(get_type_name!(ThinArena))
+            .field("allocated_bytes", &self.allocated_bytes())
+            .finish()
+    }
+}
+impl Drop for ThinArena<'_> {
+    fn drop(&mut self) {
+        let mut entry = self.drop_list.take();
+        while let Some(current) = entry {
+            // Safety: the entry is stored right before an object that hasn't
+            // been dropped yet and the arena is never used again.
+            unsafe {
+                let DropEntry { next, drop } = ptr::read(current.as_ptr());
+                drop(NonNull::new_unchecked(current.as_ptr().add(1) as *mut u8));
+                entry = next;
+            }
+        }
+        for (start, layout) in self.chunks.get_mut().drain(..) {
+            // Safety: the chunk was allocated with this layout.
+            unsafe { alloc::alloc::dealloc(start.as_ptr(), layout) };
+        }
+    }
+}
@@ -0,0 +1,262 @@
+//! Thin boxes that can be swapped atomically.
+//!
+//! A [`ThinBox`] is a single pointer so it can be stored in an [`AtomicPtr`],
+//! which allows replacing a type erased object from other threads without
+//! locking.
+//!
+//! The methods that replace a thin box take ownership of the previous one, so
+//! they always synchronize with the thread that stored it: swaps and successful
+//! compare exchanges use [`Ordering::AcqRel`] and failed compare exchanges use
+//! [`Ordering::Acquire`]. Weaker orderings would allow the previous object to
+//! be used or dropped before its contents are visible to the current thread.
+
+use super::{Thin, ThinBox, ThinTrait, ThinTraitAutoTraitsMarker};
+
+use core::{
+    fmt,
+    marker::PhantomData,
+    ptr::{self, NonNull},
+    sync::atomic::{AtomicPtr, Ordering},
+};
+
+/// Convert an optional [`ThinBox`] to a pointer that might be null.
+fn into_ptr<'erased_data, V, C>(
+    value: Option<ThinBox<'erased_data, V, C>>,
+) -> *mut Thin<'erased_data, V, C>
+where
+    V: ThinTrait<C> + ?Sized,
+{
+    value.map_or(ptr::null_mut(), |value| {
+        ThinBox::into_raw_ptr(value).as_ptr()
+    })
+}
+
+/// Take ownership of a pointer created by [`into_ptr`].
+///
+/// # Safety
+///
+/// The pointer must be null or it must have been created by [`into_ptr`] and
+/// not have been converted back yet.
+unsafe fn from_ptr<'erased_data, V, C>(
+    ptr: *mut Thin<'erased_data, V, C>,
+) -> Option<ThinBox<'erased_data, V, C>>
+where
+    V: ThinTrait<C> + ?Sized,
+{
+    NonNull::new(ptr).map(|ptr| ThinBox::from_raw_ptr(ptr))
+}
+
+/// A [`ThinBox`] that can be replaced atomically, built on [`AtomicPtr`].
+///
+/// The stored object can't be borrowed since another thread could replace and
+/// drop it at any time, instead it can be swapped out for another object. Use
+/// [`AtomicOptionThinBox`] if the slot can be empty.
+///
+/// This type is [`Send`] and [`Sync`] if the erased object can be sent to
+/// other threads, which is only guaranteed if the auto trait config enforces
+/// [`Send`] with [`HasSend`](crate::auto_traits::HasSend) (for example when `V`
+/// is `dyn Trait + Send`).
+pub struct AtomicThinBox<'erased_data, V, C>
+where
+    V: ThinTrait<C> + ?Sized,
+{
+    inner: AtomicOptionThinBox<'erased_data, V, C>,
+}
+impl<'erased_data, V, C> AtomicThinBox<'erased_data, V, C>
+where
+    V: ThinTrait<C> + ?Sized,
+{
+    /// Create a new [`AtomicThinBox`] that stores a thin box.
+    pub fn new(value: ThinBox<'erased_data, V, C>) -> Self {
+        Self {
+            inner: AtomicOptionThinBox::new(Some(value)),
+        }
+    }
+    /// Store a new thin box and return the previous one.
+    pub fn swap(&self, value: ThinBox<'erased_data, V, C>) -> ThinBox<'erased_data, V, C> {
+        self.inner
+            .swap(Some(value))
+            .expect("an `AtomicThinBox` always stores a value")
+    }
+    /// Store a new thin box and drop the previous one.
+    pub fn store(&self, value: ThinBox<'erased_data, V, C>) {
+        drop(self.swap(value));
+    }
+    /// Store a new thin box if the current one is `current` (as returned by
+    /// [`AtomicThinBox::load_ptr`]). On success the previous thin box is
+    /// returned, otherwise the new thin box is given back.
+    pub fn compare_exchange(
+        &self,
+        current: *const Thin<'erased_data, V, C>,
+        value: ThinBox<'erased_data, V, C>,
+    ) -> Result<ThinBox<'erased_data, V, C>, ThinBox<'erased_data, V, C>> {
+        match self.inner.compare_exchange(current, Some(value)) {
+            Ok(previous) => Ok(previous.expect("an `AtomicThinBox` always stores a value")),
+            Err(value) => Err(value.expect("the new value was `Some`")),
+        }
+    }
+    /// The address of the currently stored thin box. The pointer should only be
+    /// compared with other pointers since the allocation could be freed by
+    /// another thread at any time.
+    pub fn load_ptr(&self, order: Ordering) -> *const Thin<'erased_data, V, C> {
+        self.inner.load_ptr(order)
+    }
+    /// Get a mutable reference to the stored thin box. This is safe since the
+    /// mutable reference guarantees that no other thread can access it.
+    pub fn get_mut(&mut self) -> &mut ThinBox<'erased_data, V, C> {
+        self.inner
+            .get_mut()
+            .as_mut()
+            .expect("an `AtomicThinBox` always stores a value")
+    }
+    /// Take the stored thin box.
+    pub fn into_inner(self) -> ThinBox<'erased_data, V, C> {
+        self.inner
+            .into_inner()
+            .expect("an `AtomicThinBox` always stores a value")
+    }
+}
+impl<V, C> fmt::Debug for AtomicThinBox<'_, V, C>
+where
+    V: ThinTrait<C> + ?Sized,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple(get_type_name!(AtomicThinBox))
+            .field(&self.load_ptr(Ordering::Relaxed))
+            .finish()
+    }
+}
+
+/// An optional [`ThinBox`] that can be replaced atomically, built on
+/// [`AtomicPtr`]. A null pointer is stored when it is empty.
+///
+/// This type is [`Send`] and [`Sync`] under the same conditions as
+/// [`AtomicThinBox`].
+pub struct AtomicOptionThinBox<'erased_data, V, C>
+where
+    V: ThinTrait<C> + ?Sized,
+{
+    ptr: AtomicPtr<Thin<'erased_data, V, C>>,
+    _owned: PhantomData<ThinBox<'erased_data, V, C>>,
+}
+// Safety: the thin boxes can be moved to another thread by any thread that has
+// a shared reference, but they are never borrowed. So like `Mutex` only `Send`
+// is required. The auto trait marker only implements `Send` if the auto trait
+// config guarantees that the erased object implements it.
+unsafe impl<'erased_data, V, C> Send for AtomicOptionThinBox<'erased_data, V, C>
+where
+    V: ThinTrait<C> + ?Sized,
+    ThinTraitAutoTraitsMarker<V, C>: Send,
+    C: Send,
+{
+}
+unsafe impl<'erased_data, V, C> Sync for AtomicOptionThinBox<'erased_data, V, C>
+where
+    V: ThinTrait<C> + ?Sized,
+    ThinTraitAutoTraitsMarker<V, C>: Send,
+    C: Send,
+{
+}
+impl<'erased_data, V, C> AtomicOptionThinBox<'erased_data, V, C>
+where
+    V: ThinTrait<C> + ?Sized,
+{
+    /// Create a new [`AtomicOptionThinBox`] that might store a thin box.
+    pub fn new(value: Option<ThinBox<'erased_data, V, C>>) -> Self {
+        Self {
+            ptr: AtomicPtr::new(into_ptr(value)),
+            _owned: PhantomData,
+        }
+    }
+    /// Create an empty [`AtomicOptionThinBox`].
+    pub const fn none() -> Self {
+        Self {
+            ptr: AtomicPtr::new(ptr::null_mut()),
+            _owned: PhantomData,
+        }
+    }
+    /// Store a new value and return the previous one.
+    pub fn swap(
+        &self,
+        value: Option<ThinBox<'erased_data, V, C>>,
+    ) -> Option<ThinBox<'erased_data, V, C>> {
+        let previous = self.ptr.swap(into_ptr(value), Ordering::AcqRel);
+        // Safety: the pointer was created by `into_ptr` and it was removed from
+        // the atomic pointer so it won't be converted back again.
+        unsafe { from_ptr(previous) }
+    }
+    /// Store a new value and drop the previous one.
+    pub fn store(&self, value: Option<ThinBox<'erased_data, V, C>>) {
+        drop(self.swap(value));
+    }
+    /// Take the stored thin box and leave the slot empty.
+    pub fn take(&self) -> Option<ThinBox<'erased_data, V, C>> {
+        self.swap(None)
+    }
+    /// Store a new value if the current one is `current` (as returned by
+    /// [`AtomicOptionThinBox::load_ptr`], null for an empty slot). On success
+    /// the previous value is returned, otherwise the new value is given back.
+    pub fn compare_exchange(
+        &self,
+        current: *const Thin<'erased_data, V, C>,
+        value: Option<ThinBox<'erased_data, V, C>>,
+    ) -> Result<Option<ThinBox<'erased_data, V, C>>, Option<ThinBox<'erased_data, V, C>>> {
+        let new = into_ptr(value);
+        match self
+            .ptr
+            .compare_exchange(current as *mut _, new, Ordering::AcqRel, Ordering::Acquire)
+        {
+            // Safety: the previous pointer was removed from the atomic pointer.
+            Ok(previous) => Ok(unsafe { from_ptr(previous) }),
+            // Safety: the new pointer was never stored.
+            Err(_) => Err(unsafe { from_ptr(new) }),
+        }
+    }
+    /// The address of the currently stored thin box or null if there isn't
+    /// one. The pointer should only be compared with other pointers since the
+    /// allocation could be freed by another thread at any time.
+    pub fn load_ptr(&self, order: Ordering) -> *const Thin<'erased_data, V, C> {
+        self.ptr.load(order)
+    }
+    /// Get a mutable reference to the stored value. This is safe since the
+    /// mutable reference guarantees that no other thread can access it.
+    pub fn get_mut(&mut self) -> &mut Option<ThinBox<'erased_data, V, C>> {
+        // Safety: `ThinBox` has the same layout as a non-null pointer to its
+        // allocation, so `Option<ThinBox>` has the same layout as a pointer
+        // that might be null. The pointer was created by `into_ptr`.
+        unsafe {
+            &mut *(self.ptr.get_mut() as *mut *mut Thin<'erased_data, V, C>
+                as *mut Option<ThinBox<'erased_data, V, C>>)
+        }
+    }
+    /// Take the stored value.
+    pub fn into_inner(mut self) -> Option<ThinBox<'erased_data, V, C>> {
+        self.get_mut().take()
+    }
+}
+impl<V, C> Default for AtomicOptionThinBox<'_, V, C>
+where
+    V: ThinTrait<C> + ?Sized,
+{
+    fn default() -> Self {
+        Self::none()
+    }
+}
+impl<V, C> fmt::Debug for AtomicOptionThinBox<'_, V, C>
+where
+    V: ThinTrait<C> + ?Sized,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple(get_type_name!(AtomicOptionThinBox))
+            .field(&self.load_ptr(Ordering::Relaxed))
+            .finish()
+    }
+}
+impl<V, C> Drop for AtomicOptionThinBox<'_, V, C>
+where
+    V: ThinTrait<C> + ?Sized,
+{
+    fn drop(&mut self) {
+        drop(self.get_mut().take());
+    }
+}
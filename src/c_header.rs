@@ -0,0 +1,283 @@
+//! Generate C headers for vtables that were defined with `extern "C"`.
+//!
+//! The [`define_v_table`](crate::define_v_table) macro implements
+//! [`DescribeVTable`] for the vtables of traits that are defined with
+//! `extern "C"`. The description can then be turned into a C header with
+//! [`c_header`], for example from a test or a small binary that writes the
+//! header to a file so that C and C++ code stays in sync with the trait:
+//!
+//! ```
+//! use thin_trait_object::{c_header::c_header, define_v_table};
+//!
+//! define_v_table! {
+//!     pub extern "C" trait Counter {
+//!         fn get(&self) -> u32;
+//!         fn add(&mut self, amount: u32);
+//!     }
+//! }
+//!
+//! let header = c_header::<dyn Counter, u64>(Some("uint64_t"));
+//! assert!(header.contains("typedef void (*Counter_add)(Counter *self, uint32_t amount);"));
+//! ```
+//!
+//! Rust types are written to the header with the following rules:
+//!
+//! - Integers, floats and `bool` use the types from `<stdint.h>` and
+//!   `<stdbool.h>`. `char` is written as `uint32_t`.
+//! - The type aliases from [`core::ffi`] (`c_int`, `c_char` and so on) use the
+//!   C type that they are named after and `c_void` and `()` become `void`.
+//! - Raw pointers and references become C pointers.
+//! - Any other type is written as the last segment of its path, so a C type
+//!   with that name must be declared before the header is included.
+
+#[cfg(feature = "alloc")]
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+#[cfg(feature = "alloc")]
+use core::fmt::Write;
+
+#[cfg(feature = "alloc")]
+use super::ThinTrait;
+
+/// Describes the layout of a vtable that C code can use.
+///
+/// # Safety
+///
+/// The description must match the actual layout of the vtable, otherwise C
+/// code that uses a generated header would call the wrong function pointers.
+pub unsafe trait DescribeVTable {
+    /// The description of the vtable.
+    const DESCRIPTION: VTableDescription;
+}
+
+/// The methods and supertraits that are stored in a vtable.
+#[derive(Debug, Clone, Copy)]
+pub struct VTableDescription {
+    /// The name of the trait.
+    pub trait_name: &'static str,
+    /// The vtables of the forwarded supertraits that are stored at the start of
+    /// the vtable.
+    pub supertraits: &'static [VTableDescription],
+    /// The methods in the order that their function pointers are stored.
+    pub methods: &'static [MethodDescription],
+    /// `true` if the vtable stores a `clone` function pointer.
+    pub thin_clone: bool,
+    /// The number of function pointers that are stored after the fields that
+    /// C code can use. They are declared in the header so that the vtable of a
+    /// supertrait has the right size when it is stored inside another vtable.
+    pub rust_only_fields: usize,
+}
+
+/// A method that is stored as a function pointer in a vtable.
+#[derive(Debug, Clone, Copy)]
+pub struct MethodDescription {
+    /// The name of the method.
+    pub name: &'static str,
+    /// How the method takes `self`.
+    pub receiver: Receiver,
+    /// The names and Rust types of the method's arguments, `self` not included.
+    pub arguments: &'static [(&'static str, &'static str)],
+    /// The Rust return type of the method, empty if it doesn't return anything.
+    pub return_type: &'static str,
+}
+
+/// How a method takes `self`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Receiver {
+    /// `&self`, the method gets a `const` pointer.
+    Ref,
+    /// `&mut self`, the method gets a mutable pointer.
+    Mut,
+    /// `self`, the method takes ownership of the allocation and frees it.
+    Value,
+}
+
+/// Generate a C header that declares the vtable of the trait object type `V`,
+/// a struct for the start of the allocations that store type erased objects
+/// and a function pointer typedef for each method.
+///
+/// `common` is the C type of the common data `C`, use `None` if there isn't
+/// any common data (for example if it is `()`).
+///
+/// The vtables of forwarded supertraits are declared as well. Generic
+/// parameters of the traits aren't part of the C names so a header should
+/// only be generated for one set of generic arguments.
+#[cfg(feature = "alloc")]
+#[cfg_attr(feature = "docs", doc(cfg(feature = "alloc")))]
+pub fn c_header<V, C>(common: Option<&str>) -> String
+where
+    V: ThinTrait<C> + ?Sized,
+    V::VTable: DescribeVTable,
+{
+    let description = &<V::VTable as DescribeVTable>::DESCRIPTION;
+    let mut header = String::new();
+    let _ = writeln!(
+        header,
+        "/* Generated by thin_trait_object for the `{}` trait. */",
+        description.trait_name
+    );
+    header.push_str("#include <stdbool.h>\n#include <stdint.h>\n\n");
+
+    let mut declared = Vec::new();
+    write_vtable(&mut header, description, common, &mut declared);
+    header
+}
+
+/// Declare the header and vtable structs of a trait after the ones of its
+/// supertraits. Traits that are already `declared` are skipped.
+#[cfg(feature = "alloc")]
+fn write_vtable(
+    header: &mut String,
+    description: &VTableDescription,
+    common: Option<&str>,
+    declared: &mut Vec<&'static str>,
+) {
+    for supertrait in description.supertraits {
+        write_vtable(header, supertrait, common, declared);
+    }
+    let name = description.trait_name;
+    if declared.contains(&name) {
+        return;
+    }
+    declared.push(name);
+
+    let _ = writeln!(header, "typedef struct {}VTable {}VTable;\n", name, name);
+    let _ = writeln!(
+        header,
+        "/* The start of an allocation that stores a type erased `{}` object. */",
+        name
+    );
+    let _ = writeln!(header, "typedef struct {} {{", name);
+    let _ = writeln!(header, "    const {}VTable *vtable;", name);
+    if let Some(common) = common {
+        let _ = writeln!(header, "    {} common;", common);
+    }
+    let _ = writeln!(header, "}} {};\n", name);
+
+    for method in description.methods {
+        let self_ty = match method.receiver {
+            Receiver::Ref => format!("const {} *self", name),
+            Receiver::Mut | Receiver::Value => format!("{} *self", name),
+        };
+        let arguments = method
+            .arguments
+            .iter()
+            .map(|(argument, ty)| format!(", {}", declaration(&c_type(ty), argument)))
+            .collect::<String>();
+        let function = format!("(*{}_{})({}{})", name, method.name, self_ty, arguments);
+        let _ = writeln!(
+            header,
+            "typedef {};",
+            declaration(&c_type(method.return_type), &function)
+        );
+    }
+    let _ = writeln!(
+        header,
+        "typedef void (*{}_drop_in_place)({} *self);",
+        name, name
+    );
+    if description.thin_clone {
+        let _ = writeln!(
+            header,
+            "typedef {} *(*{}_clone)(const {} *self);",
+            name, name, name
+        );
+    }
+
+    let _ = writeln!(header, "\nstruct {}VTable {{", name);
+    for supertrait in description.supertraits {
+        let _ = writeln!(
+            header,
+            "    {}VTable {}_vtable;",
+            supertrait.trait_name,
+            supertrait.trait_name.to_lowercase()
+        );
+    }
+    for method in description.methods {
+        let _ = writeln!(header, "    {}_{} {};", name, method.name, method.name);
+    }
+    let _ = writeln!(header, "    {}_drop_in_place drop_in_place;", name);
+    if description.thin_clone {
+        let _ = writeln!(header, "    {}_clone clone;", name);
+    }
+    let _ = writeln!(
+        header,
+        "    /* Only used by Rust code: */\n    void (*rust_only[{}])(void);\n}};\n",
+        description.rust_only_fields
+    );
+}
+
+/// Declare `name` with a C type, pointer types are written next to the name.
+#[cfg(feature = "alloc")]
+fn declaration(ty: &str, name: &str) -> String {
+    if ty.ends_with('*') {
+        format!("{}{}", ty, name)
+    } else {
+        format!("{} {}", ty, name)
+    }
+}
+
+/// The C type for a Rust type (as written by `stringify!`).
+#[cfg(feature = "alloc")]
+fn c_type(ty: &str) -> String {
+    let ty = ty.trim();
+    let pointer = |pointee: &str, is_const: bool| {
+        let pointee = c_type(pointee);
+        match (is_const, pointee.ends_with('*')) {
+            (true, false) => format!("const {} *", pointee),
+            (true, true) => format!("{} const *", pointee),
+            (false, false) => format!("{} *", pointee),
+            (false, true) => format!("{}*", pointee),
+        }
+    };
+    if let Some(pointee) = ty.strip_prefix("*const ") {
+        return pointer(pointee, true);
+    }
+    if let Some(pointee) = ty.strip_prefix("*mut ") {
+        return pointer(pointee, false);
+    }
+    if let Some(pointee) = ty.strip_prefix('&') {
+        // Skip the lifetime of the reference:
+        let pointee = match pointee.strip_prefix('\'') {
+            Some(lifetime) => lifetime.split_once(' ').map_or("", |(_, pointee)| pointee),
+            None => pointee,
+        };
+        return match pointee.trim_start().strip_prefix("mut ") {
+            Some(pointee) => pointer(pointee, false),
+            None => pointer(pointee, true),
+        };
+    }
+    let name = ty.rsplit("::").next().unwrap_or(ty).trim();
+    match name {
+        "" | "()" | "c_void" => "void",
+        "bool" => "bool",
+        "u8" => "uint8_t",
+        "u16" => "uint16_t",
+        "u32" | "char" => "uint32_t",
+        "u64" => "uint64_t",
+        "usize" => "uintptr_t",
+        "i8" => "int8_t",
+        "i16" => "int16_t",
+        "i32" => "int32_t",
+        "i64" => "int64_t",
+        "isize" => "intptr_t",
+        "f32" | "c_float" => "float",
+        "f64" | "c_double" => "double",
+        "c_char" => "char",
+        "c_schar" => "signed char",
+        "c_uchar" => "unsigned char",
+        "c_short" => "short",
+        "c_ushort" => "unsigned short",
+        "c_int" => "int",
+        "c_uint" => "unsigned int",
+        "c_long" => "long",
+        "c_ulong" => "unsigned long",
+        "c_longlong" => "long long",
+        "c_ulonglong" => "unsigned long long",
+        other => return other.to_string(),
+    }
+    .to_string()
+}
@@ -0,0 +1,195 @@
+//! Type erased objects that are stored inline instead of in a heap allocation.
+//!
+//! A [`ThinInline`] has a buffer with a fixed size that stores the vtable
+//! pointer, the common data and the object. Unlike [`OwnedThin`](crate::OwnedThin)
+//! the object's type isn't part of the [`ThinInline`] type so objects of
+//! different types can be stored in the same variable or collection.
+
+#[cfg(feature = "alloc")]
+use super::ThinBox;
+use super::{auto_traits, GetThinTraitVTable, RawThin, Thin, ThinTrait};
+
+use core::{
+    alloc::Layout,
+    fmt,
+    marker::PhantomData,
+    mem::{self, MaybeUninit},
+    ops::{Deref, DerefMut},
+    ptr,
+};
+
+/// The buffer of a [`ThinInline`].
+#[repr(C, align(16))]
+struct InlineBuffer<const N: usize>([MaybeUninit<u8>; N]);
+
+/// Checks at compile time that an object of type `T` fits in a [`ThinInline`].
+struct AssertFits<'erased_data, V, C, T, const N: usize>(
+    PhantomData<(&'erased_data (), *const V, C, T)>,
+)
+where
+    V: ThinTrait<C> + ?Sized;
+impl<'erased_data, V, C, T, const N: usize> AssertFits<'erased_data, V, C, T, N>
+where
+    V: ThinTrait<C> + ?Sized,
+{
+    const OK: () = assert!(
+        ThinInline::<'erased_data, V, C, N>::fits(Layout::new::<
+            RawThin<'erased_data, <V as ThinTrait<C>>::VTable, C, (), T>,
+        >()),
+        "the object doesn't fit in the buffer of the `ThinInline`"
+    );
+}
+
+/// A type erased object that is stored inline in a buffer of `N` bytes.
+///
+/// The buffer stores the vtable pointer and the common data as well, so it
+/// needs to be a few words larger than the object. It is aligned to
+/// [`ThinInline::ALIGN`] bytes and objects that need a larger alignment can't
+/// be stored in it.
+///
+/// # Examples
+///
+/// ```
+/// use thin_trait_object::{define_v_table, ThinInline};
+///
+/// define_v_table!(
+///     trait Shape {
+///         fn area(&self) -> f64;
+///     }
+/// );
+/// impl Shape for f64 {
+///     fn area(&self) -> f64 { self * self }
+/// }
+/// impl Shape for (f64, f64) {
+///     fn area(&self) -> f64 { self.0 * self.1 }
+/// }
+/// impl Shape for [f64; 4] {
+///     fn area(&self) -> f64 { self.iter().product() }
+/// }
+///
+/// let shapes: [ThinInline<'_, dyn Shape, (), 32>; 2] = [
+///     ThinInline::new(2.0_f64, ()),
+///     ThinInline::new((2.0_f64, 3.0_f64), ()),
+/// ];
+/// assert_eq!(shapes[0].area() + shapes[1].area(), 10.0);
+///
+/// // Objects that are too large are given back:
+/// let large = [1.0_f64, 2.0, 3.0, 4.0];
+/// assert!(ThinInline::<'_, dyn Shape, (), 32>::try_new(large, ()).is_err());
+/// ```
+pub struct ThinInline<'erased_data, V, C, const N: usize>
+where
+    V: ThinTrait<C> + ?Sized,
+{
+    buffer: InlineBuffer<N>,
+    _owned: PhantomData<Thin<'erased_data, V, C>>,
+}
+impl<'erased_data, V, C, const N: usize> ThinInline<'erased_data, V, C, N>
+where
+    V: ThinTrait<C> + ?Sized,
+{
+    /// The alignment of the buffer, objects with a larger alignment can't be
+    /// stored inline.
+    pub const ALIGN: usize = mem::align_of::<InlineBuffer<N>>();
+
+    /// Returns `true` if a [`RawThin`] value with this layout fits in the
+    /// buffer.
+    const fn fits(raw: Layout) -> bool {
+        raw.size() <= N && raw.align() <= Self::ALIGN
+    }
+
+    /// Store an object and its common data inline. Fails to compile if the
+    /// object doesn't fit in the buffer.
+    pub fn new<T>(x: T, common: C) -> Self
+    where
+        T: auto_traits::HasAutoTraits<V::AutoTraitConfig> + 'erased_data,
+        V::VTable: GetThinTraitVTable<T>,
+    {
+        let () = AssertFits::<'erased_data, V, C, T, N>::OK;
+        match Self::try_new(x, common) {
+            Ok(this) => this,
+            Err(_) => unreachable!("the size of the object was checked at compile time"),
+        }
+    }
+    /// Store an object and its common data inline. The object and the common
+    /// data are given back if they don't fit in the buffer.
+    pub fn try_new<T>(x: T, common: C) -> Result<Self, (T, C)>
+    where
+        T: auto_traits::HasAutoTraits<V::AutoTraitConfig> + 'erased_data,
+        V::VTable: GetThinTraitVTable<T>,
+    {
+        let raw = RawThin::<'erased_data, V::VTable, C, _, T> {
+            vtable: <V::VTable as GetThinTraitVTable<T>>::get_vtable().vtable,
+            common,
+            // Safety: the `HasAutoTraits` bound ensures that the object
+            // implements the auto traits of the marker type.
+            _not_send_or_sync: PhantomData::<super::ThinTraitAutoTraitsMarker<V, C>>,
+            _lifetime: PhantomData,
+            _object: x,
+        };
+        if !Self::fits(Layout::for_value(&raw)) {
+            return Err((raw._object, raw.common));
+        }
+        let mut this = Self {
+            buffer: InlineBuffer([MaybeUninit::uninit(); N]),
+            _owned: PhantomData,
+        };
+        // Safety: the value fits in the buffer and the buffer is aligned enough.
+        unsafe { ptr::write(this.buffer.0.as_mut_ptr().cast(), raw) };
+        Ok(this)
+    }
+
+    /// Move the object into a new heap allocation that is owned by a
+    /// [`ThinBox`].
+    #[cfg(feature = "alloc")]
+    #[cfg_attr(feature = "docs", doc(cfg(feature = "alloc")))]
+    pub fn into_box(this: Self) -> ThinBox<'erased_data, V, C> {
+        let mut this = mem::ManuallyDrop::new(this);
+        // Safety: the buffer stores a valid `Thin` value that isn't dropped
+        // since it is wrapped in `ManuallyDrop`.
+        unsafe { ThinBox::move_from_thin(&mut **this) }
+    }
+}
+impl<'erased_data, V, C, const N: usize> Deref for ThinInline<'erased_data, V, C, N>
+where
+    V: ThinTrait<C> + ?Sized,
+{
+    type Target = Thin<'erased_data, V, C>;
+
+    fn deref(&self) -> &Self::Target {
+        // Safety: the buffer starts with a valid `RawThin` value and `Thin` is
+        // a `repr(transparent)` wrapper around it where the object's type is
+        // erased.
+        unsafe { &*(self.buffer.0.as_ptr() as *const Thin<'erased_data, V, C>) }
+    }
+}
+impl<'erased_data, V, C, const N: usize> DerefMut for ThinInline<'erased_data, V, C, N>
+where
+    V: ThinTrait<C> + ?Sized,
+{
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        // Safety: see `Deref`.
+        unsafe { &mut *(self.buffer.0.as_mut_ptr() as *mut Thin<'erased_data, V, C>) }
+    }
+}
+impl<V, C, const N: usize> fmt::Debug for ThinInline<'_, V, C, N>
+where
+    V: ThinTrait<C> + ?Sized,
+    C: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple(get_type_name!(ThinInline))
+            .field(&**self)
+            .finish()
+    }
+}
+impl<V, C, const N: usize> Drop for ThinInline<'_, V, C, N>
+where
+    V: ThinTrait<C> + ?Sized,
+{
+    fn drop(&mut self) {
+        // Safety: the value is never used again and its memory is part of
+        // `self` so it doesn't need to be freed.
+        unsafe { Thin::drop_in_place(&mut **self) };
+    }
+}
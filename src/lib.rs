@@ -54,6 +54,16 @@
 //! assert!(erased.is_equal(2));
 //! ```
 //!
+//! # Cargo features
+//!
+//! - `std` (enabled by default): support for `std` traits such as `UnwindSafe`.
+//!   Enables `alloc`.
+//! - `alloc`: provides [`ThinBox`] and the other types that store type erased
+//!   objects in heap allocations. Without it the crate doesn't need a global
+//!   allocator and only [`Thin`], [`ThinWithoutCommon`], [`OwnedThin`] and
+//!   [`ThinInline`] can be used.
+//! - `macros`: provides the `#[thin_trait_object]` attribute macro.
+//!
 //! # License
 //!
 //! This project is released under either:
@@ -78,10 +88,19 @@
 ))]
 #![allow(clippy::type_complexity)]
 
+#[cfg(feature = "alloc")]
 extern crate alloc;
+#[cfg(feature = "alloc")]
 use alloc::boxed::Box;
 
+#[cfg(feature = "alloc")]
+use allocator::{AllocError, Allocator, Global};
+
+#[cfg(feature = "alloc")]
+use core::mem::MaybeUninit;
 use core::{
+    alloc::Layout,
+    any::TypeId,
     fmt,
     marker::PhantomData,
     mem,
@@ -90,16 +109,17 @@ use core::{
     ptr::{self, NonNull},
 };
 
-#[cfg(test)]
+#[cfg(all(test, feature = "alloc"))]
 mod tests;
 
 pub mod prelude {
     //! This contains the items you would normally want when using a thin pointer.
 
     #[doc(inline)]
-    pub use super::{
-        define_v_table, OwnedThin, Thin, ThinBox, ThinBoxWithoutCommon, ThinWithoutCommon,
-    };
+    pub use super::{define_v_table, OwnedThin, Thin, ThinClone, ThinWithoutCommon};
+    #[cfg(feature = "alloc")]
+    #[doc(inline)]
+    pub use super::{ThinArc, ThinBox, ThinBoxWithoutCommon, ThinRc};
 }
 
 macro_rules! get_type_name {
@@ -110,6 +130,90 @@ macro_rules! get_type_name {
     }};
 }
 
+// Not public API.
+//
+// Expands to the first block if this crate was built with the `alloc` feature,
+// otherwise to the second block. The `define_v_table` macro is expanded in
+// other crates so it can't use `cfg` attributes to check this crate's features.
+#[cfg(feature = "alloc")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __if_alloc {
+    ({ $($alloc:tt)* } else { $($no_alloc:tt)* }) => {
+        $($alloc)*
+    };
+}
+#[cfg(not(feature = "alloc"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __if_alloc {
+    ({ $($alloc:tt)* } else { $($no_alloc:tt)* }) => {
+        $($no_alloc)*
+    };
+}
+
+#[cfg(feature = "alloc")]
+#[cfg_attr(feature = "docs", doc(cfg(feature = "alloc")))]
+pub mod allocator;
+#[cfg(feature = "alloc")]
+#[cfg_attr(feature = "docs", doc(cfg(feature = "alloc")))]
+pub mod arena;
+#[cfg(feature = "alloc")]
+#[cfg_attr(feature = "docs", doc(cfg(feature = "alloc")))]
+pub mod atomic;
+pub mod c_header;
+pub mod inline;
+#[cfg(feature = "alloc")]
+#[cfg_attr(feature = "docs", doc(cfg(feature = "alloc")))]
+pub mod rc;
+#[cfg(feature = "alloc")]
+#[cfg_attr(feature = "docs", doc(cfg(feature = "alloc")))]
+pub mod slice;
+#[cfg(feature = "alloc")]
+#[cfg_attr(feature = "docs", doc(cfg(feature = "alloc")))]
+pub mod small;
+#[cfg(feature = "alloc")]
+#[cfg_attr(feature = "docs", doc(cfg(feature = "alloc")))]
+pub mod sync;
+#[cfg(feature = "alloc")]
+#[cfg_attr(feature = "docs", doc(cfg(feature = "alloc")))]
+pub mod tagged;
+#[cfg(feature = "alloc")]
+#[cfg_attr(feature = "docs", doc(cfg(feature = "alloc")))]
+pub mod vec;
+
+#[cfg(feature = "macros")]
+#[cfg_attr(feature = "docs", doc(cfg(feature = "macros")))]
+#[doc(inline)]
+pub use thin_trait_object_macros::thin_trait_object;
+
+#[cfg(feature = "alloc")]
+#[doc(inline)]
+pub use arena::ThinArena;
+#[cfg(feature = "alloc")]
+#[doc(inline)]
+pub use atomic::{AtomicOptionThinBox, AtomicThinBox};
+#[doc(inline)]
+pub use inline::ThinInline;
+#[cfg(feature = "alloc")]
+#[doc(inline)]
+pub use rc::ThinRc;
+#[cfg(feature = "alloc")]
+#[doc(inline)]
+pub use slice::ThinSlice;
+#[cfg(feature = "alloc")]
+#[doc(inline)]
+pub use small::ThinSmallBox;
+#[cfg(feature = "alloc")]
+#[doc(inline)]
+pub use sync::ThinArc;
+#[cfg(feature = "alloc")]
+#[doc(inline)]
+pub use tagged::TaggedThinBox;
+#[cfg(feature = "alloc")]
+#[doc(inline)]
+pub use vec::ThinVec;
+
 // Not public API.
 //
 // The parsing in this macro was inspired by code from the `pin-project-lite`
@@ -120,9 +224,187 @@ macro_rules! get_type_name {
 #[macro_export]
 macro_rules! __define_v_table_internal {
     ////////////////////////////////////////////////////////////////////////////////
-    // The entry point of the macro.
+    // The entry point of the macro. Removes the `#[thin_supertraits(..)]` and
+    // `#[repr(C)]` attributes and the `extern "C"` ABI from the trait definition
+    // and then emits it.
+    ////////////////////////////////////////////////////////////////////////////////
+    (@find_supertraits
+        attributes = $attributes:tt,
+        supertraits = { $($supertraits:tt)* },
+        abi = $abi:tt,
+        unchecked = {
+            #[thin_supertraits( $($supertrait:path),* $(,)? )]
+            $($rest:tt)*
+        },
+    ) => {
+        $crate::__define_v_table_internal! {@find_supertraits
+            attributes = $attributes,
+            supertraits = { $($supertraits)* $($supertrait,)* },
+            abi = $abi,
+            unchecked = { $($rest)* },
+        }
+    };
+    // The vtable always has a C compatible layout:
+    (@find_supertraits
+        attributes = $attributes:tt,
+        supertraits = $supertraits:tt,
+        abi = $abi:tt,
+        unchecked = {
+            #[repr(C)]
+            $($rest:tt)*
+        },
+    ) => {
+        $crate::__define_v_table_internal! {@find_supertraits
+            attributes = $attributes,
+            supertraits = $supertraits,
+            abi = $abi,
+            unchecked = { $($rest)* },
+        }
+    };
+    (@find_supertraits
+        attributes = { $($attributes:tt)* },
+        supertraits = $supertraits:tt,
+        abi = $abi:tt,
+        unchecked = {
+            #[$($attribute:tt)*]
+            $($rest:tt)*
+        },
+    ) => {
+        $crate::__define_v_table_internal! {@find_supertraits
+            attributes = { $($attributes)* #[$($attribute)*] },
+            supertraits = $supertraits,
+            abi = $abi,
+            unchecked = { $($rest)* },
+        }
+    };
+    // The ABI of the vtable's function pointers:
+    (@find_supertraits
+        attributes = $attributes:tt,
+        supertraits = $supertraits:tt,
+        abi = {},
+        unchecked = {
+            $visibility:vis $(unsafe $(;;; $is_unsafe_trait:ident)?)? extern $abi:literal trait
+            $($rest:tt)*
+        },
+    ) => {
+        $crate::__define_v_table_internal! {@find_supertraits
+            attributes = $attributes,
+            supertraits = $supertraits,
+            abi = { extern $abi },
+            unchecked = {
+                $visibility $(unsafe $($is_unsafe_trait)?)? trait
+                $($rest)*
+            },
+        }
+    };
+    (@find_supertraits
+        attributes = { $($attributes:tt)* },
+        supertraits = $supertraits:tt,
+        abi = $abi:tt,
+        unchecked = { $($rest:tt)* },
+    ) => {
+        $($attributes)*
+        $($rest)*
+        $crate::__define_v_table_internal! {@find_thin_clone
+            depth = {},
+            unchecked = { $($rest)* },
+            input = {
+                supertraits = $supertraits,
+                abi = $abi,
+                $($attributes)*
+                $($rest)*
+            },
+        }
+    };
+    ////////////////////////////////////////////////////////////////////////////////
+    // Checks if `ThinClone` is used as a supertrait
+    // before the trait definition is parsed (paths can't be inspected after they
+    // have been parsed).
+    ////////////////////////////////////////////////////////////////////////////////
+    (@find_thin_clone
+        depth = {},
+        unchecked = { ThinClone $($rest:tt)* },
+        input = { $($input:tt)* },
+    ) => {
+        $crate::__define_v_table_internal! {@input
+            thin_clone = { true },
+            $($input)*
+        }
+    };
+    // Generic parameters of the trait or of a supertrait, ignore `ThinClone`
+    // until the closing angle bracket:
+    (@find_thin_clone
+        depth = { $($depth:tt)* },
+        unchecked = { < $($rest:tt)* },
+        input = $input:tt,
+    ) => {
+        $crate::__define_v_table_internal! {@find_thin_clone
+            depth = { < $($depth)* },
+            unchecked = { $($rest)* },
+            input = $input,
+        }
+    };
+    (@find_thin_clone
+        depth = { < $($depth:tt)* },
+        unchecked = { > $($rest:tt)* },
+        input = $input:tt,
+    ) => {
+        $crate::__define_v_table_internal! {@find_thin_clone
+            depth = { $($depth)* },
+            unchecked = { $($rest)* },
+            input = $input,
+        }
+    };
+    (@find_thin_clone
+        depth = { < < $($depth:tt)* },
+        unchecked = { >> $($rest:tt)* },
+        input = $input:tt,
+    ) => {
+        $crate::__define_v_table_internal! {@find_thin_clone
+            depth = { $($depth)* },
+            unchecked = { $($rest)* },
+            input = $input,
+        }
+    };
+    // Supertraits can't be declared after the where clause:
+    (@find_thin_clone
+        depth = {},
+        unchecked = { where $($rest:tt)* },
+        input = { $($input:tt)* },
+    ) => {
+        $crate::__define_v_table_internal! {@input
+            thin_clone = {},
+            $($input)*
+        }
+    };
+    (@find_thin_clone
+        depth = $depth:tt,
+        unchecked = { $token:tt $($rest:tt)* },
+        input = $input:tt,
+    ) => {
+        $crate::__define_v_table_internal! {@find_thin_clone
+            depth = $depth,
+            unchecked = { $($rest)* },
+            input = $input,
+        }
+    };
+    (@find_thin_clone
+        depth = $depth:tt,
+        unchecked = {},
+        input = { $($input:tt)* },
+    ) => {
+        $crate::__define_v_table_internal! {@input
+            thin_clone = {},
+            $($input)*
+        }
+    };
+    ////////////////////////////////////////////////////////////////////////////////
+    // Parse the trait definition.
     ////////////////////////////////////////////////////////////////////////////////
     (@input
+        thin_clone = $thin_clone:tt,
+        supertraits = $supertraits:tt,
+        abi = $abi:tt,
         $(#[$trait_attr:meta])*
         $visibility:vis $(unsafe $(;;; $is_unsafe_trait:ident)?)? trait $trait_name:ident
         $(<
@@ -174,16 +456,191 @@ macro_rules! __define_v_table_internal {
                     ,)*
                 )? {}
             },
+            thin_clone = $thin_clone,
+            supertraits = $supertraits,
+            abi = $abi,
             unparsed_items = { $($trait_items)* },
+            item_cfg = {},
+            sized_fns = {},
             parsed_fns = {},
             parsed_associated_types = {},
         }
     };
     ////////////////////////////////////////////////////////////////////////////////
+    // Parse: attributes of a trait item, `#[cfg]` attributes are forwarded to the
+    // code that is generated for a method and other attributes are ignored
+    ////////////////////////////////////////////////////////////////////////////////
+    (@parse_items
+        trait_def = $trait_def:tt,
+        thin_clone = $thin_clone:tt,
+        supertraits = $supertraits:tt,
+        abi = $abi:tt,
+        unparsed_items = {
+            #[cfg $($cfg:tt)*]
+            // Next trait item:
+            $($unparsed_rest:tt)*
+        },
+        item_cfg = { $($item_cfg:tt)* },
+        sized_fns = $sized_fns:tt,
+        parsed_fns = $parsed_fns:tt,
+        parsed_associated_types = $parsed_types:tt $(,)?
+    ) => {
+        $crate::__define_v_table_internal! {@parse_items
+            trait_def = $trait_def,
+            thin_clone = $thin_clone,
+            supertraits = $supertraits,
+            abi = $abi,
+            unparsed_items = { $($unparsed_rest)* },
+            item_cfg = { $($item_cfg)* #[cfg $($cfg)*] },
+            sized_fns = $sized_fns,
+            parsed_fns = $parsed_fns,
+            parsed_associated_types = $parsed_types,
+        }
+    };
+    (@parse_items
+        trait_def = $trait_def:tt,
+        thin_clone = $thin_clone:tt,
+        supertraits = $supertraits:tt,
+        abi = $abi:tt,
+        unparsed_items = {
+            #[$attr:meta]
+            // Next trait item:
+            $($unparsed_rest:tt)*
+        },
+        item_cfg = $item_cfg:tt,
+        sized_fns = $sized_fns:tt,
+        parsed_fns = $parsed_fns:tt,
+        parsed_associated_types = $parsed_types:tt $(,)?
+    ) => {
+        $crate::__define_v_table_internal! {@parse_items
+            trait_def = $trait_def,
+            thin_clone = $thin_clone,
+            supertraits = $supertraits,
+            abi = $abi,
+            unparsed_items = { $($unparsed_rest)* },
+            item_cfg = $item_cfg,
+            sized_fns = $sized_fns,
+            parsed_fns = $parsed_fns,
+            parsed_associated_types = $parsed_types,
+        }
+    };
+    ////////////////////////////////////////////////////////////////////////////////
+    // Parse: trait method that requires `Self: Sized` (not part of the vtable)
+    ////////////////////////////////////////////////////////////////////////////////
+    (@parse_items
+        trait_def = $trait_def:tt,
+        thin_clone = $thin_clone:tt,
+        supertraits = $supertraits:tt,
+        abi = $abi:tt,
+        unparsed_items = {
+            // Attributes:
+            $(#[$attr:meta])*
+            // Method definition:
+            $(unsafe $(;;; $is_unsafe:ident)?)? fn $method_name:ident
+            // Lifetime and type parameters:
+            $(<
+                $( $lifetime:lifetime $(: $lifetime_bound:lifetime)? ),* $(,)?
+                $( $generics:ident $(: $($(+)? $generics_bound:path)+ )? ),* $(,)?
+            >)?
+            // Parameters (these aren't inspected since the method isn't part of the vtable):
+            ( $($args:tt)* )
+            // Return type
+            $(-> $return_type:ty)?
+            // Where clause (must start with `Self: Sized`):
+            where Self: Sized
+            $(, $where_clause_ty:ty $(: $($(+)? $where_clause_bound:path)+ )? )* $(,)?
+            // End token:
+            ;
+            // Next trait item:
+            $($unparsed_rest:tt)*
+        },
+        item_cfg = { $($item_cfg:tt)* },
+        sized_fns = { $($sized:tt)* },
+        parsed_fns = $parsed_fns:tt,
+        parsed_associated_types = $parsed_types:tt $(,)?
+    ) => {
+        $crate::__define_v_table_internal! {@parse_items
+            trait_def = $trait_def,
+            thin_clone = $thin_clone,
+            supertraits = $supertraits,
+            abi = $abi,
+            unparsed_items = { $($unparsed_rest)* },
+            item_cfg = {},
+            sized_fns = { $($sized)* {
+                cfg = { $($item_cfg)* },
+                signature = {
+                    $(unsafe $(;;; $is_unsafe)?)? fn $method_name
+                    $(<
+                        $( $lifetime $(: $lifetime_bound)? ,)*
+                        $( $generics $(: $($generics_bound +)+ )? ,)*
+                    >)?
+                    ( $($args)* )
+                    $(-> $return_type)?
+                    where Self: Sized
+                    $(, $where_clause_ty $(: $($where_clause_bound +)+ )? )*
+                },
+            }},
+            parsed_fns = $parsed_fns,
+            parsed_associated_types = $parsed_types,
+        }
+    };
+    ////////////////////////////////////////////////////////////////////////////////
+    // Parse: trait method that requires `Self: Sized` with default implementation
+    ////////////////////////////////////////////////////////////////////////////////
+    (@parse_items
+        trait_def = $trait_def:tt,
+        thin_clone = $thin_clone:tt,
+        supertraits = $supertraits:tt,
+        abi = $abi:tt,
+        unparsed_items = {
+            // Attributes:
+            $(#[$attr:meta])*
+            // Method definition:
+            $(unsafe $(;;; $is_unsafe:ident)?)? fn $method_name:ident
+            // Lifetime and type parameters:
+            $(<
+                $( $lifetime:lifetime $(: $lifetime_bound:lifetime)? ),* $(,)?
+                $( $generics:ident $(: $($(+)? $generics_bound:path)+ )? ),* $(,)?
+            >)?
+            // Parameters:
+            ( $($args:tt)* )
+            // Return type
+            $(-> $return_type:ty)?
+            // Where clause (must start with `Self: Sized`):
+            where Self: Sized
+            $(, $where_clause_ty:ty $(: $($(+)? $where_clause_bound:path)+ )? )* $(,)?
+            // Default method implementation:
+            { $($default_impl:tt)* }
+            // Next trait item:
+            $($unparsed_rest:tt)*
+        },
+        item_cfg = $item_cfg:tt,
+        sized_fns = $sized_fns:tt,
+        parsed_fns = $parsed_fns:tt,
+        parsed_associated_types = $parsed_types:tt $(,)?
+    ) => {
+        // The default implementation is used by all generated trait implementations
+        // so there is nothing to remember about this method.
+        $crate::__define_v_table_internal! {@parse_items
+            trait_def = $trait_def,
+            thin_clone = $thin_clone,
+            supertraits = $supertraits,
+            abi = $abi,
+            unparsed_items = { $($unparsed_rest)* },
+            item_cfg = {},
+            sized_fns = $sized_fns,
+            parsed_fns = $parsed_fns,
+            parsed_associated_types = $parsed_types,
+        }
+    };
+    ////////////////////////////////////////////////////////////////////////////////
     // Parse: trait method
     ////////////////////////////////////////////////////////////////////////////////
     (@parse_items
         trait_def = $trait_def:tt,
+        thin_clone = $thin_clone:tt,
+        supertraits = $supertraits:tt,
+        abi = $abi:tt,
         unparsed_items = {
             // Attributes:
             $(#[$attr:meta])*
@@ -219,13 +676,21 @@ macro_rules! __define_v_table_internal {
             // Next trait item:
             $($unparsed_rest:tt)*
         },
+        item_cfg = { $($item_cfg:tt)* },
+        sized_fns = $sized_fns:tt,
         parsed_fns = { $($parsed:tt)* },
         parsed_associated_types = $parsed_types:tt $(,)?
     ) => {
         $crate::__define_v_table_internal! {@parse_items
             trait_def = $trait_def,
+            thin_clone = $thin_clone,
+            supertraits = $supertraits,
+            abi = $abi,
             unparsed_items = { $($unparsed_rest)* },
+            item_cfg = {},
+            sized_fns = $sized_fns,
             parsed_fns = { $($parsed)* {
+                cfg = { $($item_cfg)* },
                 is_unsafe = {  $(unsafe $(;;; $is_unsafe)?)?  },
                 method_name = { $method_name },
                 lifetimes_parameters = {  $($($lifetime,)*)?  },
@@ -274,6 +739,9 @@ macro_rules! __define_v_table_internal {
     ////////////////////////////////////////////////////////////////////////////////
     (@parse_items
         trait_def = $trait_def:tt,
+        thin_clone = $thin_clone:tt,
+        supertraits = $supertraits:tt,
+        abi = $abi:tt,
         unparsed_items = {
             // Attributes:
             $(#[$attr:meta])*
@@ -309,13 +777,21 @@ macro_rules! __define_v_table_internal {
             // Next trait item:
             $($unparsed_rest:tt)*
         },
+        item_cfg = { $($item_cfg:tt)* },
+        sized_fns = $sized_fns:tt,
         parsed_fns = { $($parsed:tt)* },
         parsed_associated_types = $parsed_types:tt $(,)?
     ) => {
         $crate::__define_v_table_internal! {@parse_items
             trait_def = $trait_def,
+            thin_clone = $thin_clone,
+            supertraits = $supertraits,
+            abi = $abi,
             unparsed_items = { $($unparsed_rest)* },
+            item_cfg = {},
+            sized_fns = $sized_fns,
             parsed_fns = { $($parsed)* {
+                cfg = { $($item_cfg)* },
                 is_unsafe = {  $(unsafe $(;;; $is_unsafe)?)?  },
                 method_name = { $method_name },
                 lifetimes_parameters = {  $($($lifetime,)*)?  },
@@ -376,8 +852,8 @@ macro_rules! __define_v_table_internal {
                 ,)*
             >)?
             $(:
-                $($(+)? $super_lifetime_bound:lifetime )*
-                $($(+)? $super_bound:path )*
+                $(+ $super_lifetime_bound:lifetime )*
+                $(+ $super_bound:path )*
             )?
             $(where
                 $( $where_clause_ty:ty
@@ -388,6 +864,9 @@ macro_rules! __define_v_table_internal {
             )?
             {}
         },
+        thin_clone = $thin_clone:tt,
+        supertraits = $supertraits:tt,
+        abi = $abi:tt,
         unparsed_items = {
             // Attributes:
             $(#[$attr:meta])*
@@ -403,6 +882,8 @@ macro_rules! __define_v_table_internal {
             // Next trait item:
             $($unparsed_rest:tt)*
         },
+        item_cfg = {},
+        sized_fns = $sized_fns:tt,
         parsed_fns = $parsed_fns:tt,
         parsed_associated_types = { $($parsed:tt)* } $(,)?
     ) => {
@@ -431,7 +912,12 @@ macro_rules! __define_v_table_internal {
                     ,)*
                 )? {}
             },
+            thin_clone = $thin_clone,
+            supertraits = $supertraits,
+            abi = $abi,
             unparsed_items = { $($unparsed_rest)* },
+            item_cfg = {},
+            sized_fns = $sized_fns,
             parsed_fns = $parsed_fns,
             parsed_associated_types = { $($parsed)* {
                 name = { $name },
@@ -440,17 +926,43 @@ macro_rules! __define_v_table_internal {
             }},
         }
     };
+    // Associated types are used by all of the generated code so they can't be
+    // conditionally compiled:
+    (@parse_items
+        trait_def = $trait_def:tt,
+        thin_clone = $thin_clone:tt,
+        supertraits = $supertraits:tt,
+        abi = $abi:tt,
+        unparsed_items = {
+            type $($unparsed_rest:tt)*
+        },
+        item_cfg = { $($item_cfg:tt)+ },
+        sized_fns = $sized_fns:tt,
+        parsed_fns = $parsed_fns:tt,
+        parsed_associated_types = $parsed_types:tt $(,)?
+    ) => {
+        ::core::compile_error!("`#[cfg]` attributes are only supported on methods");
+    };
     ////////////////////////////////////////////////////////////////////////////////
     // Parse: base case, all items parsed
     ////////////////////////////////////////////////////////////////////////////////
     (@parse_items
         trait_def = $trait_def:tt,
+        thin_clone = $thin_clone:tt,
+        supertraits = $supertraits:tt,
+        abi = $abi:tt,
         unparsed_items = {},
+        item_cfg = {},
+        sized_fns = $sized_fns:tt,
         parsed_fns = $parsed_fns:tt,
         parsed_associated_types = $parsed_types:tt $(,)?
     ) => {
         $crate::__define_v_table_internal! {@generate_code
             trait_def = $trait_def,
+            thin_clone = $thin_clone,
+            supertraits = $supertraits,
+            abi = $abi,
+            sized_fns = $sized_fns,
             parsed_fns = $parsed_fns,
             parsed_associated_types = $parsed_types,
         }
@@ -472,8 +984,8 @@ macro_rules! __define_v_table_internal {
                 ,)*
             >)?
             $(:
-                $($(+)? $super_lifetime_bound:lifetime )*
-                $($(+)? $super_bound:path )*
+                $(+ $super_lifetime_bound:lifetime )*
+                $(+ $super_bound:path )*
             )?
             $(where
                 $( $where_clause_ty:ty
@@ -484,7 +996,15 @@ macro_rules! __define_v_table_internal {
             )?
             {}
         },
+        thin_clone = { $(true $(;;; $is_thin_clone:ident)?)? },
+        supertraits = { $($forwarded_supertrait:path,)* },
+        abi = $abi:tt,
+        sized_fns = { $({
+            cfg = {  $($sized_fn_cfg:tt)*  },
+            signature = {  $($sized_fn_signature:tt)*  },
+        })* },
         parsed_fns = { $({
+            cfg = {  $($method_cfg:tt)*  },
             is_unsafe = {  $(unsafe $(;;; $method_is_unsafe:ident)?)?  },
             method_name = { $method_name:ident },
             lifetimes_parameters = { $($method_lifetime_parameter:lifetime),* $(,)? },
@@ -506,6 +1026,9 @@ macro_rules! __define_v_table_internal {
         const _: fn() = || {
             // VTable type definition:
             #[allow(explicit_outlives_requirements)]
+            // `repr(C)` so that the vtable of the first forwarded supertrait is stored
+            // at the start of this vtable (see `VTablePrefix`):
+            #[repr(C)]
             $visibility struct __VTable
             <
                 $(
@@ -520,30 +1043,66 @@ macro_rules! __define_v_table_internal {
                 __CommonData,
             >
             where
-                Self: $($($super_lifetime_bound +)* $($super_bound +)*)? ::core::marker::Sized,
+                // Supertraits that aren't auto traits might be forwarded through this vtable so
+                // they are only required by the trait implementation below.
+                Self: $($($super_lifetime_bound +)*)? ::core::marker::Sized,
             $(
-                $( $where_clause_ty:ty
-                    $(: $where_clause_bound:path)?
-                    $(: ?$where_clause_unsized_bound:path)?
-                    $(: $where_clause_lifetime_bound:lifetime)?
+                $( $where_clause_ty
+                    $(: $where_clause_bound)?
+                    $(: ?$where_clause_unsized_bound)?
+                    $(: $where_clause_lifetime_bound)?
                 ),*
             )?
             {
+                // The vtables of supertraits listed in `#[thin_supertraits(..)]`:
+                __supertraits: $crate::__define_v_table_internal!{@supertrait_vtables_type
+                    $($forwarded_supertrait,)*
+                },
+                // The trait's methods in the order they were declared, followed by
+                // `__drop_in_place` and `__clone` all use the ABI of the vtable:
                 $(
-                    $method_name: for<'__this, $( $method_lifetime_parameter ),* > fn(
+                    $($method_cfg)*
+                    $method_name: $crate::__define_v_table_internal!{@abi_fn_ptr $abi for<'__this, $( $method_lifetime_parameter ),* > fn(
                         // Self type:
                         $crate::__define_v_table_internal!{@if ($(true$(;;;$method_is_ref:ident)?)?false)
                             // Self is a reference:
                             {$(&  $($method_self_life)? )?  $(mut $(;;; $method_self_is_mut_ref)?)?  $crate::RawThin<'__this, Self, $crate::Split<__CommonData>, $crate::auto_traits::NoAutoTraits, ()>}
                             else
                             // Self is taken by value:
-                            {$crate::RawThinBox<'__this, Self, $crate::Taken<__CommonData>, $crate::auto_traits::NoAutoTraits, ()>}
+                            {$crate::__private::ErasedBox<'__this, Self, __CommonData>}
                         },
                         // Args:
                         $($method_arg_ty),*
-                    ) $(-> $return_type)?,
+                    ) $(-> $return_type)?},
                 )*
-                __drop: for<'__this> fn($crate::RawThinBox<'__this, Self,  $crate::Taken<__CommonData>, $crate::auto_traits::NoAutoTraits, ()>),
+                __drop_in_place: $crate::__define_v_table_internal!{@abi_fn_ptr $abi for<'__this> fn(&mut $crate::RawThin<'__this, Self, $crate::Split<__CommonData>, $crate::auto_traits::NoAutoTraits, ()>)},
+                $(
+                    __clone: $crate::__define_v_table_internal!{@abi_fn_ptr $abi for<'__this> fn(&$crate::RawThin<'__this, Self, $crate::Split<__CommonData>, $crate::auto_traits::NoAutoTraits, ()>) -> $crate::__private::ErasedBox<'__this, Self, __CommonData>},
+                    $(;;; $is_thin_clone)?
+                )?
+                // The remaining fields are only used from Rust:
+                __layout: fn() -> ::core::alloc::Layout,
+                __type_id: fn() -> ::core::any::TypeId,
+                __type_name: fn() -> &'static str,
+                // Rebuild ordinary trait objects from type erased objects. `__as_dyn` takes a
+                // pointer to the object itself so that it can be used for objects that aren't
+                // stored right after the vtable pointer (the lifetime is chosen by `ThinTraitDyn`):
+                __as_dyn: fn(*mut u8) -> *mut (
+                    dyn $trait_name
+                        <
+                            $(  $($lifetime,)* $($generics,)*  )?
+                            $($associated_type_name = $associated_type_name, )*
+                        >
+                        + 'static
+                ),
+                __into_box_dyn: for<'__this> fn($crate::__private::ErasedBox<'__this, Self, __CommonData>) -> *mut (
+                    dyn $trait_name
+                        <
+                            $(  $($lifetime,)* $($generics,)*  )?
+                            $($associated_type_name = $associated_type_name, )*
+                        >
+                        + '__this
+                ),
 
                 // Generic or lifetimes might not be used by methods. This is allowed in traits but not in structs.
                 // This marker ensures that the type and lifetime parameters are used without affecting the auto
@@ -580,19 +1139,34 @@ macro_rules! __define_v_table_internal {
             $trait_name<$(  $($lifetime,)* $($generics,)*  )?>
             for
             __VTable<$(  $($lifetime,)* $($generics,)*  )?  $($associated_type_name,)*  __CommonData,>
-            $(
             where
-                $( $where_clause_ty:ty
-                    $(: $where_clause_bound:path)?
-                    $(: ?$where_clause_unsized_bound:path)?
-                    $(: $where_clause_lifetime_bound:lifetime)?
-                ),* $(,)?
+                // The vtable doesn't implement forwarded supertraits so this impl doesn't apply
+                // if there are any, which means that methods that name associated types via
+                // `Self` aren't supported in that case.
+                $(Self: $forwarded_supertrait,)*
+            $(
+                $( $where_clause_ty
+                    $(: $where_clause_bound)?
+                    $(: ?$where_clause_unsized_bound)?
+                    $(: $where_clause_lifetime_bound)?
+                ),*
             )?
             {
                 $(
                     type $associated_type_name = $associated_type_name;
                 )*
                 $(
+                    #[allow(unused_variables)]
+                    $($sized_fn_cfg)*
+                    $($sized_fn_signature)*
+                    {
+                        // Methods that require `Self: Sized` have no default implementation
+                        // and so must be implemented even though they are never used.
+                        ::core::unimplemented!()
+                    }
+                )*
+                $(
+                    $($method_cfg)*
                     $($method_signature)*
                     {
                         // We only implement the trait to support naming associated types.
@@ -627,11 +1201,11 @@ macro_rules! __define_v_table_internal {
             where
                 __T: $trait_name<$(  $($lifetime,)* $($generics,)*  )?  $($associated_type_name = $associated_type_name,)*  > + ?::core::marker::Sized,
             $(
-                $( $where_clause_ty:ty
-                    $(: $where_clause_bound:path)?
-                    $(: ?$where_clause_unsized_bound:path)?
-                    $(: $where_clause_lifetime_bound:lifetime)?
-                ),* $(,)?
+                $( $where_clause_ty
+                    $(: $where_clause_bound)?
+                    $(: ?$where_clause_unsized_bound)?
+                    $(: $where_clause_lifetime_bound)?
+                ),*
             )?
             {}
 
@@ -657,11 +1231,11 @@ macro_rules! __define_v_table_internal {
             __VTable<$(  $($lifetime,)* $($generics,)*  )?  $($associated_type_name,)*  __CommonData,>
             $(
             where
-                $( $where_clause_ty:ty
-                    $(: $where_clause_bound:path)?
-                    $(: ?$where_clause_unsized_bound:path)?
-                    $(: $where_clause_lifetime_bound:lifetime)?
-                ),* $(,)?
+                $( $where_clause_ty
+                    $(: $where_clause_bound)?
+                    $(: ?$where_clause_unsized_bound)?
+                    $(: $where_clause_lifetime_bound)?
+                ),*
             )?
             {
                 type UncheckedAutoTraitMarker = dyn $trait_name
@@ -671,9 +1245,11 @@ macro_rules! __define_v_table_internal {
                 >;
             }
 
-            // impl `GetThinTraitVTable` for all types that implement the trait:
+            // impl `VTableOf` for `dyn Trait`:
+            // Used to name this vtable when the trait is forwarded as a supertrait.
             impl
             <
+                '__a,
                 $(
                     $( $lifetime $(: $lifetime_bound)? ,)*
                     $( $generics
@@ -684,71 +1260,33 @@ macro_rules! __define_v_table_internal {
                 )?
                 $($associated_type_name $(: $($associated_type_life_bound+)*  $( $associated_type_trait_bound+)*)? ,)*
                 __CommonData,
-                __T,
             >
-            $crate::GetThinTraitVTable<__T>
-            for __VTable<$(  $($lifetime,)* $($generics,)*  )?  $($associated_type_name,)*  __CommonData>
+            $crate::__private::VTableOf<__CommonData>
+            for
+            (
+                dyn $trait_name
+                <
+                    $(  $($lifetime,)* $($generics,)*  )?
+                    $($associated_type_name = $associated_type_name, )*
+                >
+                + '__a
+            )
             where
-                __T: $trait_name<$(  $($lifetime,)* $($generics,)*  )?  $($associated_type_name = $associated_type_name,)*  >,
+                __VTable<$(  $($lifetime,)* $($generics,)*  )?  $($associated_type_name,)*  __CommonData>: $($($super_lifetime_bound +)*)?,
             $(
-                $( $where_clause_ty:ty
-                    $(: $where_clause_bound:path)?
-                    $(: ?$where_clause_unsized_bound:path)?
-                    $(: $where_clause_lifetime_bound:lifetime)?
-                ),* $(,)?
+                $( $where_clause_ty
+                    $(: $where_clause_bound)?
+                    $(: ?$where_clause_unsized_bound)?
+                    $(: $where_clause_lifetime_bound)?
+                ),*
             )?
             {
-                // This warning can happen if a method is unsafe (then any unsafe uses inside it becomes unnecessary).
-                #[allow(unused_unsafe, clippy::needless_arbitrary_self_type)]
-                fn get_vtable() -> $crate::VTable<Self, __T> {
-                    let get_vtable = || -> &_ {
-                        let vtable: &__VTable<$(  $($lifetime,)* $($generics,)*  )?  $($associated_type_name,)*  __CommonData> = $crate::__define_v_table_internal! {
-                            @create_vtable
-                            parsed_fns = {
-                                $({
-                                    is_unsafe = {  $(unsafe $(;;; $method_is_unsafe:ident)?)?  },
-                                    method_name = { $method_name },
-                                    lifetimes_parameters = { $($method_lifetime_parameter,)* },
-                                    arguments = { $(  $method_arg_name: $method_arg_ty,  )* },
-                                    return_type = {  $($return_type)?  },
-                                    self_ident = {  $method_self_ident  },
-                                    self_type = {
-                                        $( &$($method_self_life)? )? $(mut $(;;; $method_self_is_mut_ref)?)? self
-                                    },
-                                    signature = {  $($method_signature)*  },
-                                })*
-                            },
-                            vtable_methods = {},
-                            common_info = {
-                                trait_name = $trait_name,
-                                trait_lifetime = $($($lifetime),*)?,
-                                trait_generics = $($($generics),*)?,
-                                // Don't need associated types since
-                                // `<Self as Trait<generics>>::method` is already fully
-                                // resolved (associated types are deduced from self type).
-                            },
-                            vtable_info = {
-                                erased_type = __T,
-                                vtable_name = __VTable,
-                            },
-                        };
-                        // We are returning a reference to a local variable from a function. If the
-                        // borrow checker allows this then it should be because the vtable was
-                        // promoted to a `'static` reference.
-                        vtable
-                    };
-                    let vtable = get_vtable();
-                    // Safety: this vtable was specifically constructed for the type `__T` so
-                    // it will behave sensibly for that type. Our vtable also only contains
-                    // methods and all of them take the type `__T` as inputs so the vtable
-                    // can't outlive any lifetime requirements of the type `__T`.
-                    unsafe { $crate::VTable::new(vtable) }
-                }
+                type VTable = __VTable<$(  $($lifetime,)* $($generics,)*  )?  $($associated_type_name,)*  __CommonData>;
             }
 
-            // impl `VTableDrop` for __VTable:
-            // Allows the vtable to be used in ThinBox's Drop implementation.
-            impl
+            // impl `VTableUpcast` for __VTable:
+            // A vtable can always be "upcast" to itself.
+            unsafe impl
             <
                 $(
                     $( $lifetime $(: $lifetime_bound)? ,)*
@@ -761,30 +1299,30 @@ macro_rules! __define_v_table_internal {
                 $($associated_type_name $(: $($associated_type_life_bound+)*  $( $associated_type_trait_bound+)*)? ,)*
                 __CommonData,
             >
-            $crate::VTableDrop<__CommonData>
-            for
-            __VTable<$(  $($lifetime,)* $($generics,)*  )?  $($associated_type_name,)*  __CommonData,>
-            $(
+            $crate::VTableUpcast<
+                dyn $trait_name
+                <
+                    $(  $($lifetime,)* $($generics,)*  )?
+                    $($associated_type_name = $associated_type_name, )*
+                >
+            >
+            for __VTable<$(  $($lifetime,)* $($generics,)*  )?  $($associated_type_name,)*  __CommonData>
             where
-                $( $where_clause_ty:ty
-                    $(: $where_clause_bound:path)?
-                    $(: ?$where_clause_unsized_bound:path)?
-                    $(: $where_clause_lifetime_bound:lifetime)?
-                ),* $(,)?
+            $(
+                $( $where_clause_ty
+                    $(: $where_clause_bound)?
+                    $(: ?$where_clause_unsized_bound)?
+                    $(: $where_clause_lifetime_bound)?
+                ),*
             )?
             {
-                unsafe fn drop_erased_box(&self, erased_box: $crate::RawThinBox<'_, Self, $crate::Taken<__CommonData>, $crate::auto_traits::NoAutoTraits, ()>) {
-                    // Forward the call to the vtable drop method:
-                    (self.__drop)(erased_box)
+                type VTable = Self;
+
+                fn upcast_vtable(&self) -> &Self::VTable {
+                    self
                 }
             }
-
-            // impl the user's trait for `ThinWithoutCommon` so that the trait methods can be called
-            // for references to the thin trait object.
-            #[allow(unused_mut, clippy::needless_arbitrary_self_type)]
-            // This warning can happen if a method is unsafe (then any unsafe uses becomes unnecessary).
-            #[allow(unused_unsafe)]
-            $(unsafe $($is_unsafe_trait)?)? impl
+            unsafe impl
             <
                 $(
                     $( $lifetime $(: $lifetime_bound)? ,)*
@@ -796,91 +1334,193 @@ macro_rules! __define_v_table_internal {
                 )?
                 $($associated_type_name $(: $($associated_type_life_bound+)*  $( $associated_type_trait_bound+)*)? ,)*
                 __CommonData,
-                __ThinTrait,
             >
-            $trait_name<$(  $($lifetime,)* $($generics,)*  )?>
-            for
-            $crate::ThinWithoutCommon<'_, __ThinTrait, __CommonData>
-            where
-                // Ensure all required auto traits are implemented (might for example constrain __CommonData):
-                Self: $($($super_lifetime_bound +)* $($super_bound +)*)?,
-                // Ensure the thin trait implementation uses our vtable:
-                __ThinTrait: $crate::ThinTrait<
-                    __CommonData,
-                    VTable =  __VTable<$(  $($lifetime,)* $($generics,)*  )?  $($associated_type_name,)*  __CommonData,>
-                > + ?::core::marker::Sized,
-                // Ensure auto trait config works for our vtable:
+            $crate::VTablePrefix<
+                dyn $trait_name
                 <
-                    __ThinTrait as $crate::ThinTrait<__CommonData>
-                >::AutoTraitConfig: $crate::auto_traits::AutoTraitConfig<
-                    <
-                        __VTable<$(  $($lifetime,)* $($generics,)*  )?  $($associated_type_name,)*  __CommonData,>
-                        as $crate::auto_traits::VTableEnforcedAutoTraits
-                    >::UncheckedAutoTraitMarker
-                >,
-                 __VTable<$(  $($lifetime,)* $($generics,)*  )?  $($associated_type_name,)*  __CommonData,>: $($($super_lifetime_bound +)*)?,
+                    $(  $($lifetime,)* $($generics,)*  )?
+                    $($associated_type_name = $associated_type_name, )*
+                >
+            >
+            for __VTable<$(  $($lifetime,)* $($generics,)*  )?  $($associated_type_name,)*  __CommonData>
+            where
             $(
-                $( $where_clause_ty:ty
-                    $(: $where_clause_bound:path)?
-                    $(: ?$where_clause_unsized_bound:path)?
-                    $(: $where_clause_lifetime_bound:lifetime)?
-                ),* $(,)?
+                $( $where_clause_ty
+                    $(: $where_clause_bound)?
+                    $(: ?$where_clause_unsized_bound)?
+                    $(: $where_clause_lifetime_bound)?
+                ),*
             )?
-            {
-                $(
-                    type $associated_type_name = $associated_type_name;
-                )*
-                $(
-                    $($method_signature)*
-                    {
-                        $crate::__define_v_table_internal!{@if ($(true$(;;;$method_is_ref:ident)?)?false)
-                            // Self is a reference:
-                            {
-                                // Safety: we will only call vtable methods is sensible ways.
-                                let __vtable = unsafe { $crate::ThinWithoutCommon::get_vtable(&$method_self_ident) };
+            {}
 
-                                let __erased_thin = $crate::__define_v_table_internal!{@if ($(true$(;;;$method_self_is_mut_ref)?)?false)
-                                    // Self is a mutable reference:
-                                    {{
-                                        $crate::RawThin::without_lifetime_marker_mut(
-                                            $crate::RawThin::as_weaker_auto_traits_marker_mut(
-                                                $crate::ThinWithoutCommon::as_raw_mut($method_self_ident)
-                                            )
-                                        )
-                                    }}
-                                    else
-                                    // Self is an immutable reference:
-                                    {{
-                                        $crate::RawThin::without_lifetime_marker(
-                                            $crate::RawThin::as_weaker_auto_traits_marker(
-                                                $crate::ThinWithoutCommon::as_raw($method_self_ident)
-                                            )
-                                        )
-                                    }}
-                                };
+            // impl `VTableUpcast` for the vtables of all forwarded supertraits:
+            $crate::__define_v_table_internal!{@supertrait_upcasts
+                impl_generics = {
+                    $(
+                        $( $lifetime $(: $lifetime_bound)? ,)*
+                        $( $generics
+                            $(: $generics_bound)?
+                            $(: ?$generics_unsized_bound)?
+                            $(: $generics_lifetime_bound)?
+                        ,)*
+                    )?
+                    $($associated_type_name $(: $($associated_type_life_bound+)*  $( $associated_type_trait_bound+)*)? ,)*
+                    __CommonData,
+                },
+                vtable = { __VTable<$(  $($lifetime,)* $($generics,)*  )?  $($associated_type_name,)*  __CommonData> },
+                where_clause = {
+                $(
+                    $( $where_clause_ty
+                        $(: $where_clause_bound)?
+                        $(: ?$where_clause_unsized_bound)?
+                        $(: $where_clause_lifetime_bound)?
+                    ),*
+                )?
+                },
+                access = {},
+                is_prefix = { true },
+                supertraits = { $($forwarded_supertrait,)* },
+            }
 
-                                let result = (__vtable.$method_name)(__erased_thin, $($method_arg_name),* );
+            // Functions with the ABI of the vtable that are stored in it:
+            $crate::__define_v_table_internal!{@extern_shims
+                abi = $abi,
+                impl_header = {
+                    impl
+                    <
+                        $(
+                            $( $lifetime $(: $lifetime_bound)? ,)*
+                            $( $generics
+                                $(: $generics_bound)?
+                                $(: ?$generics_unsized_bound)?
+                                $(: $generics_lifetime_bound)?
+                            ,)*
+                        )?
+                        $($associated_type_name $(: $($associated_type_life_bound+)*  $( $associated_type_trait_bound+)*)? ,)*
+                        __CommonData,
+                    >
+                    __VTable<$(  $($lifetime,)* $($generics,)*  )?  $($associated_type_name,)*  __CommonData>
+                    where
+                    $(
+                        $( $where_clause_ty
+                            $(: $where_clause_bound)?
+                            $(: ?$where_clause_unsized_bound)?
+                            $(: $where_clause_lifetime_bound)?
+                        ),*
+                    )?
+                },
+                erased_bound = {
+                    $trait_name<$(  $($lifetime,)* $($generics,)*  )?  $($associated_type_name = $associated_type_name,)*  >
+                },
+                common_info = {
+                    trait_name = $trait_name,
+                    trait_lifetime = $($($lifetime),*)?,
+                    trait_generics = $($($generics),*)?,
+                },
+                thin_clone = { $(true $(;;; $is_thin_clone)?)? },
+                parsed_fns = {
+                    $({
+                        cfg = {  $($method_cfg)*  },
+                        is_unsafe = {  $(unsafe $(;;; $method_is_unsafe:ident)?)?  },
+                        method_name = { $method_name },
+                        lifetimes_parameters = { $($method_lifetime_parameter,)* },
+                        arguments = { $(  $method_arg_name: $method_arg_ty,  )* },
+                        return_type = {  $($return_type)?  },
+                        self_ident = {  $method_self_ident  },
+                        self_type = {
+                            $( &$($method_self_life)? )? $(mut $(;;; $method_self_is_mut_ref)?)? self
+                        },
+                        signature = {  $($method_signature)*  },
+                    })*
+                },
+            }
 
-                                $crate::__define_v_table_internal!{@transmute_return_type
-                                    self_lifetime = { $($($method_self_life)?)? },
-                                    return_type = { $($return_type)? },
-                                    value_to_transmute = {result},
+            // impl `DescribeVTable` for `extern "C"` vtables so that C headers can be generated:
+            $crate::__define_v_table_internal!{@if_extern $abi
+                {
+                    unsafe impl
+                    <
+                        $(
+                            $( $lifetime $(: $lifetime_bound)? ,)*
+                            $( $generics
+                                $(: $generics_bound)?
+                                $(: ?$generics_unsized_bound)?
+                                $(: $generics_lifetime_bound)?
+                            ,)*
+                        )?
+                        $($associated_type_name $(: $($associated_type_life_bound+)*  $( $associated_type_trait_bound+)*)? ,)*
+                        __CommonData,
+                    >
+                    $crate::c_header::DescribeVTable
+                    for __VTable<$(  $($lifetime,)* $($generics,)*  )?  $($associated_type_name,)*  __CommonData>
+                    where
+                        $(
+                            <dyn $forwarded_supertrait as $crate::__private::VTableOf<__CommonData>>::VTable:
+                                $crate::c_header::DescribeVTable,
+                        )*
+                    $(
+                        $( $where_clause_ty
+                            $(: $where_clause_bound)?
+                            $(: ?$where_clause_unsized_bound)?
+                            $(: $where_clause_lifetime_bound)?
+                        ),*
+                    )?
+                    {
+                        const DESCRIPTION: $crate::c_header::VTableDescription = {
+                            let methods: &[$crate::c_header::MethodDescription] = &[$(
+                                $($method_cfg)*
+                                $crate::c_header::MethodDescription {
+                                    name: ::core::stringify!($method_name),
+                                    receiver: $crate::__define_v_table_internal!{@if ($(true$(;;;$method_is_ref:ident)?)?false)
+                                        {
+                                            $crate::__define_v_table_internal!{@if ($(true$(;;;$method_self_is_mut_ref)?)?false)
+                                                { $crate::c_header::Receiver::Mut }
+                                                else
+                                                { $crate::c_header::Receiver::Ref }
+                                            }
+                                        }
+                                        else
+                                        { $crate::c_header::Receiver::Value }
+                                    },
+                                    arguments: &[$(
+                                        (::core::stringify!($method_arg_name), ::core::stringify!($method_arg_ty)),
+                                    )*],
+                                    return_type: ::core::stringify!($($return_type)?),
+                                },
+                            )*];
+                            let thin_clone = $crate::__define_v_table_internal!{@if ($(true $(;;; $is_thin_clone)?)?)
+                                { true }
+                                else
+                                { false }
+                            };
+                            // The fields that C code can use are followed by the Rust-only fields:
+                            let word = ::core::mem::size_of::<fn()>();
+                            let c_fields = ::core::mem::size_of::<
+                                $crate::__define_v_table_internal!{@supertrait_vtables_type
+                                    $($forwarded_supertrait,)*
                                 }
+                            >() / word + methods.len() + 1 + thin_clone as usize;
+                            $crate::c_header::VTableDescription {
+                                trait_name: ::core::stringify!($trait_name),
+                                supertraits: &[$(
+                                    <
+                                        <dyn $forwarded_supertrait as $crate::__private::VTableOf<__CommonData>>::VTable
+                                        as $crate::c_header::DescribeVTable
+                                    >::DESCRIPTION,
+                                )*],
+                                methods,
+                                thin_clone,
+                                rust_only_fields: ::core::mem::size_of::<Self>() / word - c_fields,
                             }
-                            else
-                            // Self is taken by value:
-                            {::core::unreachable!("`ThinWithoutCommon` is always behind a reference.")}
-                        }
+                        };
                     }
-                )*
+                }
+                else
+                {}
             }
 
-            // impl the user's trait for `ThinBox` so that the trait methods can be called
-            // for methods that consumes self.
-            #[allow(unused_mut, clippy::needless_arbitrary_self_type)]
-            // This warning can happen if a method is unsafe (then any unsafe uses becomes unnecessary).
-            #[allow(unused_unsafe)]
-            $(unsafe $($is_unsafe_trait)?)? impl
+            // impl `ConstVTable` for all types that implement the trait:
+            impl
             <
                 $(
                     $( $lifetime $(: $lifetime_bound)? ,)*
@@ -892,99 +1532,74 @@ macro_rules! __define_v_table_internal {
                 )?
                 $($associated_type_name $(: $($associated_type_life_bound+)*  $( $associated_type_trait_bound+)*)? ,)*
                 __CommonData,
-                __ThinTrait,
+                __T,
             >
-            $trait_name<$(  $($lifetime,)* $($generics,)*  )?>
-            for
-            $crate::ThinBox<'_, __ThinTrait, __CommonData>
+            $crate::__private::ConstVTable<__T>
+            for __VTable<$(  $($lifetime,)* $($generics,)*  )?  $($associated_type_name,)*  __CommonData>
             where
-                // Ensure all required auto traits are implemented (might for example constrain __CommonData):
-                Self: $($($super_lifetime_bound +)* $($super_bound +)*)?,
-                // Ensure the thin trait implementation uses our vtable:
-                __ThinTrait: $crate::ThinTrait<
-                    __CommonData,
-                    VTable =  __VTable<$(  $($lifetime,)* $($generics,)*  )?  $($associated_type_name,)*  __CommonData,>
-                > + ?::core::marker::Sized,
-                // Ensure auto trait config works for our vtable:
-                <
-                    __ThinTrait as $crate::ThinTrait<__CommonData>
-                >::AutoTraitConfig: $crate::auto_traits::AutoTraitConfig<
-                    <
-                        __VTable<$(  $($lifetime,)* $($generics,)*  )?  $($associated_type_name,)*  __CommonData,>
-                        as $crate::auto_traits::VTableEnforcedAutoTraits
-                    >::UncheckedAutoTraitMarker
-                >,
-                 __VTable<$(  $($lifetime,)* $($generics,)*  )?  $($associated_type_name,)*  __CommonData,>: $($($super_lifetime_bound +)*)?,
+                __T: $trait_name<$(  $($lifetime,)* $($generics,)*  )?  $($associated_type_name = $associated_type_name,)*  >
+                    // The `ThinClone` marker trait requires that the type can be cloned:
+                    $( + ::core::clone::Clone $(;;; $is_thin_clone)? )?,
+                // The vtables of forwarded supertraits must be constructible for the type:
+                $(
+                    <dyn $forwarded_supertrait as $crate::__private::VTableOf<__CommonData>>::VTable:
+                        $crate::__private::ConstVTable<__T>,
+                )*
             $(
-                $( $where_clause_ty:ty
-                    $(: $where_clause_bound:path)?
-                    $(: ?$where_clause_unsized_bound:path)?
-                    $(: $where_clause_lifetime_bound:lifetime)?
-                ),* $(,)?
+                $( $where_clause_ty
+                    $(: $where_clause_bound)?
+                    $(: ?$where_clause_unsized_bound)?
+                    $(: $where_clause_lifetime_bound)?
+                ),*
             )?
             {
-                $(
-                    type $associated_type_name = $associated_type_name;
-                )*
-                $(
-                    $($method_signature)*
-                    {
-                        // Safety: we will only call vtable methods is sensible ways.
-                        let __vtable = unsafe { $crate::ThinWithoutCommon::get_vtable(&$method_self_ident) };
-
-                        let __erased = $crate::__define_v_table_internal!{@if ($(true$(;;;$method_is_ref:ident)?)?false)
-                            // Self is a reference:
-                            {
-                                $crate::__define_v_table_internal!{@if ($(true$(;;;$method_self_is_mut_ref)?)?false)
-                                    // Self is a mutable reference:
-                                    {{
-                                        $crate::RawThin::without_lifetime_marker_mut(
-                                            $crate::RawThin::as_weaker_auto_traits_marker_mut(
-                                                $crate::ThinWithoutCommon::as_raw_mut($method_self_ident)
-                                            )
-                                        )
-                                    }}
-                                    else
-                                    // Self is an immutable reference:
-                                    {{
-                                        $crate::RawThin::without_lifetime_marker(
-                                            $crate::RawThin::as_weaker_auto_traits_marker(
-                                                $crate::ThinWithoutCommon::as_raw($method_self_ident)
-                                            )
-                                        )
-                                    }}
-                                }
-                            }
-                            else
-                            // Self is taken by value:
-                            {
-                                unsafe {
-                                    // Safety: we can forget the lifetime since we will be consuming
-                                    // the value now anyway.
-                                    $crate::ThinBox::into_raw($method_self_ident)
-                                        .weaken_auto_traits_marker()
-                                        .forget_lifetime()
-                                        .free_common_data()
-                                }
-                            }
-                        };
-                        let result = (__vtable.$method_name)(__erased, $($method_arg_name),* );
-
-                        $crate::__define_v_table_internal!{@transmute_return_type
-                            self_lifetime = { $($($method_self_life)?)? },
-                            return_type = { $($return_type)? },
-                            value_to_transmute = {result},
-                        }
-                    }
-                )*
+                // This warning can happen if a method is unsafe (then any unsafe uses inside it becomes unnecessary).
+                #[allow(unused_unsafe, clippy::needless_arbitrary_self_type)]
+                const VTABLE: Self = $crate::__define_v_table_internal! {
+                    @create_vtable
+                    parsed_fns = {
+                        $({
+                            cfg = {  $($method_cfg)*  },
+                            is_unsafe = {  $(unsafe $(;;; $method_is_unsafe:ident)?)?  },
+                            method_name = { $method_name },
+                            lifetimes_parameters = { $($method_lifetime_parameter,)* },
+                            arguments = { $(  $method_arg_name: $method_arg_ty,  )* },
+                            return_type = {  $($return_type)?  },
+                            self_ident = {  $method_self_ident  },
+                            self_type = {
+                                $( &$($method_self_life)? )? $(mut $(;;; $method_self_is_mut_ref)?)? self
+                            },
+                            signature = {  $($method_signature)*  },
+                        })*
+                    },
+                    vtable_methods = {},
+                    common_info = {
+                        trait_name = $trait_name,
+                        trait_lifetime = $($($lifetime),*)?,
+                        trait_generics = $($($generics),*)?,
+                        // Don't need associated types since
+                        // `<Self as Trait<generics>>::method` is already fully
+                        // resolved (associated types are deduced from self type).
+                    },
+                    vtable_info = {
+                        erased_type = __T,
+                        vtable_name = __VTable,
+                        thin_clone = { $(true $(;;; $is_thin_clone)?)? },
+                        supertraits = { $($forwarded_supertrait,)* },
+                        abi = $abi,
+                        dyn_trait = {
+                            dyn $trait_name
+                            <
+                                $(  $($lifetime,)* $($generics,)*  )?
+                                $($associated_type_name = $associated_type_name, )*
+                            >
+                        },
+                    },
+                };
             }
 
-            // impl the user's trait for `ThinBoxWithoutCommon` so that the trait methods can be called
-            // for methods that consumes self.
-            #[allow(unused_mut, clippy::needless_arbitrary_self_type)]
-            // This warning can happen if a method is unsafe (then any unsafe uses becomes unnecessary).
-            #[allow(unused_unsafe)]
-            $(unsafe $($is_unsafe_trait)?)? impl
+            // impl `GetThinTraitVTable` for all types that implement the trait:
+            impl
             <
                 $(
                     $( $lifetime $(: $lifetime_bound)? ,)*
@@ -996,50 +1611,250 @@ macro_rules! __define_v_table_internal {
                 )?
                 $($associated_type_name $(: $($associated_type_life_bound+)*  $( $associated_type_trait_bound+)*)? ,)*
                 __CommonData,
-                __ThinTrait,
+                __T,
             >
-            $trait_name<$(  $($lifetime,)* $($generics,)*  )?>
-            for
-            $crate::ThinBoxWithoutCommon<'_, __ThinTrait, __CommonData>
+            $crate::GetThinTraitVTable<__T>
+            for __VTable<$(  $($lifetime,)* $($generics,)*  )?  $($associated_type_name,)*  __CommonData>
             where
-                // Ensure all required auto traits are implemented (might for example constrain __CommonData):
-                Self: $($($super_lifetime_bound +)* $($super_bound +)*)?,
-                // Ensure the thin trait implementation uses our vtable:
-                __ThinTrait: $crate::ThinTrait<
-                    __CommonData,
-                    VTable =  __VTable<$(  $($lifetime,)* $($generics,)*  )?  $($associated_type_name,)*  __CommonData,>
-                > + ?::core::marker::Sized,
-                // Ensure auto trait config works for our vtable:
-                <
-                    __ThinTrait as $crate::ThinTrait<__CommonData>
-                >::AutoTraitConfig: $crate::auto_traits::AutoTraitConfig<
-                    <
-                        __VTable<$(  $($lifetime,)* $($generics,)*  )?  $($associated_type_name,)*  __CommonData,>
-                        as $crate::auto_traits::VTableEnforcedAutoTraits
-                    >::UncheckedAutoTraitMarker
-                >,
-                 __VTable<$(  $($lifetime,)* $($generics,)*  )?  $($associated_type_name,)*  __CommonData,>: $($($super_lifetime_bound +)*)?,
+                __T: $trait_name<$(  $($lifetime,)* $($generics,)*  )?  $($associated_type_name = $associated_type_name,)*  >
+                    // The `ThinClone` marker trait requires that the type can be cloned:
+                    $( + ::core::clone::Clone $(;;; $is_thin_clone)? )?,
+                // The vtables of forwarded supertraits must be constructible for the type:
+                $(
+                    <dyn $forwarded_supertrait as $crate::__private::VTableOf<__CommonData>>::VTable:
+                        $crate::__private::ConstVTable<__T>,
+                )*
             $(
-                $( $where_clause_ty:ty
-                    $(: $where_clause_bound:path)?
-                    $(: ?$where_clause_unsized_bound:path)?
-                    $(: $where_clause_lifetime_bound:lifetime)?
-                ),* $(,)?
+                $( $where_clause_ty
+                    $(: $where_clause_bound)?
+                    $(: ?$where_clause_unsized_bound)?
+                    $(: $where_clause_lifetime_bound)?
+                ),*
+            )?
+            {
+                const VTABLE: $crate::VTable<Self, __T> = {
+                    // The vtable is promoted to a `'static` reference, otherwise the
+                    // pointer in the constant would be dangling and this wouldn't compile.
+                    let vtable: &__VTable<$(  $($lifetime,)* $($generics,)*  )?  $($associated_type_name,)*  __CommonData> =
+                        &<Self as $crate::__private::ConstVTable<__T>>::VTABLE;
+                    // Safety: this vtable was specifically constructed for the type `__T` so
+                    // it will behave sensibly for that type. Our vtable also only contains
+                    // methods and all of them take the type `__T` as inputs so the vtable
+                    // can't outlive any lifetime requirements of the type `__T`.
+                    unsafe { $crate::VTable::new(vtable) }
+                };
+            }
+
+            // impl `VTableDrop` for __VTable:
+            // Allows the vtable to be used in ThinBox's Drop implementation.
+            impl
+            <
+                $(
+                    $( $lifetime $(: $lifetime_bound)? ,)*
+                    $( $generics
+                        $(: $generics_bound)?
+                        $(: ?$generics_unsized_bound)?
+                        $(: $generics_lifetime_bound)?
+                    ,)*
+                )?
+                $($associated_type_name $(: $($associated_type_life_bound+)*  $( $associated_type_trait_bound+)*)? ,)*
+                __CommonData,
+            >
+            $crate::VTableDrop<__CommonData>
+            for
+            __VTable<$(  $($lifetime,)* $($generics,)*  )?  $($associated_type_name,)*  __CommonData,>
+            $(
+            where
+                $( $where_clause_ty
+                    $(: $where_clause_bound)?
+                    $(: ?$where_clause_unsized_bound)?
+                    $(: $where_clause_lifetime_bound)?
+                ),*
+            )?
+            {
+                unsafe fn drop_erased_in_place(&self, erased: &mut $crate::RawThin<'_, Self, $crate::Split<__CommonData>, $crate::auto_traits::NoAutoTraits, ()>) {
+                    (self.__drop_in_place)(erased)
+                }
+                unsafe fn drop_erased_object(&self, object: *mut u8) {
+                    ::core::ptr::drop_in_place((self.__as_dyn)(object))
+                }
+                fn erased_layout(&self) -> ::core::alloc::Layout {
+                    (self.__layout)()
+                }
+                fn erased_type_id(&self) -> ::core::any::TypeId {
+                    (self.__type_id)()
+                }
+                fn erased_type_name(&self) -> &'static str {
+                    (self.__type_name)()
+                }
+                fn erased_debug_vtable(&self) -> ::core::option::Option<&$crate::DebugVTable> {
+                    $crate::__private::FindDebugVTable::find_debug_vtable(&self.__supertraits)
+                }
+            }
+
+            // impl `FindDebugVTable` for __VTable:
+            // Allows `Debug` to be found when this trait is forwarded as a supertrait.
+            impl
+            <
+                $(
+                    $( $lifetime $(: $lifetime_bound)? ,)*
+                    $( $generics
+                        $(: $generics_bound)?
+                        $(: ?$generics_unsized_bound)?
+                        $(: $generics_lifetime_bound)?
+                    ,)*
+                )?
+                $($associated_type_name $(: $($associated_type_life_bound+)*  $( $associated_type_trait_bound+)*)? ,)*
+                __CommonData,
+            >
+            $crate::__private::FindDebugVTable
+            for
+            __VTable<$(  $($lifetime,)* $($generics,)*  )?  $($associated_type_name,)*  __CommonData,>
+            $(
+            where
+                $( $where_clause_ty
+                    $(: $where_clause_bound)?
+                    $(: ?$where_clause_unsized_bound)?
+                    $(: $where_clause_lifetime_bound)?
+                ),*
+            )?
+            {
+                fn find_debug_vtable(&self) -> ::core::option::Option<&$crate::DebugVTable> {
+                    $crate::__private::FindDebugVTable::find_debug_vtable(&self.__supertraits)
+                }
+            }
+
+            // impl `VTableClone` for __VTable if the trait has `ThinClone` as a supertrait:
+            // Allows `ThinBox` and `ThinBoxWithoutCommon` to implement `Clone`.
+            $crate::__if_alloc!{
+                {
+                $crate::__define_v_table_internal!{@if ($(true $(;;; $is_thin_clone)?)?)
+                    {
+                        impl
+                        <
+                            $(
+                                $( $lifetime $(: $lifetime_bound)? ,)*
+                                $( $generics
+                                    $(: $generics_bound)?
+                                    $(: ?$generics_unsized_bound)?
+                                    $(: $generics_lifetime_bound)?
+                                ,)*
+                            )?
+                            $($associated_type_name $(: $($associated_type_life_bound+)*  $( $associated_type_trait_bound+)*)? ,)*
+                            __CommonData,
+                        >
+                        $crate::VTableClone<__CommonData>
+                        for
+                        __VTable<$(  $($lifetime,)* $($generics,)*  )?  $($associated_type_name,)*  __CommonData,>
+                        $(
+                        where
+                            $( $where_clause_ty
+                                $(: $where_clause_bound)?
+                                $(: ?$where_clause_unsized_bound)?
+                                $(: $where_clause_lifetime_bound)?
+                            ),*
+                        )?
+                        {
+                            unsafe fn clone_erased_box<'__this>(&self, erased: &$crate::RawThin<'__this, Self, $crate::Split<__CommonData>, $crate::auto_traits::NoAutoTraits, ()>) -> $crate::__private::ErasedBox<'__this, Self, __CommonData> {
+                                (self.__clone)(erased)
+                            }
+                        }
+                    }
+                    else
+                    {}
+                }
+                }
+                else
+                {}
+            }
+
+            // impl the user's trait for `ThinWithoutCommon` so that the trait methods can be called
+            // for references to the thin trait object.
+            #[allow(unused_mut, clippy::needless_arbitrary_self_type)]
+            // This warning can happen if a method is unsafe (then any unsafe uses becomes unnecessary).
+            #[allow(unused_unsafe)]
+            $(unsafe $($is_unsafe_trait)?)? impl
+            <
+                $(
+                    $( $lifetime $(: $lifetime_bound)? ,)*
+                    $( $generics
+                        $(: $generics_bound)?
+                        $(: ?$generics_unsized_bound)?
+                        $(: $generics_lifetime_bound)?
+                    ,)*
+                )?
+                $($associated_type_name $(: $($associated_type_life_bound+)*  $( $associated_type_trait_bound+)*)? ,)*
+                __CommonData,
+                __ThinTrait,
+            >
+            $trait_name<$(  $($lifetime,)* $($generics,)*  )?>
+            for
+            $crate::ThinWithoutCommon<'_, __ThinTrait, __CommonData>
+            where
+                // Ensure all required auto traits are implemented (might for example constrain __CommonData):
+                Self: $($($super_lifetime_bound +)* $($super_bound +)*)?,
+                __ThinTrait: $crate::ThinTrait<__CommonData> + ?::core::marker::Sized,
+                // Determines the associated types (trait objects implement their own trait):
+                __ThinTrait: $trait_name<
+                    $(  $($lifetime,)* $($generics,)*  )?
+                    $($associated_type_name = $associated_type_name, )*
+                >,
+                // Ensure the thin trait implementation uses our vtable or the vtable of a trait
+                // that forwards our trait as a supertrait:
+                <
+                    __ThinTrait as $crate::ThinTrait<__CommonData>
+                >::VTable: $crate::VTableUpcast<
+                    dyn $trait_name
+                    <
+                        $(  $($lifetime,)* $($generics,)*  )?
+                        $($associated_type_name = $associated_type_name, )*
+                    >,
+                    VTable = __VTable<$(  $($lifetime,)* $($generics,)*  )?  $($associated_type_name,)*  __CommonData,>
+                >,
+                // Ensure auto trait config works for the thin trait's vtable:
+                <
+                    __ThinTrait as $crate::ThinTrait<__CommonData>
+                >::AutoTraitConfig: $crate::auto_traits::AutoTraitConfig<
+                    <
+                        <__ThinTrait as $crate::ThinTrait<__CommonData>>::VTable
+                        as $crate::auto_traits::VTableEnforcedAutoTraits
+                    >::UncheckedAutoTraitMarker
+                >,
+                 __VTable<$(  $($lifetime,)* $($generics,)*  )?  $($associated_type_name,)*  __CommonData,>: $($($super_lifetime_bound +)*)?,
+            $(
+                $( $where_clause_ty
+                    $(: $where_clause_bound)?
+                    $(: ?$where_clause_unsized_bound)?
+                    $(: $where_clause_lifetime_bound)?
+                ),*
             )?
             {
                 $(
                     type $associated_type_name = $associated_type_name;
                 )*
                 $(
+                    #[allow(unused_variables)]
+                    $($sized_fn_cfg)*
+                    $($sized_fn_signature)*
+                    {
+                        // Methods that require `Self: Sized` aren't part of the vtable so there is
+                        // nothing to forward to (`dyn Trait` doesn't implement them at all).
+                        ::core::panic!("methods with a `Self: Sized` bound can't be called on type erased objects")
+                    }
+                )*
+                $(
+                    $($method_cfg)*
                     $($method_signature)*
                     {
-                        // Safety: we will only call vtable methods is sensible ways.
-                        let __vtable = unsafe { $crate::ThinWithoutCommon::get_vtable(&$method_self_ident) };
-
-                        let __erased = $crate::__define_v_table_internal!{@if ($(true$(;;;$method_is_ref:ident)?)?false)
+                        $crate::__define_v_table_internal!{@if ($(true$(;;;$method_is_ref:ident)?)?false)
                             // Self is a reference:
                             {
-                                $crate::__define_v_table_internal!{@if ($(true$(;;;$method_self_is_mut_ref)?)?false)
+                                // Safety: we will only call vtable methods is sensible ways.
+                                let __vtable = unsafe { $crate::ThinWithoutCommon::get_vtable(&$method_self_ident) };
+                                // Use the vtable of our trait if it is forwarded as a supertrait:
+                                let __vtable = $crate::VTableUpcast::upcast_vtable(__vtable);
+
+                                let __erased_thin = $crate::__define_v_table_internal!{@if ($(true$(;;;$method_self_is_mut_ref)?)?false)
                                     // Self is a mutable reference:
                                     {{
                                         $crate::RawThin::without_lifetime_marker_mut(
@@ -1057,60 +1872,39 @@ macro_rules! __define_v_table_internal {
                                             )
                                         )
                                     }}
+                                };
+
+                                // Safety: the vtable was created for the erased object's type.
+                                let result = (__vtable.$method_name)(unsafe { $crate::__private::CastVTable::cast_vtable(__erased_thin) }, $($method_arg_name),* );
+
+                                $crate::__define_v_table_internal!{@transmute_return_type
+                                    self_lifetime = { $($($method_self_life)?)? },
+                                    return_type = { $($return_type)? },
+                                    value_to_transmute = {result},
                                 }
                             }
                             else
                             // Self is taken by value:
-                            {
-                                unsafe {
-                                    // Safety: we can forget the lifetime since we will be consuming
-                                    // the value now anyway.
-                                    $crate::ThinBoxWithoutCommon::into_raw($method_self_ident)
-                                        .weaken_auto_traits_marker()
-                                        .forget_lifetime()
-                                }
-                            }
-                        };
-                        let result = (__vtable.$method_name)(__erased, $($method_arg_name),* );
-
-                        $crate::__define_v_table_internal!{@transmute_return_type
-                            self_lifetime = { $($($method_self_life)?)? },
-                            return_type = { $($return_type)? },
-                            value_to_transmute = {result},
+                            {::core::unreachable!("`ThinWithoutCommon` is always behind a reference.")}
                         }
                     }
                 )*
             }
 
-            // impl `ThinTrait` for `dyn UserTrait` as a way to name the anonymous vtable type:
-            // We also implement `ThinTrait` for auto trait combinations like: `dyn UserTrait + Send + Sync`.
-            // (we use a macro for that, since all the implementations are largely the same.)
-            $crate::__define_v_table_internal! {@thin_trait_impl
-                auto_trait_combinations = {
-                    // No auto traits:
-                    => (),
-
-                    // Send:
-                    ::core::marker::Send => $crate::auto_traits::HasSend<()>,
-                    // Sync:
-                    ::core::marker::Sync => $crate::auto_traits::HasSync<()>,
-                    // Send + Sync:
-                    ::core::marker::Send + ::core::marker::Sync => $crate::auto_traits::HasSend<$crate::auto_traits::HasSync<()>>,
-
-                    // Unpin:
-                    ::core::marker::Unpin => $crate::auto_traits::HasUnpin<()>,
-                    // Send + Unpin:
-                    ::core::marker::Send + ::core::marker::Unpin => $crate::auto_traits::HasSend<$crate::auto_traits::HasUnpin<()>>,
-                    // Sync + Unpin:
-                    ::core::marker::Sync + ::core::marker::Unpin => $crate::auto_traits::HasSync<$crate::auto_traits::HasUnpin<()>>,
-                    // Send + Sync + Unpin:
-                    ::core::marker::Send + ::core::marker::Sync + ::core::marker::Unpin => $crate::auto_traits::HasSend<$crate::auto_traits::HasSync<$crate::auto_traits::HasUnpin<()>>>,
-                },
-                // Everything before the `for` in `impl Trait for Type {...}`:
-                before_for = {
-                    impl
+            // The thin pointers that own their objects require the `alloc` feature:
+            $crate::__if_alloc!{
+                {
+                // Traits without methods have nothing to forward. They are often implemented
+                // through blanket impls (such as `impl<T: Clone> Marker for T {}`) which would
+                // overlap with impls for the boxes since those are `Clone` for `ThinClone` traits.
+                $crate::__define_v_table_internal!{@if_has_methods ($($method_name)*) {
+                    // impl the user's trait for `ThinBox` so that the trait methods can be called
+                    // for methods that consumes self.
+                    #[allow(unused_mut, clippy::needless_arbitrary_self_type)]
+                    // This warning can happen if a method is unsafe (then any unsafe uses becomes unnecessary).
+                    #[allow(unused_unsafe)]
+                    $(unsafe $($is_unsafe_trait)?)? impl
                     <
-                        '__a,
                         $(
                             $( $lifetime $(: $lifetime_bound)? ,)*
                             $( $generics
@@ -1121,692 +1915,2990 @@ macro_rules! __define_v_table_internal {
                         )?
                         $($associated_type_name $(: $($associated_type_life_bound+)*  $( $associated_type_trait_bound+)*)? ,)*
                         __CommonData,
+                        __ThinTrait,
                     >
-                    $crate::ThinTrait<__CommonData>
-                },
-                // `dyn Trait` syntax for the user specified trait (without any auto traits):
-                dyn_trait_base = {
-                    dyn $trait_name
-                    <
-                        $(  $($lifetime,)* $($generics,)*  )?
-                        $($associated_type_name = $associated_type_name, )*
-                    >
-                    + '__a
-                },
-                // Where clause (`impl Trait for Type [insert where clause here] {...}`):
-                where_clause = {
+                    $trait_name<$(  $($lifetime,)* $($generics,)*  )?>
+                    for
+                    $crate::ThinBox<'_, __ThinTrait, __CommonData>
                     where
-                        __VTable<$(  $($lifetime,)* $($generics,)*  )?  $($associated_type_name,)*  __CommonData>: $($($lifetime +)*)? '__a + ::core::marker::Unpin,
-                        $($($lifetime: '__a,)*)?
+                        // Ensure all required auto traits are implemented (might for example constrain __CommonData):
+                        Self: $($($super_lifetime_bound +)* $($super_bound +)*)?,
+                        __ThinTrait: $crate::ThinTrait<__CommonData> + ?::core::marker::Sized,
+                        // Determines the associated types (trait objects implement their own trait):
+                        __ThinTrait: $trait_name<
+                            $(  $($lifetime,)* $($generics,)*  )?
+                            $($associated_type_name = $associated_type_name, )*
+                        >,
+                        // Ensure the thin trait implementation uses our vtable or the vtable of a trait
+                        // that forwards our trait as a supertrait:
+                        <
+                            __ThinTrait as $crate::ThinTrait<__CommonData>
+                        >::VTable: $crate::VTableUpcast<
+                            dyn $trait_name
+                            <
+                                $(  $($lifetime,)* $($generics,)*  )?
+                                $($associated_type_name = $associated_type_name, )*
+                            >,
+                            VTable = __VTable<$(  $($lifetime,)* $($generics,)*  )?  $($associated_type_name,)*  __CommonData,>
+                        >,
+                        // Ensure auto trait config works for the thin trait's vtable:
+                        <
+                            __ThinTrait as $crate::ThinTrait<__CommonData>
+                        >::AutoTraitConfig: $crate::auto_traits::AutoTraitConfig<
+                            <
+                                <__ThinTrait as $crate::ThinTrait<__CommonData>>::VTable
+                                as $crate::auto_traits::VTableEnforcedAutoTraits
+                            >::UncheckedAutoTraitMarker
+                        >,
+                         __VTable<$(  $($lifetime,)* $($generics,)*  )?  $($associated_type_name,)*  __CommonData,>: $($($super_lifetime_bound +)*)?,
                     $(
-                        $( $where_clause_ty:ty
-                            $(: $where_clause_bound:path)?
-                            $(: ?$where_clause_unsized_bound:path)?
-                            $(: $where_clause_lifetime_bound:lifetime)?
-                        ),* $(,)?
+                        $( $where_clause_ty
+                            $(: $where_clause_bound)?
+                            $(: ?$where_clause_unsized_bound)?
+                            $(: $where_clause_lifetime_bound)?
+                        ),*
                     )?
-                },
-                // The part of the `ThinTrait` implementation that doesn't depend on auto traits:
-                common_impl = {
-                    type VTable = __VTable<$(  $($lifetime,)* $($generics,)*  )?  $($associated_type_name,)*  __CommonData>;
-                },
-            }
-        };
-    };
-    ////////////////////////////////////////////////////////////////////////////////
-    // Code that creates the vtable for a specific type:
-    ////////////////////////////////////////////////////////////////////////////////
-    (@create_vtable
-        parsed_fns = { {
-            is_unsafe = {  $(unsafe $(;;; $method_is_unsafe:ident)?)?  },
-            method_name = { $method_name:ident },
-            lifetimes_parameters = { $($method_lifetime_parameter:lifetime),* $(,)? },
-            arguments = { $(  $method_arg_name:ident: $method_arg_ty:ty,  )* },
-            return_type = {  $($return_type:ty)?  },
-            self_ident = {  $method_self_ident:ident  },
-            self_type = {
-                $( &  $(;;;$method_is_ref:ident)?  $($method_self_life:lifetime)? )? $(mut $(;;; $method_self_is_mut_ref:ident)?)? self
-            },
-            signature = {  $($method_signature:tt)*  },
-        } $($next_fn:tt)* },
-        vtable_methods = {  $($vtable_methods:tt)*  },
-        common_info = {
-            trait_name = $trait_name:ident,
-            trait_lifetime = $($trait_lifetime:lifetime),*,
-            trait_generics = $($trait_generics:ident),*,
-        },
-        vtable_info = {
-            erased_type = $erased_ty:ident,
-            vtable_name = $vtable_name:ident,
-        },
-    ) => {
-        $crate::__define_v_table_internal! {@create_vtable
-            parsed_fns = {  $($next_fn)*  },
-            vtable_methods = { $($vtable_methods)* {
-                $method_name: |__this, $($method_arg_name),*| {
-                    $(unsafe $(;;; $method_is_unsafe:ident)?)?  {
-                        let result = <$erased_ty as $trait_name<  $($trait_lifetime,)*  $($trait_generics,)*  >>::$method_name(
+                    {
+                        $(
+                            type $associated_type_name = $associated_type_name;
+                        )*
+                        $(
+                            #[allow(unused_variables)]
+                            $($sized_fn_cfg)*
+                            $($sized_fn_signature)*
                             {
-                                // Safety: getting access to a vtable requires calling an unsafe
-                                // method on a `ThinWithoutCommon` type. This ensures that the
-                                // vtable method is only called with a type that has the
-                                // correct erased type.
-                                $crate::__define_v_table_internal!{@if ($(true$(;;;$method_is_ref:ident)?)?false)
-                                    // Self is a reference:
-                                    {
-                                        $crate::__define_v_table_internal!{@if ($(true$(;;;$method_self_is_mut_ref)?)?false)
-                                            // Self is a mutable reference:
+                                // Methods that require `Self: Sized` aren't part of the vtable so there is
+                                // nothing to forward to (`dyn Trait` doesn't implement them at all).
+                                ::core::panic!("methods with a `Self: Sized` bound can't be called on type erased objects")
+                            }
+                        )*
+                        $(
+                            $($method_cfg)*
+                            $($method_signature)*
+                            {
+                                // Safety: we will only call vtable methods is sensible ways.
+                                let __vtable = unsafe { $crate::ThinWithoutCommon::get_vtable(&$method_self_ident) };
+                                // Use the vtable of our trait if it is forwarded as a supertrait:
+                                let __vtable = $crate::VTableUpcast::upcast_vtable(__vtable);
+
+                                let __erased = $crate::__define_v_table_internal!{@if ($(true$(;;;$method_is_ref:ident)?)?false)
+                                    // Self is a reference:
+                                    {
+                                        $crate::__define_v_table_internal!{@if ($(true$(;;;$method_self_is_mut_ref)?)?false)
+                                            // Self is a mutable reference:
                                             {{
-                                                let __this = $crate::RawThin::without_lifetime_marker_mut(__this);
-                                                let unerased = unsafe { $crate::RawThin::as_unerase_mut::<$erased_ty>(__this) };
-                                                $crate::RawThin::as_object_mut(unerased)
+                                                $crate::RawThin::without_lifetime_marker_mut(
+                                                    $crate::RawThin::as_weaker_auto_traits_marker_mut(
+                                                        $crate::ThinWithoutCommon::as_raw_mut($method_self_ident)
+                                                    )
+                                                )
                                             }}
                                             else
                                             // Self is an immutable reference:
                                             {{
-                                                let __this = $crate::RawThin::without_lifetime_marker(__this);
-                                                let unerased = unsafe { $crate::RawThin::as_unerase::<$erased_ty>(__this) };
-                                                $crate::RawThin::as_object(unerased)
+                                                $crate::RawThin::without_lifetime_marker(
+                                                    $crate::RawThin::as_weaker_auto_traits_marker(
+                                                        $crate::ThinWithoutCommon::as_raw($method_self_ident)
+                                                    )
+                                                )
                                             }}
                                         }
                                     }
                                     else
                                     // Self is taken by value:
                                     {
-                                        unsafe { __this.unerase::<$erased_ty>() }.into_inner()
+                                        unsafe {
+                                            // Safety: we can forget the lifetime since we will be consuming
+                                            // the value now anyway.
+                                            $crate::ThinBox::into_raw($method_self_ident)
+                                                .weaken_auto_traits_marker()
+                                                .forget_lifetime()
+                                                .free_common_data()
+                                        }
                                     }
+                                };
+                                // Safety: the vtable was created for the erased object's type.
+                                let result = (__vtable.$method_name)(unsafe { $crate::__private::CastVTable::cast_vtable(__erased) }, $($method_arg_name),* );
+
+                                $crate::__define_v_table_internal!{@transmute_return_type
+                                    self_lifetime = { $($($method_self_life)?)? },
+                                    return_type = { $($return_type)? },
+                                    value_to_transmute = {result},
                                 }
                             }
-                            $(,$method_arg_name)*
-                        );
-                        $crate::__define_v_table_internal!{@transmute_return_type
-                            self_lifetime = { $($($method_self_life)?)? },
-                            return_type = { $($return_type)? },
-                            value_to_transmute = {result},
-                        }
+                        )*
                     }
-                }
-            }},
-            common_info = {
-                trait_name = $trait_name,
-                trait_lifetime = $($trait_lifetime),*,
-                trait_generics = $($trait_generics),*,
-            },
-            vtable_info = {
-                erased_type = $erased_ty,
-                vtable_name = $vtable_name,
-            },
-        }
-    };
-    ////////////////////////////////////////////////////////////////////////////////
-    // Code that creates the vtable for a specific type (base case):
-    ////////////////////////////////////////////////////////////////////////////////
-    (@create_vtable
-        parsed_fns = {  },
-        vtable_methods = { $({
-            $method_name:ident: $method_value:expr
-        })* },
-        common_info = $common_info:tt,
-        vtable_info = {
-            erased_type = $erased_ty:ident,
-            vtable_name = $vtable_name:ident,
-        },
-    ) => {
-        &__VTable {
-            __priv: __Private,
-            __ensure_all_type_params_are_used: ::core::marker::PhantomData,
-            __drop: |erased| {
-                // Safety: this vtable method is only called with `ThinBox`s that
-                // contain the type `__T`.
-                unsafe { erased.unerase::<__T>().free() };
-            },
-            $(
-                $method_name: $method_value,
-            )*
-        }
-    };
-    ////////////////////////////////////////////////////////////////////////////////
-    // Code that implements the `ThinTrait` for `dyn UserTrait`:
-    ////////////////////////////////////////////////////////////////////////////////
-    (@thin_trait_impl
-        auto_trait_combinations = {
-            $($(+)? $auto_trait:path)* => $config:ty,
-            $($next_config:tt)*
-        },
-        before_for = { $($before_for:tt)* },
-        dyn_trait_base = { $($dyn_trait_base:tt)* },
-        where_clause = { $($where_clause:tt)* },
-        common_impl = { $($common_impl:tt)* },
-    ) => {
-        $($before_for)*
-        for
-        (
-            $($dyn_trait_base)* $(+ $auto_trait)*
-        )
-        $($where_clause)*
-        {
-            $($common_impl)*
-            type AutoTraitConfig = $config;
-        }
 
-
-        $crate::__define_v_table_internal!{@thin_trait_impl
-            auto_trait_combinations = {
-                  $($next_config)*
-            },
-            before_for = { $($before_for)* },
-            dyn_trait_base = { $($dyn_trait_base)* },
-            where_clause = { $($where_clause)* },
-            common_impl = { $($common_impl)* },
-        }
-    };
-    ////////////////////////////////////////////////////////////////////////////////
-    // Code that implements the `ThinTrait` for `dyn UserTrait` (Base case):
-    ////////////////////////////////////////////////////////////////////////////////
-    (@thin_trait_impl
-        auto_trait_combinations = {},
-        before_for = { $($before_for:tt)* },
-        dyn_trait_base = { $($dyn_trait_base:tt)* },
-        where_clause = { $($where_clause:tt)* },
-        common_impl = { $($common_impl:tt)* },
-    ) => {};
-    ////////////////////////////////////////////////////////////////////////////////
-    // Transmute a return type to disconnect its lifetime requirement from generic
-    // types that shouldn't affect it:
-    ////////////////////////////////////////////////////////////////////////////////
-    (@transmute_return_type
-        self_lifetime = { $($self_lifetime:lifetime)? },
-        return_type = { $($return_type:ty)? },
-        value_to_transmute = {$result:ident},
-    ) => {
-        $crate::__define_v_table_internal!{@if ($(true;;;$self_lifetime)?false)
-            // Self type has explicit lifetime
-            {
-                $crate::__define_v_table_internal!{@if ($(true;;;$return_type)?false)
-                    // Method has a return type
+                    // impl the user's trait for `ThinBoxWithoutCommon` so that the trait methods can be called
+                    // for methods that consumes self.
+                    #[allow(unused_mut, clippy::needless_arbitrary_self_type)]
+                    // This warning can happen if a method is unsafe (then any unsafe uses becomes unnecessary).
+                    #[allow(unused_unsafe)]
+                    $(unsafe $($is_unsafe_trait)?)? impl
+                    <
+                        $(
+                            $( $lifetime $(: $lifetime_bound)? ,)*
+                            $( $generics
+                                $(: $generics_bound)?
+                                $(: ?$generics_unsized_bound)?
+                                $(: $generics_lifetime_bound)?
+                            ,)*
+                        )?
+                        $($associated_type_name $(: $($associated_type_life_bound+)*  $( $associated_type_trait_bound+)*)? ,)*
+                        __CommonData,
+                        __ThinTrait,
+                    >
+                    $trait_name<$(  $($lifetime,)* $($generics,)*  )?>
+                    for
+                    $crate::ThinBoxWithoutCommon<'_, __ThinTrait, __CommonData>
+                    where
+                        // Ensure all required auto traits are implemented (might for example constrain __CommonData):
+                        Self: $($($super_lifetime_bound +)* $($super_bound +)*)?,
+                        __ThinTrait: $crate::ThinTrait<__CommonData> + ?::core::marker::Sized,
+                        // Determines the associated types (trait objects implement their own trait):
+                        __ThinTrait: $trait_name<
+                            $(  $($lifetime,)* $($generics,)*  )?
+                            $($associated_type_name = $associated_type_name, )*
+                        >,
+                        // Ensure the thin trait implementation uses our vtable or the vtable of a trait
+                        // that forwards our trait as a supertrait:
+                        <
+                            __ThinTrait as $crate::ThinTrait<__CommonData>
+                        >::VTable: $crate::VTableUpcast<
+                            dyn $trait_name
+                            <
+                                $(  $($lifetime,)* $($generics,)*  )?
+                                $($associated_type_name = $associated_type_name, )*
+                            >,
+                            VTable = __VTable<$(  $($lifetime,)* $($generics,)*  )?  $($associated_type_name,)*  __CommonData,>
+                        >,
+                        // Ensure auto trait config works for the thin trait's vtable:
+                        <
+                            __ThinTrait as $crate::ThinTrait<__CommonData>
+                        >::AutoTraitConfig: $crate::auto_traits::AutoTraitConfig<
+                            <
+                                <__ThinTrait as $crate::ThinTrait<__CommonData>>::VTable
+                                as $crate::auto_traits::VTableEnforcedAutoTraits
+                            >::UncheckedAutoTraitMarker
+                        >,
+                         __VTable<$(  $($lifetime,)* $($generics,)*  )?  $($associated_type_name,)*  __CommonData,>: $($($super_lifetime_bound +)*)?,
+                    $(
+                        $( $where_clause_ty
+                            $(: $where_clause_bound)?
+                            $(: ?$where_clause_unsized_bound)?
+                            $(: $where_clause_lifetime_bound)?
+                        ),*
+                    )?
                     {
-                        // If the lifetime `'a` in `&'a $(mut)? self` is tied to the return
-                        // type then we need to transmute that lifetime to a new lifetime
-                        // that is longer since the compiler will complain that `__T` might
-                        // not live long enough for the lifetime to be valid. We know it must
-                        // be though since the self type (`Thin`) can't live longer than `__T`.
-
-                        struct __MaybeTransmuteLifetime<T>(T);
-                        impl __MaybeTransmuteLifetime<[(); 0]> {
-                            unsafe fn handle<T>(v: T) -> T { v }
-                        }
-                        impl __MaybeTransmuteLifetime<[(); 1]> {
-                            unsafe fn handle<T, U>(v: T) -> U {
-                                let v = ::core::mem::ManuallyDrop::<T>::new(v);
-                                ::core::mem::transmute_copy::<T, U>(&v)
+                        $(
+                            type $associated_type_name = $associated_type_name;
+                        )*
+                        $(
+                            #[allow(unused_variables)]
+                            $($sized_fn_cfg)*
+                            $($sized_fn_signature)*
+                            {
+                                // Methods that require `Self: Sized` aren't part of the vtable so there is
+                                // nothing to forward to (`dyn Trait` doesn't implement them at all).
+                                ::core::panic!("methods with a `Self: Sized` bound can't be called on type erased objects")
                             }
-                        }
-                        // Only transmute if the return type is connected to the self lifetime
-                        // We could always transmute but this should hopefully be catch limit
-                        // the unsafety.
-                        unsafe { __MaybeTransmuteLifetime::<[(); {
-                            let return_type_text = ::core::stringify!($($return_type)?).as_bytes();
-                            let self_lifetime = ::core::stringify!($($self_lifetime)?).as_bytes();
+                        )*
+                        $(
+                            $($method_cfg)*
+                            $($method_signature)*
+                            {
+                                // Safety: we will only call vtable methods is sensible ways.
+                                let __vtable = unsafe { $crate::ThinWithoutCommon::get_vtable(&$method_self_ident) };
+                                // Use the vtable of our trait if it is forwarded as a supertrait:
+                                let __vtable = $crate::VTableUpcast::upcast_vtable(__vtable);
 
-                            let mut scan_index = 0;
-                            let mut compare_index = 0;
-                            loop {
-                                // Uncomment the next line and use the lifetime `'z` as `&'z self` to
-                                // test what would happen if transmute wasn't used to fix issues.
-                                // if self_lifetime[1] == b"z"[0] { break 0; }
+                                let __erased = $crate::__define_v_table_internal!{@if ($(true$(;;;$method_is_ref:ident)?)?false)
+                                    // Self is a reference:
+                                    {
+                                        $crate::__define_v_table_internal!{@if ($(true$(;;;$method_self_is_mut_ref)?)?false)
+                                            // Self is a mutable reference:
+                                            {{
+                                                $crate::RawThin::without_lifetime_marker_mut(
+                                                    $crate::RawThin::as_weaker_auto_traits_marker_mut(
+                                                        $crate::ThinWithoutCommon::as_raw_mut($method_self_ident)
+                                                    )
+                                                )
+                                            }}
+                                            else
+                                            // Self is an immutable reference:
+                                            {{
+                                                $crate::RawThin::without_lifetime_marker(
+                                                    $crate::RawThin::as_weaker_auto_traits_marker(
+                                                        $crate::ThinWithoutCommon::as_raw($method_self_ident)
+                                                    )
+                                                )
+                                            }}
+                                        }
+                                    }
+                                    else
+                                    // Self is taken by value:
+                                    {
+                                        unsafe {
+                                            // Safety: we can forget the lifetime since we will be consuming
+                                            // the value now anyway.
+                                            $crate::ThinBoxWithoutCommon::into_raw($method_self_ident)
+                                                .weaken_auto_traits_marker()
+                                                .forget_lifetime()
+                                        }
+                                    }
+                                };
+                                // Safety: the vtable was created for the erased object's type.
+                                let result = (__vtable.$method_name)(unsafe { $crate::__private::CastVTable::cast_vtable(__erased) }, $($method_arg_name),* );
 
-                                if compare_index >= self_lifetime.len() {
-                                    // Found self lifetime
-                                    break 1;
-                                }
-                                if compare_index + scan_index >= return_type_text.len() {
-                                    // Reached end of return type without finding lifetime
-                                    break 0;
-                                }
-                                if return_type_text[compare_index + scan_index] != self_lifetime[compare_index] {
-                                    // Scan next position in return type
-                                    scan_index += 1;
-                                    compare_index = 0;
-                                } else {
-                                    // Continue comparing this pos in return type
-                                    compare_index += 1;
+                                $crate::__define_v_table_internal!{@transmute_return_type
+                                    self_lifetime = { $($($method_self_life)?)? },
+                                    return_type = { $($return_type)? },
+                                    value_to_transmute = {result},
                                 }
                             }
-                        }]>::handle($result) }
+                        )*
                     }
-                    else
-                    // Method returns `()` so self lifetime can't be connected to it:
-                    {$result}
-                }
-            }
-            else
-            // Self type is not tied to a lifetime.
-            {$result}
-        }
-    };
-    ////////////////////////////////////////////////////////////////////////////////
-    // Utilities:
-    ////////////////////////////////////////////////////////////////////////////////
-    (@if (true $($condition:tt)*) { $($true:tt)* } else { $($false:tt)* } ) => { $($true)* };
-    (@if ($($condition:tt)*) { $($true:tt)* } else { $($false:tt)* } ) => { $($false)* };
-}
+                } else {}}
 
-/// Parses a trait definition and define a vtable that can be used to interact
-/// with a type erased object behind a thin pointer when that type implements
-/// the provided trait.
-///
-/// This macro takes a trait definition as input. If the trait has any supertraits
-/// then they should probably be auto traits or traits that have blanket implementations
-/// since the macro will try to implement the trait for thin pointer types such as
-/// [`ThinBox`] and [`Thin`].
-///
-/// # Generated code
-///
-/// This macro will parse the provided trait definition and then expand to the trait
-/// as is. The macro will also expand to some extra code that defines a vtable
-/// for the trait.
-///
-/// If the macro is used on a trait like this:
-///
-/// ```
-/// thin_trait_object::define_v_table! {
-///     pub trait Number {
-///         fn get(&self) -> u32;
-///     }
-/// }
-/// ```
-///
+                $crate::__define_v_table_internal!{@if_shared_refs ($(
+                    [$(&$(;;;$method_is_ref)?)? $(mut$(;;;$method_self_is_mut_ref)?)?]
+                )*) {
+                    // impl the user's trait for `ThinRc` so that the trait methods can be called
+                    // directly on the shared pointer. Methods that take `&mut self` or `self` can't be
+                    // forwarded through shared ownership so the trait is only implemented when all
+                    // methods take `&self`. Otherwise the object can still be reached through `Deref`
+                    // (and `ThinRc::get_mut` for mutation). Traits without methods are skipped for
+                    // the same reason as for the boxes above.
+                    #[allow(unused_mut, clippy::needless_arbitrary_self_type)]
+                    // This warning can happen if a method is unsafe (then any unsafe uses becomes unnecessary).
+                    #[allow(unused_unsafe)]
+                    $(unsafe $($is_unsafe_trait)?)? impl
+                    <
+                        $(
+                            $( $lifetime $(: $lifetime_bound)? ,)*
+                            $( $generics
+                                $(: $generics_bound)?
+                                $(: ?$generics_unsized_bound)?
+                                $(: $generics_lifetime_bound)?
+                            ,)*
+                        )?
+                        $($associated_type_name $(: $($associated_type_life_bound+)*  $( $associated_type_trait_bound+)*)? ,)*
+                        __CommonData,
+                        __ThinTrait,
+                    >
+                    $trait_name<$(  $($lifetime,)* $($generics,)*  )?>
+                    for
+                    $crate::ThinRc<'_, __ThinTrait, __CommonData>
+                    where
+                        // Ensure all required auto traits are implemented (might for example constrain __CommonData):
+                        Self: $($($super_lifetime_bound +)* $($super_bound +)*)?,
+                        __ThinTrait: $crate::ThinTrait<__CommonData> + ?::core::marker::Sized,
+                        // Determines the associated types (trait objects implement their own trait):
+                        __ThinTrait: $trait_name<
+                            $(  $($lifetime,)* $($generics,)*  )?
+                            $($associated_type_name = $associated_type_name, )*
+                        >,
+                        // Ensure the thin trait implementation uses our vtable or the vtable of a trait
+                        // that forwards our trait as a supertrait:
+                        <
+                            __ThinTrait as $crate::ThinTrait<__CommonData>
+                        >::VTable: $crate::VTableUpcast<
+                            dyn $trait_name
+                            <
+                                $(  $($lifetime,)* $($generics,)*  )?
+                                $($associated_type_name = $associated_type_name, )*
+                            >,
+                            VTable = __VTable<$(  $($lifetime,)* $($generics,)*  )?  $($associated_type_name,)*  __CommonData,>
+                        >,
+                        // Ensure auto trait config works for the thin trait's vtable:
+                        <
+                            __ThinTrait as $crate::ThinTrait<__CommonData>
+                        >::AutoTraitConfig: $crate::auto_traits::AutoTraitConfig<
+                            <
+                                <__ThinTrait as $crate::ThinTrait<__CommonData>>::VTable
+                                as $crate::auto_traits::VTableEnforcedAutoTraits
+                            >::UncheckedAutoTraitMarker
+                        >,
+                         __VTable<$(  $($lifetime,)* $($generics,)*  )?  $($associated_type_name,)*  __CommonData,>: $($($super_lifetime_bound +)*)?,
+                    $(
+                        $( $where_clause_ty
+                            $(: $where_clause_bound)?
+                            $(: ?$where_clause_unsized_bound)?
+                            $(: $where_clause_lifetime_bound)?
+                        ),*
+                    )?
+                    {
+                        $(
+                            type $associated_type_name = $associated_type_name;
+                        )*
+                        $(
+                            #[allow(unused_variables)]
+                            $($sized_fn_cfg)*
+                            $($sized_fn_signature)*
+                            {
+                                // Methods that require `Self: Sized` aren't part of the vtable so there is
+                                // nothing to forward to (`dyn Trait` doesn't implement them at all).
+                                ::core::panic!("methods with a `Self: Sized` bound can't be called on type erased objects")
+                            }
+                        )*
+                        $(
+                            $($method_cfg)*
+                            $($method_signature)*
+                            {
+                                // Safety: we will only call vtable methods is sensible ways.
+                                let __vtable = unsafe { $crate::ThinWithoutCommon::get_vtable(&$method_self_ident) };
+                                // Use the vtable of our trait if it is forwarded as a supertrait:
+                                let __vtable = $crate::VTableUpcast::upcast_vtable(__vtable);
+
+                                // Every method takes `&self` (otherwise this impl isn't emitted):
+                                let __erased = $crate::RawThin::without_lifetime_marker(
+                                    $crate::RawThin::as_weaker_auto_traits_marker(
+                                        $crate::ThinWithoutCommon::as_raw($method_self_ident)
+                                    )
+                                );
+                                // Safety: the vtable was created for the erased object's type.
+                                let result = (__vtable.$method_name)(unsafe { $crate::__private::CastVTable::cast_vtable(__erased) }, $($method_arg_name),* );
+
+                                $crate::__define_v_table_internal!{@transmute_return_type
+                                    self_lifetime = { $($($method_self_life)?)? },
+                                    return_type = { $($return_type)? },
+                                    value_to_transmute = {result},
+                                }
+                            }
+                        )*
+                    }
+
+                    // impl the user's trait for `ThinArc` so that the trait methods can be called
+                    // directly on the shared pointer.
+                    #[allow(unused_mut, clippy::needless_arbitrary_self_type)]
+                    // This warning can happen if a method is unsafe (then any unsafe uses becomes unnecessary).
+                    #[allow(unused_unsafe)]
+                    $(unsafe $($is_unsafe_trait)?)? impl
+                    <
+                        $(
+                            $( $lifetime $(: $lifetime_bound)? ,)*
+                            $( $generics
+                                $(: $generics_bound)?
+                                $(: ?$generics_unsized_bound)?
+                                $(: $generics_lifetime_bound)?
+                            ,)*
+                        )?
+                        $($associated_type_name $(: $($associated_type_life_bound+)*  $( $associated_type_trait_bound+)*)? ,)*
+                        __CommonData,
+                        __ThinTrait,
+                    >
+                    $trait_name<$(  $($lifetime,)* $($generics,)*  )?>
+                    for
+                    $crate::ThinArc<'_, __ThinTrait, __CommonData>
+                    where
+                        // Ensure all required auto traits are implemented (might for example constrain __CommonData):
+                        Self: $($($super_lifetime_bound +)* $($super_bound +)*)?,
+                        __ThinTrait: $crate::ThinTrait<__CommonData> + ?::core::marker::Sized,
+                        // Determines the associated types (trait objects implement their own trait):
+                        __ThinTrait: $trait_name<
+                            $(  $($lifetime,)* $($generics,)*  )?
+                            $($associated_type_name = $associated_type_name, )*
+                        >,
+                        // Ensure the thin trait implementation uses our vtable or the vtable of a trait
+                        // that forwards our trait as a supertrait:
+                        <
+                            __ThinTrait as $crate::ThinTrait<__CommonData>
+                        >::VTable: $crate::VTableUpcast<
+                            dyn $trait_name
+                            <
+                                $(  $($lifetime,)* $($generics,)*  )?
+                                $($associated_type_name = $associated_type_name, )*
+                            >,
+                            VTable = __VTable<$(  $($lifetime,)* $($generics,)*  )?  $($associated_type_name,)*  __CommonData,>
+                        >,
+                        // Ensure auto trait config works for the thin trait's vtable:
+                        <
+                            __ThinTrait as $crate::ThinTrait<__CommonData>
+                        >::AutoTraitConfig: $crate::auto_traits::AutoTraitConfig<
+                            <
+                                <__ThinTrait as $crate::ThinTrait<__CommonData>>::VTable
+                                as $crate::auto_traits::VTableEnforcedAutoTraits
+                            >::UncheckedAutoTraitMarker
+                        >,
+                         __VTable<$(  $($lifetime,)* $($generics,)*  )?  $($associated_type_name,)*  __CommonData,>: $($($super_lifetime_bound +)*)?,
+                    $(
+                        $( $where_clause_ty
+                            $(: $where_clause_bound)?
+                            $(: ?$where_clause_unsized_bound)?
+                            $(: $where_clause_lifetime_bound)?
+                        ),*
+                    )?
+                    {
+                        $(
+                            type $associated_type_name = $associated_type_name;
+                        )*
+                        $(
+                            #[allow(unused_variables)]
+                            $($sized_fn_cfg)*
+                            $($sized_fn_signature)*
+                            {
+                                // Methods that require `Self: Sized` aren't part of the vtable so there is
+                                // nothing to forward to (`dyn Trait` doesn't implement them at all).
+                                ::core::panic!("methods with a `Self: Sized` bound can't be called on type erased objects")
+                            }
+                        )*
+                        $(
+                            $($method_cfg)*
+                            $($method_signature)*
+                            {
+                                // Safety: we will only call vtable methods is sensible ways.
+                                let __vtable = unsafe { $crate::ThinWithoutCommon::get_vtable(&$method_self_ident) };
+                                // Use the vtable of our trait if it is forwarded as a supertrait:
+                                let __vtable = $crate::VTableUpcast::upcast_vtable(__vtable);
+
+                                // Every method takes `&self` (otherwise this impl isn't emitted):
+                                let __erased = $crate::RawThin::without_lifetime_marker(
+                                    $crate::RawThin::as_weaker_auto_traits_marker(
+                                        $crate::ThinWithoutCommon::as_raw($method_self_ident)
+                                    )
+                                );
+                                // Safety: the vtable was created for the erased object's type.
+                                let result = (__vtable.$method_name)(unsafe { $crate::__private::CastVTable::cast_vtable(__erased) }, $($method_arg_name),* );
+
+                                $crate::__define_v_table_internal!{@transmute_return_type
+                                    self_lifetime = { $($($method_self_life)?)? },
+                                    return_type = { $($return_type)? },
+                                    value_to_transmute = {result},
+                                }
+                            }
+                        )*
+                    }
+                } else {}}
+
+                $crate::__define_v_table_internal!{@if_refs ($(
+                    [$(&$(;;;$method_is_ref)?)?]
+                )*) {
+                    // impl the user's trait for `BoxedDyn` so that boxed trait objects can be stored
+                    // inside thin pointers. A `self` argument can't be moved out of the box since it
+                    // is unsized so the trait is only implemented if all methods take a reference:
+                    #[allow(unused_variables, unused_mut, clippy::needless_arbitrary_self_type)]
+                    // This warning can happen if a method isn't unsafe.
+                    #[allow(unused_unsafe)]
+                    $(unsafe $($is_unsafe_trait)?)? impl
+                    <
+                        $(
+                            $( $lifetime $(: $lifetime_bound)? ,)*
+                            $( $generics
+                                $(: $generics_bound)?
+                                $(: ?$generics_unsized_bound)?
+                                $(: $generics_lifetime_bound)?
+                            ,)*
+                        )?
+                        $($associated_type_name $(: $($associated_type_life_bound+)*  $( $associated_type_trait_bound+)*)? ,)*
+                        __V,
+                    >
+                    $trait_name<$(  $($lifetime,)* $($generics,)*  )?>
+                    for
+                    $crate::BoxedDyn<__V>
+                    where
+                        // Ensure all supertraits are implemented (might for example constrain __V):
+                        Self: $($($super_lifetime_bound +)* $($super_bound +)*)?,
+                        __V: $trait_name<$(  $($lifetime,)* $($generics,)*  )?  $($associated_type_name = $associated_type_name,)*  > + ?::core::marker::Sized,
+                    $(
+                        $( $where_clause_ty
+                            $(: $where_clause_bound)?
+                            $(: ?$where_clause_unsized_bound)?
+                            $(: $where_clause_lifetime_bound)?
+                        ),*
+                    )?
+                    {
+                        $(
+                            type $associated_type_name = $associated_type_name;
+                        )*
+                        $(
+                            #[allow(unused_variables)]
+                            $($sized_fn_cfg)*
+                            $($sized_fn_signature)*
+                            {
+                                // Methods that require `Self: Sized` aren't part of the vtable so there is
+                                // nothing to forward to (`dyn Trait` doesn't implement them at all).
+                                ::core::panic!("methods with a `Self: Sized` bound can't be called on type erased objects")
+                            }
+                        )*
+                        $(
+                            $($method_cfg)*
+                            $($method_signature)*
+                            {
+                                unsafe {
+                                    $trait_name::$method_name(
+                                        $crate::__define_v_table_internal!{@if ($(true$(;;;$method_self_is_mut_ref)?)?false)
+                                            { &mut *$method_self_ident.0 }
+                                            else
+                                            { &*$method_self_ident.0 }
+                                        }
+                                        $(,$method_arg_name)*
+                                    )
+                                }
+                            }
+                        )*
+                    }
+                } else {}}
+                }
+                else
+                {}
+            }
+
+            // impl `ThinTrait` for `dyn UserTrait` as a way to name the anonymous vtable type:
+            // We also implement `ThinTrait` for auto trait combinations like: `dyn UserTrait + Send + Sync`.
+            // (we use a macro for that, since all the implementations are largely the same.)
+            $crate::__define_v_table_internal! {@thin_trait_impl
+                auto_trait_combinations = {
+                    // No auto traits:
+                    => (),
+
+                    // Send:
+                    ::core::marker::Send => $crate::auto_traits::HasSend<()>,
+                    // Sync:
+                    ::core::marker::Sync => $crate::auto_traits::HasSync<()>,
+                    // Send + Sync:
+                    ::core::marker::Send + ::core::marker::Sync => $crate::auto_traits::HasSend<$crate::auto_traits::HasSync<()>>,
+
+                    // Unpin:
+                    ::core::marker::Unpin => $crate::auto_traits::HasUnpin<()>,
+                    // Send + Unpin:
+                    ::core::marker::Send + ::core::marker::Unpin => $crate::auto_traits::HasSend<$crate::auto_traits::HasUnpin<()>>,
+                    // Sync + Unpin:
+                    ::core::marker::Sync + ::core::marker::Unpin => $crate::auto_traits::HasSync<$crate::auto_traits::HasUnpin<()>>,
+                    // Send + Sync + Unpin:
+                    ::core::marker::Send + ::core::marker::Sync + ::core::marker::Unpin => $crate::auto_traits::HasSend<$crate::auto_traits::HasSync<$crate::auto_traits::HasUnpin<()>>>,
+                },
+                // Everything before the `for` in `impl Trait for Type {...}`:
+                before_for = {
+                    impl
+                    <
+                        '__a,
+                        $(
+                            $( $lifetime $(: $lifetime_bound)? ,)*
+                            $( $generics
+                                $(: $generics_bound)?
+                                $(: ?$generics_unsized_bound)?
+                                $(: $generics_lifetime_bound)?
+                            ,)*
+                        )?
+                        $($associated_type_name $(: $($associated_type_life_bound+)*  $( $associated_type_trait_bound+)*)? ,)*
+                        __CommonData,
+                    >
+                    $crate::ThinTrait<__CommonData>
+                },
+                // `dyn Trait` syntax for the user specified trait (without any auto traits):
+                dyn_trait_base = {
+                    dyn $trait_name
+                    <
+                        $(  $($lifetime,)* $($generics,)*  )?
+                        $($associated_type_name = $associated_type_name, )*
+                    >
+                    + '__a
+                },
+                // Where clause (`impl Trait for Type [insert where clause here] {...}`):
+                where_clause = {
+                    where
+                        __VTable<$(  $($lifetime,)* $($generics,)*  )?  $($associated_type_name,)*  __CommonData>: $($($lifetime +)*)? '__a + ::core::marker::Unpin,
+                        $($($lifetime: '__a,)*)?
+                    $(
+                        $( $where_clause_ty
+                            $(: $where_clause_bound)?
+                            $(: ?$where_clause_unsized_bound)?
+                            $(: $where_clause_lifetime_bound)?
+                        ),*
+                    )?
+                },
+                // The part of the `ThinTrait` implementation that doesn't depend on auto traits:
+                common_impl = {
+                    type VTable = __VTable<$(  $($lifetime,)* $($generics,)*  )?  $($associated_type_name,)*  __CommonData>;
+                    type TraitObject = dyn $trait_name
+                    <
+                        $(  $($lifetime,)* $($generics,)*  )?
+                        $($associated_type_name = $associated_type_name, )*
+                    >;
+                },
+                // Everything before the `for` in the `ThinTraitDyn` implementation:
+                dyn_before_for = {
+                    unsafe impl
+                    <
+                        '__a,
+                        '__erased_data: '__a,
+                        $(
+                            $( $lifetime $(: $lifetime_bound)? ,)*
+                            $( $generics
+                                $(: $generics_bound)?
+                                $(: ?$generics_unsized_bound)?
+                                $(: $generics_lifetime_bound)?
+                            ,)*
+                        )?
+                        $($associated_type_name $(: $($associated_type_life_bound+)*  $( $associated_type_trait_bound+)*)? ,)*
+                        __CommonData,
+                    >
+                    $crate::ThinTraitDyn<'__erased_data, __CommonData>
+                },
+                // The `ThinTraitDyn` implementation (the same for all auto traits):
+                dyn_impl = {
+                    unsafe fn erased_as_dyn(
+                        vtable: &Self::VTable,
+                        object: *mut u8,
+                    ) -> *mut Self {
+                        // Safety: the erased object outlives `'__erased_data` (which outlives `'__a`)
+                        // and it implements all auto traits that are enforced by `Self`.
+                        ::core::mem::transmute((vtable.__as_dyn)(object))
+                    }
+                    $crate::__if_alloc!{
+                        {
+                            unsafe fn erased_into_box_dyn(
+                                vtable: &Self::VTable,
+                                erased: $crate::RawThinBox<'__erased_data, Self::VTable, $crate::Taken<__CommonData>, $crate::auto_traits::NoAutoTraits, ()>,
+                            ) -> *mut Self {
+                                // Safety: the erased object outlives `'__erased_data` (which outlives `'__a`)
+                                // and it implements all auto traits that are enforced by `Self`.
+                                ::core::mem::transmute((vtable.__into_box_dyn)(erased))
+                            }
+                        }
+                        else
+                        {}
+                    }
+                },
+            }
+        };
+    };
+    ////////////////////////////////////////////////////////////////////////////////
+    // Code that creates the vtable for a specific type:
+    ////////////////////////////////////////////////////////////////////////////////
+    (@create_vtable
+        parsed_fns = { {
+            cfg = {  $($method_cfg:tt)*  },
+            is_unsafe = {  $(unsafe $(;;; $method_is_unsafe:ident)?)?  },
+            method_name = { $method_name:ident },
+            lifetimes_parameters = { $($method_lifetime_parameter:lifetime),* $(,)? },
+            arguments = { $(  $method_arg_name:ident: $method_arg_ty:ty,  )* },
+            return_type = {  $($return_type:ty)?  },
+            self_ident = {  $method_self_ident:ident  },
+            self_type = {
+                $( &  $(;;;$method_is_ref:ident)?  $($method_self_life:lifetime)? )? $(mut $(;;; $method_self_is_mut_ref:ident)?)? self
+            },
+            signature = {  $($method_signature:tt)*  },
+        } $($next_fn:tt)* },
+        vtable_methods = {  $($vtable_methods:tt)*  },
+        common_info = {
+            trait_name = $trait_name:ident,
+            trait_lifetime = $($trait_lifetime:lifetime),*,
+            trait_generics = $($trait_generics:ident),*,
+        },
+        vtable_info = {
+            erased_type = $erased_ty:ident,
+            vtable_name = $vtable_name:ident,
+            thin_clone = $thin_clone:tt,
+            supertraits = $supertraits:tt,
+            abi = $abi:tt,
+            dyn_trait = $dyn_trait:tt,
+        },
+    ) => {
+        $crate::__define_v_table_internal! {@create_vtable
+            parsed_fns = {  $($next_fn)*  },
+            vtable_methods = { $($vtable_methods)* {
+                $($method_cfg)*
+                $method_name: $crate::__define_v_table_internal!{@if_extern $abi
+                    // The method is implemented by a function with the right ABI
+                    // (see `@extern_shims`):
+                    { Self::$method_name::<$erased_ty> }
+                    else
+                    {
+                        |__this, $($method_arg_name),*| $crate::__define_v_table_internal!{@method_body
+                            this = __this,
+                            erased_type = $erased_ty,
+                            common_info = {
+                                trait_name = $trait_name,
+                                trait_lifetime = $($trait_lifetime),*,
+                                trait_generics = $($trait_generics),*,
+                            },
+                            is_unsafe = {  $(unsafe $(;;; $method_is_unsafe)?)?  },
+                            method_name = $method_name,
+                            arguments = { $($method_arg_name,)* },
+                            self_type = {
+                                $( & $($method_self_life)? )? $(mut $(;;; $method_self_is_mut_ref)?)? self
+                            },
+                            return_type = {  $($return_type)?  },
+                        }
+                    }
+                }
+            }},
+            common_info = {
+                trait_name = $trait_name,
+                trait_lifetime = $($trait_lifetime),*,
+                trait_generics = $($trait_generics),*,
+            },
+            vtable_info = {
+                erased_type = $erased_ty,
+                vtable_name = $vtable_name,
+                thin_clone = $thin_clone,
+                supertraits = $supertraits,
+                abi = $abi,
+                dyn_trait = $dyn_trait,
+            },
+        }
+    };
+    ////////////////////////////////////////////////////////////////////////////////
+    // The body of a vtable method, `__this` is the erased object:
+    ////////////////////////////////////////////////////////////////////////////////
+    (@method_body
+        this = $this:ident,
+        erased_type = $erased_ty:ident,
+        common_info = {
+            trait_name = $trait_name:ident,
+            trait_lifetime = $($trait_lifetime:lifetime),*,
+            trait_generics = $($trait_generics:ident),*,
+        },
+        is_unsafe = {  $(unsafe $(;;; $method_is_unsafe:ident)?)?  },
+        method_name = $method_name:ident,
+        arguments = { $($method_arg_name:ident,)* },
+        self_type = {
+            $( &  $(;;;$method_is_ref:ident)?  $($method_self_life:lifetime)? )? $(mut $(;;; $method_self_is_mut_ref:ident)?)? self
+        },
+        return_type = {  $($return_type:ty)?  },
+    ) => {
+        $(unsafe $(;;; $method_is_unsafe:ident)?)?  {
+            let result = <$erased_ty as $trait_name<  $($trait_lifetime,)*  $($trait_generics,)*  >>::$method_name(
+                {
+                    // Safety: getting access to a vtable requires calling an unsafe
+                    // method on a `ThinWithoutCommon` type. This ensures that the
+                    // vtable method is only called with a type that has the
+                    // correct erased type.
+                    $crate::__define_v_table_internal!{@if ($(true$(;;;$method_is_ref:ident)?)?false)
+                        // Self is a reference:
+                        {
+                            $crate::__define_v_table_internal!{@if ($(true$(;;;$method_self_is_mut_ref)?)?false)
+                                // Self is a mutable reference:
+                                {{
+                                    let $this = $crate::RawThin::without_lifetime_marker_mut($this);
+                                    let unerased = unsafe { $crate::RawThin::as_unerase_mut::<$erased_ty>($this) };
+                                    $crate::RawThin::as_object_mut(unerased)
+                                }}
+                                else
+                                // Self is an immutable reference:
+                                {{
+                                    let $this = $crate::RawThin::without_lifetime_marker($this);
+                                    let unerased = unsafe { $crate::RawThin::as_unerase::<$erased_ty>($this) };
+                                    $crate::RawThin::as_object(unerased)
+                                }}
+                            }
+                        }
+                        else
+                        // Self is taken by value:
+                        {
+                            $crate::__if_alloc!{
+                                { unsafe { $this.unerase::<$erased_ty>() }.into_inner() }
+                                else
+                                { $crate::__private::ErasedBox::unreachable($this) }
+                            }
+                        }
+                    }
+                }
+                $(,$method_arg_name)*
+            );
+            $crate::__define_v_table_internal!{@transmute_return_type
+                self_lifetime = { $($($method_self_life)?)? },
+                return_type = { $($return_type)? },
+                value_to_transmute = {result},
+            }
+        }
+    };
+    (@drop_in_place_body
+        erased_type = $erased_ty:ident,
+        erased = $erased:ident,
+    ) => {
+        let $erased = $crate::RawThin::without_lifetime_marker_mut($erased);
+        // Safety: this vtable method is only called with objects that
+        // have the type `__T` and the caller won't use the object again.
+        unsafe {
+            let unerased = $crate::RawThin::as_unerase_mut::<$erased_ty>($erased);
+            ::core::ptr::drop_in_place($crate::RawThin::as_object_mut(unerased));
+        }
+    };
+    (@clone_body
+        erased_type = $erased_ty:ident,
+        erased = $erased:ident,
+    ) => {
+        $crate::__if_alloc!{
+            {
+                // Safety: this vtable method is only called with objects that
+                // have the type `__T`.
+                unsafe { $crate::RawThin::clone_erased::<$erased_ty>($erased) }
+            }
+            else
+            {
+                // There are no thin boxes that could be cloned.
+                let _ = $erased;
+                ::core::unreachable!()
+            }
+        }
+    };
+    ////////////////////////////////////////////////////////////////////////////////
+    // Code that creates the vtable for a specific type (base case):
+    ////////////////////////////////////////////////////////////////////////////////
+    (@create_vtable
+        parsed_fns = {  },
+        vtable_methods = { $({
+            $(#[$method_cfg:meta])*
+            $method_name:ident: $method_value:expr
+        })* },
+        common_info = $common_info:tt,
+        vtable_info = {
+            erased_type = $erased_ty:ident,
+            vtable_name = $vtable_name:ident,
+            thin_clone = { $(true $(;;; $is_thin_clone:ident)?)? },
+            supertraits = { $($forwarded_supertrait:path,)* },
+            abi = $abi:tt,
+            dyn_trait = { $($dyn_trait:tt)* },
+        },
+    ) => {
+        __VTable {
+            __supertraits: $crate::__define_v_table_internal! {@supertrait_vtables_value
+                erased_type = $erased_ty,
+                supertraits = { $($forwarded_supertrait,)* },
+            },
+            __priv: __Private,
+            __ensure_all_type_params_are_used: ::core::marker::PhantomData,
+            __drop_in_place: $crate::__define_v_table_internal!{@if_extern $abi
+                { Self::__drop_in_place::<$erased_ty> }
+                else
+                {
+                    |erased| {
+                        $crate::__define_v_table_internal!{@drop_in_place_body
+                            erased_type = $erased_ty,
+                            erased = erased,
+                        }
+                    }
+                }
+            },
+            __layout: ::core::alloc::Layout::new::<__T>,
+            __type_id: $crate::__non_static_type_id::<__T>,
+            __type_name: ::core::any::type_name::<__T>,
+            $(
+                __clone: $crate::__define_v_table_internal!{@if_extern $abi
+                    { Self::__clone::<$erased_ty> }
+                    else
+                    {
+                        |erased| {
+                            $crate::__define_v_table_internal!{@clone_body
+                                erased_type = $erased_ty,
+                                erased = erased,
+                            }
+                        }
+                    }
+                },
+                $(;;; $is_thin_clone)?
+            )?
+            __as_dyn: |object| {
+                let object: *mut ($($dyn_trait)* + '_) = object as *mut __T;
+                // Safety: `ThinTraitDyn` shortens the lifetime again before the
+                // pointer is used.
+                unsafe { ::core::mem::transmute(object) }
+            },
+            __into_box_dyn: |erased| {
+                $crate::__if_alloc!{
+                    {
+                        // Safety: this vtable method is only called with `ThinBox`s that
+                        // contain the type `__T`.
+                        let object = unsafe { erased.unerase::<__T>() }.into_box();
+                        let object: *mut ($($dyn_trait)* + '_) = $crate::__private::Box::into_raw(object);
+                        // Safety: the caller guarantees that the erased object outlives
+                        // the lifetime of the `RawThinBox` type.
+                        unsafe { ::core::mem::transmute(object) }
+                    }
+                    else
+                    { $crate::__private::ErasedBox::unreachable(erased) }
+                }
+            },
+            $(
+                $(#[$method_cfg])*
+                $method_name: $method_value,
+            )*
+        }
+    };
+    ////////////////////////////////////////////////////////////////////////////////
+    // Functions with a foreign ABI that are stored in an `extern "C"` vtable
+    // (closures can only be coerced to function pointers with the Rust ABI):
+    ////////////////////////////////////////////////////////////////////////////////
+    (@extern_shims
+        abi = {},
+        impl_header = $impl_header:tt,
+        erased_bound = $erased_bound:tt,
+        common_info = $common_info:tt,
+        thin_clone = $thin_clone:tt,
+        parsed_fns = $parsed_fns:tt,
+    ) => {};
+    (@extern_shims
+        abi = { extern $abi:literal },
+        impl_header = { $($impl_header:tt)* },
+        erased_bound = { $($erased_bound:tt)* },
+        common_info = $common_info:tt,
+        thin_clone = $thin_clone:tt,
+        parsed_fns = { {
+            cfg = {  $($method_cfg:tt)*  },
+            is_unsafe = {  $(unsafe $(;;; $method_is_unsafe:ident)?)?  },
+            method_name = { $method_name:ident },
+            lifetimes_parameters = { $($method_lifetime_parameter:lifetime),* $(,)? },
+            arguments = { $(  $method_arg_name:ident: $method_arg_ty:ty,  )* },
+            return_type = {  $($return_type:ty)?  },
+            self_ident = {  $method_self_ident:ident  },
+            self_type = {
+                $( &  $(;;;$method_is_ref:ident)?  $($method_self_life:lifetime)? )? $(mut $(;;; $method_self_is_mut_ref:ident)?)? self
+            },
+            signature = {  $($method_signature:tt)*  },
+        } $($next_fn:tt)* },
+    ) => {
+        $($impl_header)* {
+            // This warning can happen if a method is unsafe (then any unsafe uses inside it becomes unnecessary).
+            #[allow(unused_unsafe)]
+            $($method_cfg)*
+            extern $abi fn $method_name<'__this, $($method_lifetime_parameter,)* __T>(
+                __this: $crate::__define_v_table_internal!{@if ($(true$(;;;$method_is_ref:ident)?)?false)
+                    // Self is a reference:
+                    {$(&  $($method_self_life)? )?  $(mut $(;;; $method_self_is_mut_ref)?)?  $crate::RawThin<'__this, Self, $crate::Split<__CommonData>, $crate::auto_traits::NoAutoTraits, ()>}
+                    else
+                    // Self is taken by value:
+                    {$crate::__private::ErasedBox<'__this, Self, __CommonData>}
+                },
+                $($method_arg_name: $method_arg_ty),*
+            ) $(-> $return_type)?
+            where
+                __T: $($erased_bound)*,
+            {
+                $crate::__define_v_table_internal!{@method_body
+                    this = __this,
+                    erased_type = __T,
+                    common_info = $common_info,
+                    is_unsafe = {  $(unsafe $(;;; $method_is_unsafe)?)?  },
+                    method_name = $method_name,
+                    arguments = { $($method_arg_name,)* },
+                    self_type = {
+                        $( & $($method_self_life)? )? $(mut $(;;; $method_self_is_mut_ref)?)? self
+                    },
+                    return_type = {  $($return_type)?  },
+                }
+            }
+        }
+        $crate::__define_v_table_internal!{@extern_shims
+            abi = { extern $abi },
+            impl_header = { $($impl_header)* },
+            erased_bound = { $($erased_bound)* },
+            common_info = $common_info,
+            thin_clone = $thin_clone,
+            parsed_fns = { $($next_fn)* },
+        }
+    };
+    (@extern_shims
+        abi = { extern $abi:literal },
+        impl_header = { $($impl_header:tt)* },
+        erased_bound = $erased_bound:tt,
+        common_info = $common_info:tt,
+        thin_clone = { $(true $(;;; $is_thin_clone:ident)?)? },
+        parsed_fns = {},
+    ) => {
+        $($impl_header)* {
+            extern $abi fn __drop_in_place<'__this, __T>(
+                erased: &mut $crate::RawThin<'__this, Self, $crate::Split<__CommonData>, $crate::auto_traits::NoAutoTraits, ()>,
+            ) {
+                $crate::__define_v_table_internal!{@drop_in_place_body
+                    erased_type = __T,
+                    erased = erased,
+                }
+            }
+            $(
+                extern $abi fn __clone<'__this, __T>(
+                    erased: &$crate::RawThin<'__this, Self, $crate::Split<__CommonData>, $crate::auto_traits::NoAutoTraits, ()>,
+                ) -> $crate::__private::ErasedBox<'__this, Self, __CommonData>
+                where
+                    __T: ::core::clone::Clone,
+                {
+                    $crate::__define_v_table_internal!{@clone_body
+                        erased_type = __T,
+                        erased = erased,
+                    }
+                }
+                $(;;; $is_thin_clone)?
+            )?
+        }
+    };
+    ////////////////////////////////////////////////////////////////////////////////
+    // Code that implements the `ThinTrait` for `dyn UserTrait`:
+    ////////////////////////////////////////////////////////////////////////////////
+    (@thin_trait_impl
+        auto_trait_combinations = {
+            $($(+)? $auto_trait:path)* => $config:ty,
+            $($next_config:tt)*
+        },
+        before_for = { $($before_for:tt)* },
+        dyn_trait_base = { $($dyn_trait_base:tt)* },
+        where_clause = { $($where_clause:tt)* },
+        common_impl = { $($common_impl:tt)* },
+        dyn_before_for = { $($dyn_before_for:tt)* },
+        dyn_impl = { $($dyn_impl:tt)* },
+    ) => {
+        $($before_for)*
+        for
+        (
+            $($dyn_trait_base)* $(+ $auto_trait)*
+        )
+        $($where_clause)*
+        {
+            $($common_impl)*
+            type AutoTraitConfig = $config;
+        }
+
+        $($dyn_before_for)*
+        for
+        (
+            $($dyn_trait_base)* $(+ $auto_trait)*
+        )
+        $($where_clause)*
+        {
+            $($dyn_impl)*
+        }
+
+
+        $crate::__define_v_table_internal!{@thin_trait_impl
+            auto_trait_combinations = {
+                  $($next_config)*
+            },
+            before_for = { $($before_for)* },
+            dyn_trait_base = { $($dyn_trait_base)* },
+            where_clause = { $($where_clause)* },
+            common_impl = { $($common_impl)* },
+            dyn_before_for = { $($dyn_before_for)* },
+            dyn_impl = { $($dyn_impl)* },
+        }
+    };
+    ////////////////////////////////////////////////////////////////////////////////
+    // Code that implements the `ThinTrait` for `dyn UserTrait` (Base case):
+    ////////////////////////////////////////////////////////////////////////////////
+    (@thin_trait_impl
+        auto_trait_combinations = {},
+        before_for = { $($before_for:tt)* },
+        dyn_trait_base = { $($dyn_trait_base:tt)* },
+        where_clause = { $($where_clause:tt)* },
+        common_impl = { $($common_impl:tt)* },
+        dyn_before_for = { $($dyn_before_for:tt)* },
+        dyn_impl = { $($dyn_impl:tt)* },
+    ) => {};
+    ////////////////////////////////////////////////////////////////////////////////
+    // Transmute a return type to disconnect its lifetime requirement from generic
+    // types that shouldn't affect it:
+    ////////////////////////////////////////////////////////////////////////////////
+    (@transmute_return_type
+        self_lifetime = { $($self_lifetime:lifetime)? },
+        return_type = { $($return_type:ty)? },
+        value_to_transmute = {$result:ident},
+    ) => {
+        $crate::__define_v_table_internal!{@if ($(true;;;$self_lifetime)?false)
+            // Self type has explicit lifetime
+            {
+                $crate::__define_v_table_internal!{@if ($(true;;;$return_type)?false)
+                    // Method has a return type
+                    {
+                        // If the lifetime `'a` in `&'a $(mut)? self` is tied to the return
+                        // type then we need to transmute that lifetime to a new lifetime
+                        // that is longer since the compiler will complain that `__T` might
+                        // not live long enough for the lifetime to be valid. We know it must
+                        // be though since the self type (`Thin`) can't live longer than `__T`.
+
+                        struct __MaybeTransmuteLifetime<T>(T);
+                        impl __MaybeTransmuteLifetime<[(); 0]> {
+                            unsafe fn handle<T>(v: T) -> T { v }
+                        }
+                        impl __MaybeTransmuteLifetime<[(); 1]> {
+                            unsafe fn handle<T, U>(v: T) -> U {
+                                let v = ::core::mem::ManuallyDrop::<T>::new(v);
+                                ::core::mem::transmute_copy::<T, U>(&v)
+                            }
+                        }
+                        // Only transmute if the return type is connected to the self lifetime
+                        // We could always transmute but this should hopefully be catch limit
+                        // the unsafety.
+                        unsafe { __MaybeTransmuteLifetime::<[(); {
+                            let return_type_text = ::core::stringify!($($return_type)?).as_bytes();
+                            let self_lifetime = ::core::stringify!($($self_lifetime)?).as_bytes();
+
+                            let mut scan_index = 0;
+                            let mut compare_index = 0;
+                            loop {
+                                // Uncomment the next line and use the lifetime `'z` as `&'z self` to
+                                // test what would happen if transmute wasn't used to fix issues.
+                                // if self_lifetime[1] == b"z"[0] { break 0; }
+
+                                if compare_index >= self_lifetime.len() {
+                                    // Found self lifetime
+                                    break 1;
+                                }
+                                if compare_index + scan_index >= return_type_text.len() {
+                                    // Reached end of return type without finding lifetime
+                                    break 0;
+                                }
+                                if return_type_text[compare_index + scan_index] != self_lifetime[compare_index] {
+                                    // Scan next position in return type
+                                    scan_index += 1;
+                                    compare_index = 0;
+                                } else {
+                                    // Continue comparing this pos in return type
+                                    compare_index += 1;
+                                }
+                            }
+                        }]>::handle($result) }
+                    }
+                    else
+                    // Method returns `()` so self lifetime can't be connected to it:
+                    {$result}
+                }
+            }
+            else
+            // Self type is not tied to a lifetime.
+            {$result}
+        }
+    };
+    ////////////////////////////////////////////////////////////////////////////////
+    // Code that stores and exposes the vtables of forwarded supertraits:
+    ////////////////////////////////////////////////////////////////////////////////
+    (@supertrait_vtables_type) => { () };
+    (@supertrait_vtables_type $first:path, $($rest:path,)*) => {
+        $crate::__private::SupertraitVTables<
+            <dyn $first as $crate::__private::VTableOf<__CommonData>>::VTable,
+            $crate::__define_v_table_internal!{@supertrait_vtables_type $($rest,)*},
+        >
+    };
+    (@supertrait_vtables_value
+        erased_type = $erased_ty:ident,
+        supertraits = {},
+    ) => { () };
+    (@supertrait_vtables_value
+        erased_type = $erased_ty:ident,
+        supertraits = { $first:path, $($rest:path,)* },
+    ) => {
+        $crate::__private::SupertraitVTables(
+            <
+                <dyn $first as $crate::__private::VTableOf<__CommonData>>::VTable
+                as $crate::__private::ConstVTable<$erased_ty>
+            >::VTABLE,
+            $crate::__define_v_table_internal!{@supertrait_vtables_value
+                erased_type = $erased_ty,
+                supertraits = { $($rest,)* },
+            },
+        )
+    };
+    (@supertrait_upcasts
+        impl_generics = { $($impl_generics:tt)* },
+        vtable = { $($vtable:tt)* },
+        where_clause = { $($where_clause:tt)* },
+        access = { $($access:tt)* },
+        is_prefix = { $($is_prefix:tt)* },
+        supertraits = {},
+    ) => {};
+    (@supertrait_upcasts
+        impl_generics = { $($impl_generics:tt)* },
+        vtable = { $($vtable:tt)* },
+        where_clause = { $($where_clause:tt)* },
+        access = { $($access:tt)* },
+        is_prefix = { $($is_prefix:tt)* },
+        supertraits = { $first:path, $($rest:path,)* },
+    ) => {
+        // Safety: the supertrait's vtable was created for the same erased type
+        // as this vtable.
+        unsafe impl<$($impl_generics)*>
+        $crate::VTableUpcast<dyn $first>
+        for $($vtable)*
+        where
+            $($where_clause)*
+        {
+            type VTable = <dyn $first as $crate::__private::VTableOf<__CommonData>>::VTable;
+
+            fn upcast_vtable(&self) -> &Self::VTable {
+                &self.__supertraits $($access)* .0
+            }
+        }
+
+        $crate::__define_v_table_internal!{@if ($($is_prefix)*)
+            // The first supertrait's vtable is stored at the start of this vtable:
+            {
+                // Safety: both vtables and the list of supertrait vtables are `repr(C)`
+                // and the list is the first field of this vtable.
+                unsafe impl<$($impl_generics)*>
+                $crate::VTablePrefix<dyn $first>
+                for $($vtable)*
+                where
+                    $($where_clause)*
+                {}
+            }
+            else {}
+        }
+
+        $crate::__define_v_table_internal!{@supertrait_upcasts
+            impl_generics = { $($impl_generics)* },
+            vtable = { $($vtable)* },
+            where_clause = { $($where_clause)* },
+            access = { $($access)* .1 },
+            is_prefix = {},
+            supertraits = { $($rest,)* },
+        }
+    };
+    ////////////////////////////////////////////////////////////////////////////////
+    // Utilities:
+    ////////////////////////////////////////////////////////////////////////////////
+    (@if (true $($condition:tt)*) { $($true:tt)* } else { $($false:tt)* } ) => { $($true)* };
+    (@if ($($condition:tt)*) { $($true:tt)* } else { $($false:tt)* } ) => { $($false)* };
+    (@if_has_methods ($($method_name:ident)+) { $($true:tt)* } else { $($false:tt)* } ) => { $($true)* };
+    (@if_has_methods () { $($true:tt)* } else { $($false:tt)* } ) => { $($false)* };
+    // Only matches if every method receiver is a reference:
+    (@if_refs ($([&])*) { $($true:tt)* } else { $($false:tt)* } ) => { $($true)* };
+    (@if_refs ($($receiver:tt)*) { $($true:tt)* } else { $($false:tt)* } ) => { $($false)* };
+    // Only matches if there are methods and every method receiver is `&self`:
+    (@if_shared_refs ($([&])+) { $($true:tt)* } else { $($false:tt)* } ) => { $($true)* };
+    (@if_shared_refs ($($receiver:tt)*) { $($true:tt)* } else { $($false:tt)* } ) => { $($false)* };
+    (@if_extern { extern $abi:literal } { $($true:tt)* } else { $($false:tt)* } ) => { $($true)* };
+    (@if_extern {} { $($true:tt)* } else { $($false:tt)* } ) => { $($false)* };
+    // A function pointer type with the ABI of the vtable:
+    (@abi_fn_ptr { $($abi:tt)* } for< $($lifetime:lifetime),* $(,)? > fn $($rest:tt)*) => {
+        for< $($lifetime),* > $($abi)* fn $($rest)*
+    };
+}
+
+/// Parses a trait definition and define a vtable that can be used to interact
+/// with a type erased object behind a thin pointer when that type implements
+/// the provided trait.
+///
+/// This macro takes a trait definition as input. If the trait has any supertraits
+/// then they should probably be auto traits or traits that have blanket implementations
+/// since the macro will try to implement the trait for thin pointer types such as
+/// [`ThinBox`] and [`Thin`].
+///
+/// Supertraits that were also defined with this macro can instead be listed in a
+/// `#[thin_supertraits(...)]` attribute on the trait. Their vtables are then
+/// stored inside the trait's vtable so that the thin pointer types implement
+/// them as well:
+///
+/// ```
+/// use thin_trait_object::{define_v_table, ThinBox};
+///
+/// define_v_table! {
+///     pub trait Named {
+///         fn name(&self) -> String;
+///     }
+/// }
+/// define_v_table! {
+///     #[thin_supertraits(Named)]
+///     pub trait Animal: Named {
+///         fn legs(&self) -> u32;
+///     }
+/// }
+/// # struct Dog;
+/// # impl Named for Dog { fn name(&self) -> String { "dog".to_string() } }
+/// # impl Animal for Dog { fn legs(&self) -> u32 { 4 } }
+///
+/// let animal = ThinBox::<'_, dyn Animal, ()>::new(Dog, ());
+/// assert_eq!(animal.name(), "dog");
+/// ```
+///
+/// Thin pointers can also be upcast to a forwarded supertrait with
+/// [`ThinBox::upcast`] or, for the first supertrait in the attribute, by
+/// reference with [`Thin::upcast`].
+///
+/// Traits from other libraries don't have a vtable that can be stored, so they
+/// can't be forwarded like this. The exceptions are
+/// [`Display`](core::fmt::Display) and [`Debug`](core::fmt::Debug), which use
+/// [`DisplayVTable`] and [`DebugVTable`]. A forwarded `Debug` is used by the
+/// [`Debug`](core::fmt::Debug) implementations of all thin pointer types to
+/// format the erased object. A forwarded `Display` makes [`Thin`] implement
+/// [`Display`](core::fmt::Display), so it shouldn't be an ordinary supertrait
+/// since the thin pointer types that deref to [`Thin`] can't implement it:
+///
+/// ```
+/// use core::fmt;
+/// use thin_trait_object::{define_v_table, ThinBox};
+///
+/// define_v_table! {
+///     #[thin_supertraits(fmt::Display, fmt::Debug)]
+///     pub trait Shape: fmt::Debug {
+///         fn area(&self) -> u32;
+///     }
+/// }
+/// #[derive(Debug)]
+/// struct Square(u32);
+/// impl fmt::Display for Square {
+///     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+///         write!(f, "square with side {}", self.0)
+///     }
+/// }
+/// impl Shape for Square {
+///     fn area(&self) -> u32 {
+///         self.0 * self.0
+///     }
+/// }
+///
+/// let shape = ThinBox::<'_, dyn Shape, ()>::new(Square(2), ());
+/// assert_eq!(shape.to_string(), "square with side 2");
+/// assert_eq!(format!("{:?}", shape), "ThinBox(Thin { erased: Square(2), common: () })");
+/// ```
+///
+/// Methods of a trait with forwarded supertraits can't name the trait's
+/// associated types through `Self`, use the associated type's name directly
+/// instead.
+///
+/// The [`ThinClone`] marker trait can be used as a supertrait to store a clone
+/// function in the vtable, [`ThinBox`] and [`ThinBoxWithoutCommon`] will then
+/// implement [`Clone`]. Since the boxes can be [`Clone`], traits without any
+/// methods aren't implemented for them. This allows marker traits to have
+/// blanket implementations such as `impl<T: Clone> Marker for T {}`.
+///
+/// Methods whose where clause starts with `Self: Sized` are left out of the
+/// vtable, just like they can't be called on a `dyn Trait`. This allows the
+/// trait to have generic methods, constructors or methods that return `Self`.
+/// Such methods without a default implementation will panic if called on a
+/// thin pointer type.
+///
+/// Write `extern "C"` before the `trait` keyword to make the vtable usable from
+/// C code. All function pointers that C code might call then use the C ABI and
+/// the vtable is `#[repr(C)]` with its fields in this order:
+///
+/// 1. The vtables of the supertraits listed in `#[thin_supertraits(...)]`, in
+///    the listed order.
+/// 2. One function pointer for each method, in the order they were declared.
+///    Methods that take `self` by value free the allocation.
+/// 3. `drop_in_place`, which drops the erased object without freeing it.
+/// 4. `clone`, if [`ThinClone`] is a supertrait, which returns a pointer to a
+///    new allocation.
+/// 5. Fields that are only used by Rust code.
+///
+/// Every function pointer takes a pointer to the start of the allocation as
+/// its first argument, see [`RawThin`] for how that is laid out. A
+/// `#[repr(C)]` attribute on the trait is accepted but not needed, the vtable
+/// always uses that layout. Panics can't unwind through these function
+/// pointers so they will abort the process instead. A C header that declares
+/// the vtable can be generated with [`c_header::c_header`].
+///
+/// ```
+/// use thin_trait_object::{define_v_table, ThinBox};
+///
+/// define_v_table! {
+///     #[repr(C)]
+///     pub extern "C" trait Callback {
+///         fn call(&mut self, value: u32) -> u32;
+///     }
+/// }
+/// impl Callback for u32 {
+///     fn call(&mut self, value: u32) -> u32 {
+///         *self += value;
+///         *self
+///     }
+/// }
+///
+/// let mut callback = ThinBox::<'_, dyn Callback, ()>::new(1, ());
+/// assert_eq!(callback.call(2), 3);
+/// ```
+///
+/// `#[cfg]` attributes on methods are forwarded to the vtable field and the
+/// generated method implementations. Associated types can't have them since
+/// all of the generated code uses them.
+///
+/// This macro can't parse all trait definitions, for example argument patterns
+/// aren't supported. The `#[thin_trait_object]` attribute macro that is
+/// available when the `macros` feature is enabled generates the same code and
+/// supports more trait definitions.
+///
+/// # Generated code
+///
+/// This macro will parse the provided trait definition and then expand to the trait
+/// as is. The macro will also expand to some extra code that defines a vtable
+/// for the trait.
+///
+/// If the macro is used on a trait like this:
+///
+/// ```
+/// thin_trait_object::define_v_table! {
+///     pub trait Number {
+///         fn get(&self) -> u32;
+///     }
+/// }
+/// ```
+///
 /// Then it would expand to some code similar to:
 ///
-/// ```rust,ignore
-/// // Emit the trait as it was defined (without any changes):
-/// pub trait Number {
-///     fn get(&self) -> u32;
-/// }
+/// ```rust,ignore
+/// // Emit the trait as it was defined (without any changes):
+/// pub trait Number {
+///     fn get(&self) -> u32;
+/// }
+///
+/// // Create a new anonymous scope so that the names of the generated items won't
+/// // conflict with anything else that might be in scope.
+/// const _: fn() = || {
+///     // This is the most important part, it defines a struct that holds a function for
+///     // each method in the trait and one extra function to drop a type erased `Box`.
+///     // Uses a fixed layout so that the vtable of the first forwarded supertrait is a prefix of this vtable:
+///     #[repr(C)]
+///     pub struct __VTable<__CommonData> {
+///         // The vtables of supertraits listed in `#[thin_supertraits(...)]` (nested pairs of vtables):
+///         __supertraits: (),
+///         // These function pointers are `extern "C"` if the trait is defined with `extern "C"`:
+///         get: fn(&thin_trait_object::RawThin<Self, thin_trait_object::Split<__CommonData>, thin_trait_object::auto_traits::NoAutoTraits, ()>),
+///         // Drop the erased object without freeing its memory (used by `ThinRc` and `ThinArc`):
+///         __drop_in_place: fn(&mut thin_trait_object::RawThin<Self, thin_trait_object::Split<__CommonData>, thin_trait_object::auto_traits::NoAutoTraits, ()>),
+///         // The remaining fields are only used from Rust:
+///         __layout: fn() -> core::alloc::Layout,
+///         // Used to downcast the erased object:
+///         __type_id: fn() -> core::any::TypeId,
+///         // Used to inspect the erased object (for example when debugging):
+///         __type_name: fn() -> &'static str,
+///         // Used to convert the erased object into an ordinary trait object:
+///         __as_dyn: fn(*mut u8) -> *mut dyn Number,
+///         __into_box_dyn: fn(thin_trait_object::RawThinBox<Self, thin_trait_object::Taken<__CommonData>, thin_trait_object::auto_traits::NoAutoTraits, ()>) -> *mut dyn Number,
+///     }
+///
+///     // This allows associated types to be named in for arguments or return types of functions inside `__VTable`.
+///     impl <__CommonData> Number for __VTable<__CommonData> {
+///         fn get(&self) -> u32 { unimplemented!() }
+///     }
+///     // Enforce that `thin_trait_object::VTable` is only created with types `__T` that implement the `Number` trait.
+///     unsafe impl <__CommonData, __T> thin_trait_object::auto_traits::EnforceAutoTraits<__T> for __VTable<__CommonData> where __T: Number {}
+///     // Allows `Thin` and other thin pointer types to implement all auto traits that are enforced as supertraits of `Number`.
+///     impl <__CommonData> thin_trait_object::auto_traits::VTableEnforcedAutoTraits for __VTable<__CommonData> {
+///         type UncheckedAutoTraitMarker = dyn Number;
+///     }
+///
+///     // Used to find the vtable of `Number` inside the vtable of a trait that forwards it as a supertrait:
+///     impl <'__a, __CommonData> thin_trait_object::__private::VTableOf<__CommonData> for (dyn Number + '__a) {
+///         type VTable = __VTable<__CommonData>;
+///     }
+///     unsafe impl <__CommonData> thin_trait_object::VTableUpcast<dyn Number> for __VTable<__CommonData> {
+///         type VTable = Self;
+///         fn upcast_vtable(&self) -> &Self::VTable { self }
+///     }
+///     unsafe impl <__CommonData> thin_trait_object::VTablePrefix<dyn Number> for __VTable<__CommonData> {}
+///
+///     // Create a vtable for a type `__T` that implements `Number`.
+///     impl <__CommonData, __T> thin_trait_object::__private::ConstVTable<__T> for __VTable<__CommonData> where __T: Number {
+///         const VTABLE: Self = __VTable {
+///             __supertraits: (),
+///             __drop_in_place: |erased| {
+///                 let unerased = unsafe { thin_trait_object::RawThin::as_unerase_mut::<__T>(erased) };
+///                 unsafe { core::ptr::drop_in_place(thin_trait_object::RawThin::as_object_mut(unerased)) };
+///             },
+///             __layout: core::alloc::Layout::new::<__T>,
+///             __type_id: thin_trait_object::__non_static_type_id::<__T>,
+///             __type_name: core::any::type_name::<__T>,
+///             __as_dyn: |object| object as *mut __T as *mut dyn Number,
+///             __into_box_dyn: |erased| {
+///                 let boxed = unsafe { erased.unerase::<__T>() }.into_box();
+///                 Box::into_raw(boxed) as *mut dyn Number
+///             },
+///             get: |__this| {
+///                 // Safety: this vtable function will only be called with type `__T`.
+///                 let unerased = unsafe { thin_trait_object::RawThin::as_unerase::<__T>(__this) };
+///                 <__T as Number>::get(thin_trait_object::RawThin::as_object(unerased))
+///             },
+///         };
+///     }
+///     impl <__CommonData, __T> thin_trait_object::GetThinTraitVTable<__T> for __VTable<__CommonData> where __T: Number {
+///         const VTABLE: thin_trait_object::VTable<Self, __T> = {
+///             // This must be a `'static` reference. Static promotion allows this to compile:
+///             let vtable: &__VTable<__CommonData> = &<Self as thin_trait_object::__private::ConstVTable<__T>>::VTABLE;
+///             // Safety: the vtable will have sensible behavior for `__T`:
+///             unsafe { thin_trait_object::VTable::new(vtable) }
+///         };
+///     }
+///     // Allows `ThinBox` to call the vtable drop function in its `Drop` implementation.
+///     impl <__CommonData> thin_trait_object::VTableDrop<__CommonData> for __VTable<__CommonData> {
+///         unsafe fn drop_erased_in_place(&self, erased: &mut thin_trait_object::RawThin<Self, thin_trait_object::Split<__CommonData>, thin_trait_object::auto_traits::NoAutoTraits, ()>) {
+///             (self.__drop_in_place)(erased)
+///         }
+///         unsafe fn drop_erased_object(&self, object: *mut u8) {
+///             core::ptr::drop_in_place((self.__as_dyn)(object))
+///         }
+///         fn erased_layout(&self) -> core::alloc::Layout {
+///             (self.__layout)()
+///         }
+///         fn erased_type_id(&self) -> core::any::TypeId {
+///             (self.__type_id)()
+///         }
+///         fn erased_type_name(&self) -> &'static str {
+///             (self.__type_name)()
+///         }
+///         fn erased_debug_vtable(&self) -> Option<&thin_trait_object::DebugVTable> {
+///             thin_trait_object::__private::FindDebugVTable::find_debug_vtable(&self.__supertraits)
+///         }
+///     }
+///     impl <__CommonData> thin_trait_object::__private::FindDebugVTable for __VTable<__CommonData> {
+///         fn find_debug_vtable(&self) -> Option<&thin_trait_object::DebugVTable> {
+///             thin_trait_object::__private::FindDebugVTable::find_debug_vtable(&self.__supertraits)
+///         }
+///     }
+///
+///
+///     // Implement `Number` for thin pointer types:
+///     impl <__CommonData, __ThinTrait> Number for thin_trait_object::ThinWithoutCommon<__ThinTrait, __CommonData>
+///     where
+///         __ThinTrait: thin_trait_object::ThinTrait<__CommonData> + Number,
+///         // Either our own vtable or the vtable of a trait that forwards `Number`:
+///         <__ThinTrait as thin_trait_object::ThinTrait<__CommonData>>::VTable: thin_trait_object::VTableUpcast<dyn Number, VTable = __VTable<__CommonData>>,
+///     {
+///         fn get(&self) -> u32 {
+///             let __vtable = unsafe { thin_trait_object::ThinWithoutCommon::get_vtable(&self) };
+///             let __vtable = thin_trait_object::VTableUpcast::upcast_vtable(__vtable);
+///             let __erased_thin = thin_trait_object::RawThin::as_weaker_auto_traits_marker(thin_trait_object::ThinWithoutCommon::as_raw(self));
+///             (__vtable.get)(unsafe { thin_trait_object::__private::CastVTable::cast_vtable(__erased_thin) })
+///         }
+///     }
+///     impl <__CommonData, __ThinTrait> Number for thin_trait_object::ThinBox<__ThinTrait, __CommonData>
+///     where
+///         __ThinTrait: thin_trait_object::ThinTrait<__CommonData> + Number,
+///         // Either our own vtable or the vtable of a trait that forwards `Number`:
+///         <__ThinTrait as thin_trait_object::ThinTrait<__CommonData>>::VTable: thin_trait_object::VTableUpcast<dyn Number, VTable = __VTable<__CommonData>>,
+///     {
+///         fn get(&self) -> u32 {
+///             let __vtable = unsafe { thin_trait_object::ThinWithoutCommon::get_vtable(&self) };
+///             let __vtable = thin_trait_object::VTableUpcast::upcast_vtable(__vtable);
+///             let __erased = thin_trait_object::RawThin::as_weaker_auto_traits_marker(thin_trait_object::ThinWithoutCommon::as_raw(self));
+///             (__vtable.get)(unsafe { thin_trait_object::__private::CastVTable::cast_vtable(__erased) })
+///         }
+///     }
+///     impl <__CommonData, __ThinTrait> Number for thin_trait_object::ThinBoxWithoutCommon<__ThinTrait, __CommonData>
+///     where
+///         __ThinTrait: thin_trait_object::ThinTrait<__CommonData> + Number,
+///         // Either our own vtable or the vtable of a trait that forwards `Number`:
+///         <__ThinTrait as thin_trait_object::ThinTrait<__CommonData>>::VTable: thin_trait_object::VTableUpcast<dyn Number, VTable = __VTable<__CommonData>>,
+///     {
+///         fn get(&self) -> u32 {
+///             let __vtable = unsafe { thin_trait_object::ThinWithoutCommon::get_vtable(&self) };
+///             let __vtable = thin_trait_object::VTableUpcast::upcast_vtable(__vtable);
+///             let __erased = thin_trait_object::RawThin::as_weaker_auto_traits_marker(thin_trait_object::ThinWithoutCommon::as_raw(self));
+///             (__vtable.get)(unsafe { thin_trait_object::__private::CastVTable::cast_vtable(__erased) })
+///         }
+///     }
+///     // `Number` is also implemented for `ThinRc` and `ThinArc` in the same way
+///     // since all of its methods take `&self`.
+///
+///     // Implement `Number` for boxed trait objects so that they can be stored
+///     // behind thin pointers:
+///     impl <__V> Number for thin_trait_object::BoxedDyn<__V> where __V: Number + ?Sized {
+///         fn get(&self) -> u32 {
+///             Number::get(&*self.0)
+///         }
+///     }
 ///
-/// // Create a new anonymous scope so that the names of the generated items won't
-/// // conflict with anything else that might be in scope.
-/// const _: fn() = || {
-///     // This is the most important part, it defines a struct that holds a function for
-///     // each method in the trait and one extra function to drop a type erased `Box`.
-///     pub struct __VTable<__CommonData> {
-///         get: fn(&thin_trait_object::RawThin<Self, thin_trait_object::Split<__CommonData>, thin_trait_object::auto_traits::NoAutoTraits, ()>),
-///         __drop: fn(thin_trait_object::RawThinBox<Self, thin_trait_object::Taken<__CommonData>, thin_trait_object::auto_traits::NoAutoTraits, ()>),
+///     // Implement `ThinTrait` to allow the `__VTable` type to be named from outside
+///     // this anonymous scope.
+///     impl <__CommonData> thin_trait_object::ThinTrait<__CommonData> for dyn Number {
+///         type VTable = __VTable<__CommonData>;
+///         type AutoTraitConfig = ();
+///         type TraitObject = dyn Number;
 ///     }
 ///
-///     // This allows associated types to be named in for arguments or return types of functions inside `__VTable`.
-///     impl <__CommonData> Number for __VTable<__CommonData> {
-///         fn get(&self) -> u32 { unimplemented!() }
+///     // Implement `ThinTrait` for some auto trait combinations to allow for easily
+///     // enforcing some auto traits for the types that are stored behind a thin pointer.
+///     impl <__CommonData> thin_trait_object::ThinTrait<__CommonData> for (dyn Number<> + ::core::marker::Send) {
+///         type VTable = __VTable<__CommonData>;
+///         type AutoTraitConfig = thin_trait_object::auto_traits::HasSend<()>;
+///         type TraitObject = dyn Number;
 ///     }
-///     // Enforce that `thin_trait_object::VTable` is only created with types `__T` that implement the `Number` trait.
-///     unsafe impl <__CommonData, __T> thin_trait_object::auto_traits::EnforceAutoTraits<__T> for __VTable<__CommonData> where __T: Number {}
-///     // Allows `Thin` and other thin pointer types to implement all auto traits that are enforced as supertraits of `Number`.
-///     impl <__CommonData> thin_trait_object::auto_traits::VTableEnforcedAutoTraits for __VTable<__CommonData> {
-///         type UncheckedAutoTraitMarker = dyn Number;
+///     impl <__CommonData> thin_trait_object::ThinTrait<__CommonData> for (dyn Number<> + ::core::marker::Sync) {
+///         type VTable = __VTable<__CommonData>;
+///         type AutoTraitConfig = thin_trait_object::auto_traits::HasSync<()>;
+///         type TraitObject = dyn Number;
+///     }
+///     impl <__CommonData> thin_trait_object::ThinTrait<__CommonData> for (dyn Number<> + ::core::marker::Send + ::core::marker::Sync) {
+///         type VTable = __VTable<__CommonData>;
+///         type AutoTraitConfig = thin_trait_object::auto_traits::HasSend<thin_trait_object::auto_traits::HasSync<()>>;
+///         type TraitObject = dyn Number;
+///     }
+///
+///     // Implement `ThinTraitDyn` for the same trait object types to allow converting
+///     // type erased objects back into ordinary trait objects.
+///     unsafe impl <'__a, '__erased_data: '__a, __CommonData> thin_trait_object::ThinTraitDyn<'__erased_data, __CommonData> for (dyn Number + '__a) {
+///         unsafe fn erased_as_dyn(vtable: &Self::VTable, object: *mut u8) -> *mut Self {
+///             core::mem::transmute((vtable.__as_dyn)(object))
+///         }
+///         // `erased_into_box_dyn` is implemented in the same way.
+///     }
+///     // `ThinTraitDyn` is also implemented for the same auto trait combinations as `ThinTrait`.
+/// };
+/// ```
+#[macro_export]
+macro_rules! define_v_table {
+    ($($token:tt)*) => {
+        $crate::__define_v_table_internal! {@find_supertraits
+            attributes = {},
+            supertraits = {},
+            abi = {},
+            unchecked = { $($token)* },
+        }
+    };
+}
+
+/// This type guarantees that a vtable is well formed for a specific type.
+pub struct VTable<V, T> {
+    vtable: StaticVTableRef<V>,
+    type_info: PhantomData<fn() -> T>,
+}
+impl<V, T> fmt::Debug for VTable<V, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("VTable").finish()
+    }
+}
+impl<V, T> VTable<V, T>
+where
+    V: auto_traits::EnforceAutoTraits<T>,
+{
+    /// Create a new vtable.
+    ///
+    /// # Safety
+    ///
+    /// - All methods in the vtable must have sensible behavior when called.
+    /// - The type `T` must implement all auto traits that are implemented by the
+    /// `<V as auto_traits::VTableEnforcedAutoTraits>::UncheckedAutoTraitMarker`
+    /// type. This should always be guaranteed by the `V: auto_traits::EnforceAutoTraits<T>`
+    /// trait bound.
+    /// - The lifetime for the reference to the vtable type `V` must be long enough
+    /// that it is always valid to use with the type `T`. So it can't have a more
+    /// restrictive lifetime than `T` itself.
+    pub const unsafe fn new(vtable: &V) -> Self {
+        Self {
+            vtable: StaticVTableRef(NonNull::new_unchecked(vtable as *const V as *mut V)),
+            type_info: PhantomData,
+        }
+    }
+}
+
+/// Specifies the VTable type that should be used and what auto traits should be
+/// enforced for all type erased object.
+///
+/// The [`define_v_table`] macro will implement this for the trait object type
+/// of the provided trait.
+///
+/// The `C` type parameter is the common data that is stored inside the type
+/// erased allocation. This is necessary since the common data affects how the
+/// allocation is freed so we need a different vtable for each common data type.
+pub trait ThinTrait<C> {
+    /// The type of the vtable.
+    type VTable: auto_traits::VTableEnforcedAutoTraits
+        + auto_traits::EnforceAutoTraits<
+            <Self::VTable as auto_traits::VTableEnforcedAutoTraits>::UncheckedAutoTraitMarker,
+        > + VTableDrop<C>;
+    /// Indicates what auto traits should be enforced for the erased type.
+    type AutoTraitConfig: auto_traits::AutoTraitConfig<
+        <Self::VTable as auto_traits::VTableEnforcedAutoTraits>::UncheckedAutoTraitMarker,
+    >;
+    /// The trait object type without any auto traits. This identifies the
+    /// trait when a vtable is upcast to the vtable of a supertrait, see
+    /// [`VTableUpcast`].
+    type TraitObject: ?Sized;
+}
+
+/// The auto traits marker type for a `ThinTrait` implementor `V` for a certain
+/// "common" data type `C`.
+type ThinTraitAutoTraitsMarker<V, C> = auto_traits::AutoTraitConfigMarkerType<
+    <V as ThinTrait<C>>::VTable,
+    <V as ThinTrait<C>>::AutoTraitConfig,
+>;
+
+/// This trait should be implemented for a vtable to allow using it in a type's
+/// [`Drop`] implementation.
+pub trait VTableDrop<C>: Sized {
+    /// Drop the type erased object in place without freeing the memory it is
+    /// stored in. This doesn't drop the common data.
+    ///
+    /// Thin boxes are freed by calling this and then deallocating the memory
+    /// with [`erased_layout`](Self::erased_layout) and the box's own
+    /// allocator, see [`RawThinBox::free_via_vtable`].
+    ///
+    /// # Safety
+    ///
+    /// - The type erased object must have the same type as the one that the
+    ///   vtable manages.
+    /// - The object must not be used after this call (it has been dropped).
+    unsafe fn drop_erased_in_place(
+        &self,
+        erased: &mut RawThin<'_, Self, Split<C>, auto_traits::NoAutoTraits, ()>,
+    );
+    /// Drop a type erased object that isn't stored inside a [`RawThin`] value,
+    /// for example an object inside a [`ThinSlice`].
+    ///
+    /// # Safety
+    ///
+    /// - The pointer must point to an object with the same type as the one
+    ///   that the vtable manages.
+    /// - The object must not be used after this call (it has been dropped).
+    unsafe fn drop_erased_object(&self, object: *mut u8);
+    /// The memory layout of the type erased object that the vtable manages.
+    fn erased_layout(&self) -> Layout;
+    /// The [`TypeId`] of the type erased object that the vtable manages.
+    ///
+    /// Lifetimes are ignored when the id is computed so it should only be
+    /// compared to the id of a `'static` type if the erased object is known to
+    /// be `'static`.
+    fn erased_type_id(&self) -> TypeId;
+    /// The name of the type erased object's type, as returned by
+    /// [`core::any::type_name`].
+    fn erased_type_name(&self) -> &'static str;
+    /// The vtable of [`Debug`](fmt::Debug) if it is forwarded as a
+    /// supertrait. The [`Debug`](fmt::Debug) implementations of the thin
+    /// pointer types use it to format the type erased object.
+    fn erased_debug_vtable(&self) -> Option<&DebugVTable> {
+        None
+    }
+}
+
+/// This trait is implemented by the [`define_v_table`] macro for vtables of
+/// traits that have [`ThinClone`] as a supertrait.
+#[cfg(feature = "alloc")]
+#[cfg_attr(feature = "docs", doc(cfg(feature = "alloc")))]
+pub trait VTableClone<C>: VTableDrop<C> {
+    /// Clone the type erased object into a new allocation. The common data
+    /// isn't cloned, the new allocation only has space for it.
+    ///
+    /// # Safety
+    ///
+    /// - The type erased object must have the same type as the one that the
+    ///   vtable manages.
+    unsafe fn clone_erased_box<'a>(
+        &self,
+        erased: &RawThin<'a, Self, Split<C>, auto_traits::NoAutoTraits, ()>,
+    ) -> RawThinBox<'a, Self, Taken<C>, auto_traits::NoAutoTraits, ()>;
+}
+
+/// A marker trait that can be used as a supertrait of a trait that is passed to
+/// the [`define_v_table`] macro. The generated vtable will then store a function
+/// that clones the type erased object, which allows [`ThinBox`] and
+/// [`ThinBoxWithoutCommon`] to implement [`Clone`].
+///
+/// This trait is implemented for all types so that the trait can still be
+/// implemented for the thin pointer types. Instead a vtable can only be created
+/// for types that implement [`Clone`].
+///
+/// Note that the macro looks for the name `ThinClone` so the trait can't be
+/// renamed when it is imported.
+///
+/// # Examples
+///
+/// ```
+/// use thin_trait_object::{define_v_table, ThinBox, ThinClone};
+///
+/// define_v_table!(
+///     trait Greet: ThinClone {
+///         fn greet(&self) -> String;
 ///     }
+/// );
+/// #[derive(Clone)]
+/// struct Greeter(String);
+/// impl Greet for Greeter {
+///     fn greet(&self) -> String { format!("Hello {}", self.0) }
+/// }
+///
+/// let erased = ThinBox::<'_, dyn Greet, u8>::new(Greeter("world".to_string()), 2);
+/// let cloned = erased.clone();
+/// assert_eq!(cloned.greet(), "Hello world");
+/// ```
+pub trait ThinClone {}
+impl<T: ?Sized> ThinClone for T {}
+
+/// Allows converting type erased objects back into ordinary trait objects.
+///
+/// The [`define_v_table`] macro implements this for the trait object type of
+/// the provided trait (the same types that implement [`ThinTrait`]). The
+/// lifetime of the trait object must be shorter than the `'erased_data`
+/// lifetime.
+///
+/// # Safety
+///
+/// The returned pointers must point to the type erased object that was passed
+/// in and the object's type must actually implement the trait object type.
+pub unsafe trait ThinTraitDyn<'erased_data, C>: ThinTrait<C> {
+    /// Get a trait object pointer to a type erased object. The pointer points
+    /// to the object itself, which doesn't need to be stored inside a
+    /// [`RawThin`] value.
+    ///
+    /// # Safety
+    ///
+    /// - The vtable must have been created for the object's type.
+    /// - The pointer must point to a valid object of that type.
+    unsafe fn erased_as_dyn(vtable: &Self::VTable, object: *mut u8) -> *mut Self;
+    /// Move a type erased object into a new heap allocation that is owned by a
+    /// [`Box`]. The returned pointer should be passed to [`Box::from_raw`].
+    ///
+    /// # Safety
+    ///
+    /// The vtable must be the one that is stored inside the type erased object.
+    #[cfg(feature = "alloc")]
+    unsafe fn erased_into_box_dyn(
+        vtable: &Self::VTable,
+        erased: RawThinBox<'erased_data, Self::VTable, Taken<C>, auto_traits::NoAutoTraits, ()>,
+    ) -> *mut Self;
+}
+
+/// A boxed trait object that implements the trait it wraps. This allows it to
+/// be stored inside a thin pointer, see [`ThinBox::from_box_dyn`].
+///
+/// The [`define_v_table`] macro implements the provided trait for this type if
+/// all of the trait's methods take `self` by reference. Methods that take
+/// `self` by value can't be forwarded to the boxed trait object since it is
+/// unsized, so traits with such methods can't be stored this way.
+#[cfg(feature = "alloc")]
+#[cfg_attr(feature = "docs", doc(cfg(feature = "alloc")))]
+#[derive(Debug)]
+pub struct BoxedDyn<V: ?Sized>(pub Box<V>);
+
+/// Get the vtable of a supertrait from the vtable of a subtrait. The supertrait
+/// is identified by its trait object type, for example `dyn Trait`.
+///
+/// The [`define_v_table`] macro implements this for every vtable with the
+/// vtable's own trait and with the supertraits that are listed in a
+/// `#[thin_supertraits(..)]` attribute. The provided trait is then implemented
+/// for thin pointers to any trait object whose vtable implements this.
+///
+/// # Safety
+///
+/// The returned vtable must have sensible behavior for the same erased type as
+/// `self`.
+pub unsafe trait VTableUpcast<Id: ?Sized> {
+    /// The vtable of the supertrait.
+    type VTable;
+    /// Get the vtable of the supertrait.
+    fn upcast_vtable(&self) -> &Self::VTable;
+}
+
+/// Indicates that the vtable of a supertrait is stored at the start of the
+/// vtable of a subtrait. A pointer to the subtrait's vtable is then also a
+/// valid pointer to the supertrait's vtable, which allows upcasting borrowed
+/// thin pointers such as [`Thin`].
+///
+/// The [`define_v_table`] macro implements this for the first trait that is
+/// listed in a `#[thin_supertraits(..)]` attribute.
+///
+/// # Safety
+///
+/// [`VTableUpcast::upcast_vtable`] must return a reference to the start of
+/// `self`.
+pub unsafe trait VTablePrefix<Id: ?Sized>: VTableUpcast<Id> {}
+
+/// The vtable that is stored when [`Display`](fmt::Display) is listed in a
+/// `#[thin_supertraits(..)]` attribute. [`Thin`] then implements
+/// [`Display`](fmt::Display) by formatting the type erased object.
+#[derive(Debug)]
+#[repr(C)]
+pub struct DisplayVTable {
+    fmt: unsafe fn(*const u8, &mut fmt::Formatter<'_>) -> fmt::Result,
+}
+
+/// The vtable that is stored when [`Debug`](fmt::Debug) is listed in a
+/// `#[thin_supertraits(..)]` attribute. The [`Debug`](fmt::Debug)
+/// implementations of the thin pointer types then format the type erased
+/// object instead of only its type name.
+#[derive(Debug)]
+#[repr(C)]
+pub struct DebugVTable {
+    fmt: unsafe fn(*const u8, &mut fmt::Formatter<'_>) -> fmt::Result,
+}
+
+/// Formats a type erased object with the vtable of a formatting trait.
+struct ErasedFmt<'a, V> {
+    vtable: &'a V,
+    object: *const u8,
+}
+impl fmt::Display for ErasedFmt<'_, DisplayVTable> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Safety: the vtable was created for the object's type.
+        unsafe { (self.vtable.fmt)(self.object, f) }
+    }
+}
+impl fmt::Debug for ErasedFmt<'_, DebugVTable> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Safety: the vtable was created for the object's type.
+        unsafe { (self.vtable.fmt)(self.object, f) }
+    }
+}
+
+/// Get the [`TypeId`] of a type that might not be `'static`. All lifetimes in
+/// the type are ignored.
+///
+/// This is used by the [`define_v_table`] macro to store the type of the erased
+/// object in its vtable.
+#[doc(hidden)]
+pub fn __non_static_type_id<T: ?Sized>() -> TypeId {
+    trait NonStaticAny {
+        fn get_type_id(&self) -> TypeId
+        where
+            Self: 'static;
+    }
+    impl<T: ?Sized> NonStaticAny for PhantomData<T> {
+        fn get_type_id(&self) -> TypeId
+        where
+            Self: 'static,
+        {
+            TypeId::of::<T>()
+        }
+    }
+    let marker = PhantomData::<T>;
+    // Safety: type ids don't depend on lifetimes so we can pretend that the
+    // type is `'static` while we get its id.
+    let marker =
+        unsafe { mem::transmute::<&dyn NonStaticAny, &(dyn NonStaticAny + 'static)>(&marker) };
+    marker.get_type_id()
+}
+
+/// Items used by the [`define_v_table`] macro.
+#[doc(hidden)]
+pub mod __private {
+    use super::*;
+
+    #[cfg(feature = "alloc")]
+    pub use alloc::boxed::Box;
+
+    /// The type that vtable methods that take ownership of a type erased
+    /// object are called with.
+    #[cfg(feature = "alloc")]
+    pub type ErasedBox<'erased_data, V, C> =
+        RawThinBox<'erased_data, V, Taken<C>, auto_traits::NoAutoTraits, ()>;
+    /// Without the `alloc` feature there are no boxes that could own a type
+    /// erased object, so the vtable methods that take ownership of an object
+    /// can never be called.
+    #[cfg(not(feature = "alloc"))]
+    pub struct ErasedBox<'erased_data, V, C> {
+        never: Never,
+        _raw: PhantomData<RawThin<'erased_data, V, Taken<C>, auto_traits::NoAutoTraits, ()>>,
+    }
+    #[cfg(not(feature = "alloc"))]
+    enum Never {}
+    #[cfg(not(feature = "alloc"))]
+    impl<V, C> ErasedBox<'_, V, C> {
+        /// Used as the body of vtable methods that can't be called.
+        pub fn unreachable<R>(self) -> R {
+            match self.never {}
+        }
+    }
+    #[cfg(not(feature = "alloc"))]
+    impl<V, C> fmt::Debug for ErasedBox<'_, V, C> {
+        fn fmt(&self, _: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self.never {}
+        }
+    }
+
+    /// A vtable that can be created in a const context. The vtable is then
+    /// promoted to a `'static` reference by [`GetThinTraitVTable::VTABLE`]
+    /// and it can be embedded into the vtable of a subtrait.
+    pub trait ConstVTable<T> {
+        const VTABLE: Self;
+    }
+
+    /// The vtable of a trait object. Unlike [`ThinTrait`] this doesn't require
+    /// the vtable to outlive the trait object, so it can be used to name the
+    /// vtables of supertraits for any common data type.
+    pub trait VTableOf<C> {
+        type VTable;
+    }
+
+    /// Stores the vtables of the supertraits that are forwarded through a
+    /// vtable, as a list of nested pairs that ends with `()`.
+    #[derive(Debug)]
+    #[repr(C)]
+    pub struct SupertraitVTables<H, T>(pub H, pub T);
+
+    // `Display` and `Debug` can be forwarded as supertraits even though they
+    // aren't defined with the `define_v_table` macro:
+    impl<'a, C> VTableOf<C> for dyn fmt::Display + 'a {
+        type VTable = DisplayVTable;
+    }
+    impl<T: fmt::Display> ConstVTable<T> for DisplayVTable {
+        const VTABLE: Self = DisplayVTable {
+            fmt: fmt_erased_display::<T>,
+        };
+    }
+    unsafe fn fmt_erased_display<T: fmt::Display>(
+        object: *const u8,
+        f: &mut fmt::Formatter<'_>,
+    ) -> fmt::Result {
+        fmt::Display::fmt(&*(object as *const T), f)
+    }
+    impl<'a, C> VTableOf<C> for dyn fmt::Debug + 'a {
+        type VTable = DebugVTable;
+    }
+    impl<T: fmt::Debug> ConstVTable<T> for DebugVTable {
+        const VTABLE: Self = DebugVTable {
+            fmt: fmt_erased_debug::<T>,
+        };
+    }
+    unsafe fn fmt_erased_debug<T: fmt::Debug>(
+        object: *const u8,
+        f: &mut fmt::Formatter<'_>,
+    ) -> fmt::Result {
+        fmt::Debug::fmt(&*(object as *const T), f)
+    }
+
+    /// Searches the vtables of forwarded supertraits for the vtable of
+    /// [`Debug`](fmt::Debug), see [`VTableDrop::erased_debug_vtable`].
+    pub trait FindDebugVTable {
+        fn find_debug_vtable(&self) -> Option<&DebugVTable>;
+    }
+    impl FindDebugVTable for () {
+        fn find_debug_vtable(&self) -> Option<&DebugVTable> {
+            None
+        }
+    }
+    impl<H: FindDebugVTable, T: FindDebugVTable> FindDebugVTable for SupertraitVTables<H, T> {
+        fn find_debug_vtable(&self) -> Option<&DebugVTable> {
+            self.0
+                .find_debug_vtable()
+                .or_else(|| self.1.find_debug_vtable())
+        }
+    }
+    impl FindDebugVTable for DebugVTable {
+        fn find_debug_vtable(&self) -> Option<&DebugVTable> {
+            Some(self)
+        }
+    }
+    impl FindDebugVTable for DisplayVTable {
+        fn find_debug_vtable(&self) -> Option<&DebugVTable> {
+            None
+        }
+    }
+
+    /// Change the vtable type parameter of a type erased object so that it can
+    /// be passed to a method from the vtable of a supertrait.
+    ///
+    /// # Safety
+    ///
+    /// The vtable `V2` must have sensible behavior for the erased object. The
+    /// vtable pointer stored in the object isn't changed so it must not be read
+    /// as a `V2` vtable.
+    pub unsafe trait CastVTable<V2> {
+        type Output;
+        unsafe fn cast_vtable(self) -> Self::Output;
+    }
+    unsafe impl<'a, 'erased_data, V, V2, C, M, D> CastVTable<V2>
+        for &'a RawThin<'erased_data, V, C, M, D>
+    where
+        M: ?Sized,
+        V2: 'a,
+    {
+        type Output = &'a RawThin<'erased_data, V2, C, M, D>;
+        unsafe fn cast_vtable(self) -> Self::Output {
+            // Safety: the vtable is stored as a pointer so the layout doesn't
+            // depend on its type.
+            &*((self as *const RawThin<'erased_data, V, C, M, D>)
+                as *const RawThin<'erased_data, V2, C, M, D>)
+        }
+    }
+    unsafe impl<'a, 'erased_data, V, V2, C, M, D> CastVTable<V2>
+        for &'a mut RawThin<'erased_data, V, C, M, D>
+    where
+        M: ?Sized,
+        V2: 'a,
+    {
+        type Output = &'a mut RawThin<'erased_data, V2, C, M, D>;
+        unsafe fn cast_vtable(self) -> Self::Output {
+            // Safety: the vtable is stored as a pointer so the layout doesn't
+            // depend on its type.
+            &mut *((self as *mut RawThin<'erased_data, V, C, M, D>)
+                as *mut RawThin<'erased_data, V2, C, M, D>)
+        }
+    }
+    #[cfg(feature = "alloc")]
+    unsafe impl<'erased_data, V, V2, C, M, D, A> CastVTable<V2>
+        for RawThinBox<'erased_data, V, C, M, D, A>
+    where
+        M: ?Sized,
+    {
+        type Output = RawThinBox<'erased_data, V2, C, M, D, A>;
+        unsafe fn cast_vtable(self) -> Self::Output {
+            // Safety: the vtable is stored as a pointer so the layout doesn't
+            // depend on its type.
+            mem::transmute::<
+                RawThinBox<'erased_data, V, C, M, D, A>,
+                RawThinBox<'erased_data, V2, C, M, D, A>,
+            >(self)
+        }
+    }
+}
+
+/// The layout of a [`RawThin`] allocation with the vtable `V` and the common
+/// data `C` that stores an object with the layout `object`.
 ///
-///     // Create a vtable for a type `__T` that implements `Number`.
-///     impl <__CommonData, __T> thin_trait_object::GetThinTraitVTable<__T> for __VTable<__CommonData> where __T: Number {
-///         fn get_vtable() -> thin_trait_object::VTable<Self, __T> {
-///             // This closure must return a `'static` reference. Static promotion allows this to compile:
-///             let get_vtable =|| -> &_ {
-///                 let vtable: &__VTable<__CommonData> = &__VTable {
-///                     __drop: |erased| { unsafe { erased.unerase::<__T>().free() }; },
-///                     get: |__this| {
-///                         // Safety: this vtable function will only be called with type `__T`.
-///                         let unerased = unsafe { thin_trait_object::RawThin::as_unerase::<__T>(__this) };
-///                         <__T as Number>::get(thin_trait_object::RawThin::as_object(unerased))
-///                     },
-///                 };
-///                 vtable
-///             };
-///             let vtable = get_vtable();
-///             // Safety: the vtable will have sensible behavior for `__T`:
-///             unsafe { thin_trait_object::VTable::new(vtable) }
-///         }
-///     }
-///     // Allows `ThinBox` to call the vtable drop function in its `Drop` implementation.
-///     impl <__CommonData> thin_trait_object::VTableDrop<__CommonData> for __VTable<__CommonData> {
-///         unsafe fn drop_erased_box(&self, erased_box: thin_trait_object::RawThinBox<Self, thin_trait_object::Taken<__CommonData>, thin_trait_object::auto_traits::NoAutoTraits, ()>) {
-///             (self.__drop)(erased_box)
-///         }
-///     }
+/// This is the same layout as the `repr(C)` [`RawThin`] struct would have if
+/// the object's type was known.
+#[cfg(feature = "alloc")]
+fn raw_thin_layout<V, C>(object: Layout) -> Layout {
+    let (header, _) = Layout::new::<StaticVTableRef<V>>()
+        .extend(Layout::new::<C>())
+        .expect("the layout of the common data should be valid");
+    let (layout, _) = header
+        .extend(object)
+        .expect("the layout of an allocated object should be valid");
+    layout.pad_to_align()
+}
+
+/// The layout of an allocation that stores a header of type `H` right before
+/// a [`RawThin`] that has the layout `raw`. Also returns the offset from the
+/// start of the allocation to the [`RawThin`] value.
 ///
+/// The header can be found by subtracting `size_of::<H>()` from the
+/// [`RawThin`] pointer.
+#[cfg(feature = "alloc")]
+fn raw_thin_layout_with_header<H>(raw: Layout) -> (Layout, usize) {
+    let align = raw.align().max(mem::align_of::<H>());
+    let offset = (mem::size_of::<H>() + align - 1) & !(align - 1);
+    let layout = Layout::from_size_align(offset + raw.size(), align)
+        .expect("the layout of an allocated object should be valid");
+    (layout, offset)
+}
+
+/// Allocate memory with the allocator `A` for a header of type `H` followed
+/// by a [`RawThin`] value that has the layout `raw`. Returns a pointer to where
+/// the [`RawThin`] value should be written.
+#[cfg(feature = "alloc")]
+fn alloc_raw_thin_with_header_in<H, A>(raw: Layout, allocator: &A) -> NonNull<u8>
+where
+    A: Allocator + ?Sized,
+{
+    match try_alloc_raw_thin_with_header_in::<H, A>(raw, allocator) {
+        Ok(raw_ptr) => raw_ptr,
+        Err(_) => alloc::alloc::handle_alloc_error(raw_thin_layout_with_header::<H>(raw).0),
+    }
+}
+
+/// Like [`alloc_raw_thin_with_header_in`] but returns an error instead of
+/// aborting if the allocator fails to allocate memory.
+#[cfg(feature = "alloc")]
+fn try_alloc_raw_thin_with_header_in<H, A>(
+    raw: Layout,
+    allocator: &A,
+) -> Result<NonNull<u8>, AllocError>
+where
+    A: Allocator + ?Sized,
+{
+    let (layout, offset) = raw_thin_layout_with_header::<H>(raw);
+    let start = allocator.allocate(layout)?;
+    // Safety: the offset is inside the allocation and the allocation starts at
+    // a non-null pointer.
+    Ok(unsafe { NonNull::new_unchecked(start.as_ptr().add(offset)) })
+}
+
+/// Allocate memory for a header of type `H` followed by a [`RawThin`] value
+/// and move both values into it. Returns a pointer to the [`RawThin`] value.
+#[cfg(feature = "alloc")]
+fn alloc_raw_thin_with_header<'erased_data, H, V, C, M, T>(
+    header: H,
+    raw: RawThin<'erased_data, V, C, M, T>,
+) -> NonNull<RawThin<'erased_data, V, C, M, T>>
+where
+    M: ?Sized,
+{
+    let raw_ptr =
+        alloc_raw_thin_with_header_in::<H, _>(raw_thin_layout::<V, C>(Layout::new::<T>()), &Global)
+            .cast::<RawThin<'erased_data, V, C, M, T>>();
+    // Safety: `raw_thin_layout_with_header` ensures that both values are
+    // aligned and fit inside the allocation.
+    unsafe {
+        ptr::write(raw_ptr.as_ptr(), raw);
+        ptr::write((raw_ptr.as_ptr() as *mut H).sub(1), header);
+    }
+    raw_ptr
+}
+
+/// Free memory that was allocated by [`alloc_raw_thin_with_header_in`]. This
+/// doesn't drop the header or the [`RawThin`] value.
 ///
-///     // Implement `Number` for thin pointer types:
-///     impl <__CommonData, __ThinTrait> Number for thin_trait_object::ThinWithoutCommon<__ThinTrait, __CommonData>
-///     where
-///         __ThinTrait: thin_trait_object::ThinTrait<__CommonData, VTable = __VTable<__CommonData>>,
-///     {
-///         fn get(&self) -> u32 {
-///             let __vtable = unsafe { thin_trait_object::ThinWithoutCommon::get_vtable(&self) };
-///             let __erased_thin = thin_trait_object::RawThin::as_weaker_auto_traits_marker(thin_trait_object::ThinWithoutCommon::as_raw(self));
-///             (__vtable.get)(__erased_thin)
-///         }
-///     }
-///     impl <__CommonData, __ThinTrait> Number for thin_trait_object::ThinBox<__ThinTrait, __CommonData>
-///     where
-///         __ThinTrait: thin_trait_object::ThinTrait<__CommonData, VTable =__VTable<__CommonData>>,
-///     {
-///         fn get(&self) -> u32 {
-///             let __vtable = unsafe { thin_trait_object::ThinWithoutCommon::get_vtable(&self) };
-///             let __erased = thin_trait_object::RawThin::as_weaker_auto_traits_marker(thin_trait_object::ThinWithoutCommon::as_raw(self));
-///             (__vtable.get)(__erased)
-///         }
-///     }
-///     impl <__CommonData, __ThinTrait> Number for thin_trait_object::ThinBoxWithoutCommon<__ThinTrait, __CommonData>
-///     where
-///         __ThinTrait: thin_trait_object::ThinTrait<__CommonData, VTable = __VTable<__CommonData>>,
-///     {
-///         fn get(&self) -> u32 {
-///             let __vtable = unsafe { thin_trait_object::ThinWithoutCommon::get_vtable(&self) };
-///             let __erased = thin_trait_object::RawThin::as_weaker_auto_traits_marker(thin_trait_object::ThinWithoutCommon::as_raw(self));
-///             (__vtable.get)(__erased)
-///         }
-///     }
+/// # Safety
 ///
-///     // Implement `ThinTrait` to allow the `__VTable` type to be named from outside
-///     // this anonymous scope.
-///     impl <__CommonData> thin_trait_object::ThinTrait<__CommonData> for dyn Number {
-///         type VTable = __VTable<__CommonData>;
-///         type AutoTraitConfig = ();
-///     }
+/// The pointer must have been returned from [`alloc_raw_thin_with_header_in`]
+/// with the same header type and an allocator that is equal to `allocator`.
+/// `raw_layout` must be the layout of the [`RawThin`] value that was stored in
+/// the allocation.
+#[cfg(feature = "alloc")]
+unsafe fn dealloc_raw_thin_with_header_in<H, A>(raw_ptr: *mut u8, raw_layout: Layout, allocator: &A)
+where
+    A: Allocator + ?Sized,
+{
+    let (layout, offset) = raw_thin_layout_with_header::<H>(raw_layout);
+    allocator.deallocate(NonNull::new_unchecked(raw_ptr.sub(offset)), layout);
+}
+
+/// Free memory that was allocated by [`alloc_raw_thin_with_header`]. This
+/// doesn't drop the header or the [`RawThin`] value.
 ///
-///     // Implement `ThinTrait` for some auto trait combinations to allow for easily
-///     // enforcing some auto traits for the types that are stored behind a thin pointer.
-///     impl <__CommonData> thin_trait_object::ThinTrait<__CommonData> for (dyn Number<> + ::core::marker::Send) {
-///         type VTable = __VTable<__CommonData>;
-///         type AutoTraitConfig = thin_trait_object::auto_traits::HasSend<()>;
-///     }
-///     impl <__CommonData> thin_trait_object::ThinTrait<__CommonData> for (dyn Number<> + ::core::marker::Sync) {
-///         type VTable = __VTable<__CommonData>;
-///         type AutoTraitConfig = thin_trait_object::auto_traits::HasSync<()>;
-///     }
-///     impl <__CommonData> thin_trait_object::ThinTrait<__CommonData> for (dyn Number<> + ::core::marker::Send + ::core::marker::Sync) {
-///         type VTable = __VTable<__CommonData>;
-///         type AutoTraitConfig = thin_trait_object::auto_traits::HasSend<thin_trait_object::auto_traits::HasSync<()>>;
-///     }
-/// };
-/// ```
-#[macro_export]
-macro_rules! define_v_table {
-    ($($token:tt)*) => {
-        $($token)*
-        $crate::__define_v_table_internal! {@input
-            $($token)*
-        }
-    };
+/// # Safety
+///
+/// The pointer must have been returned from [`alloc_raw_thin_with_header`]
+/// with the same header type and `raw_layout` must be the layout of the
+/// [`RawThin`] value that was stored in the allocation.
+#[cfg(feature = "alloc")]
+unsafe fn dealloc_raw_thin_with_header<H>(raw_ptr: *mut u8, raw_layout: Layout) {
+    dealloc_raw_thin_with_header_in::<H, _>(raw_ptr, raw_layout, &Global);
 }
 
-/// This type guarantees that a vtable is well formed for a specific type.
-pub struct VTable<V, T> {
-    vtable: StaticVTableRef<V>,
-    type_info: PhantomData<fn() -> T>,
+/// Gets a vtable with that has sensible behavior for the `T` type.
+pub trait GetThinTraitVTable<T>: Sized {
+    /// A vtable that has sensible behavior for the type `T`. Unlike
+    /// [`get_vtable`](GetThinTraitVTable::get_vtable) this can be used in const
+    /// contexts, for example to create an [`OwnedThin`] in a `static`.
+    const VTABLE: VTable<Self, T>;
+
+    /// Get a vtable that has sensible behavior for the type `T`.
+    fn get_vtable() -> VTable<Self, T> {
+        Self::VTABLE
+    }
 }
-impl<V, T> fmt::Debug for VTable<V, T> {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.debug_struct("VTable").finish()
+
+pub mod auto_traits {
+    //! Specifies what auto traits a thin trait implements.
+
+    use core::marker::PhantomData;
+
+    /// Allows a vtable to guarantee that some auto traits will always be implemented.
+    /// The vtable type must also implement [`EnforceAutoTraits`] on itself to ensure
+    /// a [`VTable`](super::VTable) type can't be constructed for a type that doesn't
+    /// implement the correct auto traits.
+    pub trait VTableEnforcedAutoTraits {
+        /// A type that implements all auto traits that are enforced. If its possible
+        /// to construct a vtable for a type that doesn't implement all of these auto
+        /// traits then that can lead to unsound behavior. Ensure that `GetThinTraitVTable<V>`
+        /// is only implemented for types `V` that implements all of the auto traits.
+        ///
+        /// This is useful since a trait object implements all auto traits that are
+        /// added as supertraits to its trait. So we can set this type to a trait
+        /// object and then that trait will enforce the auto traits if we use it as
+        /// a trait bound in the [`GetThinTraitVTable`](super::GetThinTraitVTable)
+        /// implementation.
+        type UncheckedAutoTraitMarker: ?Sized;
+    }
+
+    /// The auto traits marker type that implements the auto traits guaranteed by
+    /// a vtable (`VTable`) with a certain auto trait config (`Config`).
+    pub type AutoTraitConfigMarkerType<VTable, Config> = <Config as AutoTraitConfig<
+        <VTable as VTableEnforcedAutoTraits>::UncheckedAutoTraitMarker,
+    >>::MarkerType;
+
+    /// Used to enforce that a type (`Self`) has the auto traits specified by an
+    /// [`AutoTraitConfig`] (specified with the `A` type parameter).
+    ///
+    /// # Safety
+    ///
+    /// This trait must uphold the same safety invariants as the [`EnforceAutoTraits`]
+    /// trait.
+    pub unsafe trait HasAutoTraits<A: ?Sized> {}
+    unsafe impl<T: ?Sized, A: ?Sized> HasAutoTraits<A> for T where A: EnforceAutoTraits<T> {}
+
+    /// Implementation details for [`HasAutoTraits`]. This is implement on all
+    /// "auto trait config" types for types `T` that have the correct auto traits.
+    ///
+    /// This trait is also implemented on vtable types for types `T` that uphold
+    /// the vtable default auto traits specified via the vtable type's
+    /// [`VTableEnforcedAutoTraits::UncheckedAutoTraitMarker`].
+    ///
+    /// # Safety
+    ///
+    /// If the `Self` type implements [`AutoTraitConfig`] then it must do so in
+    /// a way that ensures the resulting marker type ([`AutoTraitConfig::MarkerType`])
+    /// only implements the auto traits that are enforced/guaranteed by this trait.
+    pub unsafe trait EnforceAutoTraits<T: ?Sized> {}
+
+    /// Implemented for config types that enforce that certain auto traits are
+    /// implemented for a type erased object.
+    ///
+    /// # Safety
+    ///
+    /// The specified `MarkerType` must not implement auto traits that are not
+    /// enforced via the the [`EnforceAutoTraits`] trait.
+    pub unsafe trait AutoTraitConfig<T: ?Sized> {
+        /// A type that implements all auto traits of `T` and all auto traits
+        /// that are enforced by the auto traits config.
+        type MarkerType: ?Sized;
+    }
+
+    // Base case:
+    unsafe impl<T: ?Sized> EnforceAutoTraits<T> for () {}
+    unsafe impl<T: ?Sized> AutoTraitConfig<T> for () {
+        type MarkerType = T;
+    }
+
+    /// Use this to ensure we never construct a wrapper type.
+    #[derive(Debug)]
+    enum Never {}
+
+    macro_rules! define_auto_trait_wrappers {
+        ($( $( #[$attr:meta] )* $wrapper_name:ident => $(#[unsafe] $(=>;;; never $is_unsafe:ident)?)? $auto_trait:path ),* $(,)?) => {
+            $(
+                $(#[$attr])*
+                #[derive(Debug)]
+                pub struct $wrapper_name<I: ?Sized>(PhantomData<I>, Never);
+                unsafe impl<T: ?Sized, I: ?Sized> EnforceAutoTraits<T> for $wrapper_name<I>
+                where
+                    I: EnforceAutoTraits<T>,
+                    T: $auto_trait,
+                {
+                }
+                unsafe impl<T: ?Sized, I: ?Sized> AutoTraitConfig<T> for $wrapper_name<I> where I: AutoTraitConfig<T> {
+                    type MarkerType = $wrapper_name<<I as AutoTraitConfig<T>>::MarkerType>;
+                }
+                define_auto_trait_wrappers!(@if ($(true $($is_unsafe)?)?) {
+                    unsafe impl<I: ?Sized> $auto_trait for $wrapper_name<I> {}
+                } else {
+                    impl<I: ?Sized> $auto_trait for $wrapper_name<I> {}
+                });
+            )*
+        };
+        (@if (true $($condition:tt)*) { $($true:tt)* } else { $($false:tt)* } ) => { $($true)* };
+        (@if ($($condition:tt)*) { $($true:tt)* } else { $($false:tt)* } ) => { $($false)* };
+    }
+    define_auto_trait_wrappers!(
+        /// Ensures that an erased type implements the [`Send`] auto trait.
+        HasSend => #[unsafe] Send,
+        /// Ensures that an erased type implements the [`Sync`] auto trait.
+        HasSync => #[unsafe] Sync,
+        /// Ensures that an erased type implements the [`Unpin`] auto trait.
+        HasUnpin => Unpin,
+    );
+
+    #[cfg(feature = "std")]
+    define_auto_trait_wrappers!(
+        /// Ensures that an erased type implements the [`std::panic::UnwindSafe`] auto trait.
+        HasUnwindSafe => std::panic::UnwindSafe,
+        /// Ensures that an erased type implements the [`std::panic::RefUnwindSafe`] auto trait.
+        HasRefUnwindSafe => std::panic::RefUnwindSafe,
+    );
+
+    /// Trait objects for a trait without supertraits don't implement any auto traits.
+    trait NoAutoTraitsTrait {}
+
+    /// This type relaxes all auto trait requirements. The marker type indicates
+    /// that no auto trait should be implemented for a type erased object.
+    #[derive(Debug)]
+    pub struct NoAutoTraits(PhantomData<dyn NoAutoTraitsTrait>, Never);
+    unsafe impl<T: ?Sized> EnforceAutoTraits<T> for NoAutoTraits {}
+    unsafe impl<T: ?Sized> AutoTraitConfig<T> for NoAutoTraits {
+        type MarkerType = NoAutoTraits;
+    }
+}
+
+/// The same as [`ThinBox`] except the common data has been moved out and is no
+/// longer available.
+#[cfg(feature = "alloc")]
+#[cfg_attr(feature = "docs", doc(cfg(feature = "alloc")))]
+#[repr(transparent)]
+pub struct ThinBoxWithoutCommon<'erased_data, V, C, A = Global>
+where
+    V: ThinTrait<C> + ?Sized,
+    A: Allocator,
+{
+    ptr: NonNull<ThinWithoutCommon<'erased_data, V, C>>,
+    _allocator: PhantomData<A>,
+}
+// Safety: the box owns the type erased object and the allocator.
+#[cfg(feature = "alloc")]
+unsafe impl<'erased_data, V, C, A> Send for ThinBoxWithoutCommon<'erased_data, V, C, A>
+where
+    V: ThinTrait<C> + ?Sized,
+    A: Allocator + Send,
+    ThinWithoutCommon<'erased_data, V, C>: Send,
+{
+}
+#[cfg(feature = "alloc")]
+unsafe impl<'erased_data, V, C, A> Sync for ThinBoxWithoutCommon<'erased_data, V, C, A>
+where
+    V: ThinTrait<C> + ?Sized,
+    A: Allocator + Sync,
+    ThinWithoutCommon<'erased_data, V, C>: Sync,
+{
+}
+#[cfg(feature = "alloc")]
+#[cfg(feature = "std")]
+impl<'erased_data, V, C, A> std::panic::UnwindSafe for ThinBoxWithoutCommon<'erased_data, V, C, A>
+where
+    V: ThinTrait<C> + ?Sized,
+    A: Allocator + std::panic::UnwindSafe,
+    ThinWithoutCommon<'erased_data, V, C>: std::panic::UnwindSafe,
+{
+}
+#[cfg(feature = "alloc")]
+impl<'erased_data, V> ThinBoxWithoutCommon<'erased_data, V, ()>
+where
+    V: ThinTrait<()> + ?Sized,
+{
+    /// Create a new [`ThinBoxWithoutCommon`] that stores some data in a heap allocation.
+    pub fn new<T>(x: T) -> Self
+    where
+        T: auto_traits::HasAutoTraits<V::AutoTraitConfig> + 'erased_data,
+        V::VTable: GetThinTraitVTable<T>,
+    {
+        Self::new_in(x, Global)
+    }
+    /// Create a new [`ThinBoxWithoutCommon`] that stores some data in a heap
+    /// allocation. The object is given back if the allocation fails.
+    pub fn try_new<T>(x: T) -> Result<Self, T>
+    where
+        T: auto_traits::HasAutoTraits<V::AutoTraitConfig> + 'erased_data,
+        V::VTable: GetThinTraitVTable<T>,
+    {
+        Self::try_new_in(x, Global)
+    }
+    /// Create a new [`ThinBoxWithoutCommon`] that stores a boxed trait object.
+    ///
+    /// The [`Box`] is kept as is inside a new allocation, so the object is
+    /// reached through two pointers and two allocations are used. Prefer
+    /// [`ThinBoxWithoutCommon::new`] if the concrete type is known. Only traits whose
+    /// methods all take `self` by reference are supported, see [`BoxedDyn`].
+    pub fn from_box_dyn(boxed: Box<V>) -> Self
+    where
+        BoxedDyn<V>: auto_traits::HasAutoTraits<V::AutoTraitConfig> + 'erased_data,
+        V::VTable: GetThinTraitVTable<BoxedDyn<V>>,
+    {
+        Self::new(BoxedDyn(boxed))
     }
 }
-impl<V, T> VTable<V, T>
+#[cfg(feature = "alloc")]
+impl<'erased_data, V, C> ThinBoxWithoutCommon<'erased_data, V, C>
 where
-    V: auto_traits::EnforceAutoTraits<T>,
+    V: ThinTrait<C> + ?Sized,
 {
-    /// Create a new vtable.
+    /// Convert a [`ThinBoxWithoutCommon`] into a pointer to its allocation,
+    /// see [`ThinBox::into_raw_ptr`].
+    pub fn into_raw_ptr(this: Self) -> NonNull<ThinWithoutCommon<'erased_data, V, C>> {
+        ManuallyDrop::new(this).ptr
+    }
+    /// Take ownership of an allocation that was converted into a pointer with
+    /// [`ThinBoxWithoutCommon::into_raw_ptr`].
     ///
     /// # Safety
     ///
-    /// - All methods in the vtable must have sensible behavior when called.
-    /// - The type `T` must implement all auto traits that are implemented by the
-    /// `<V as auto_traits::VTableEnforcedAutoTraits>::UncheckedAutoTraitMarker`
-    /// type. This should always be guaranteed by the `V: auto_traits::EnforceAutoTraits<T>`
-    /// trait bound.
-    /// - The lifetime for the reference to the vtable type `V` must be long enough
-    /// that it is always valid to use with the type `T`. So it can't have a more
-    /// restrictive lifetime than `T` itself.
-    pub unsafe fn new(vtable: &V) -> Self {
+    /// The pointer must have been returned by [`ThinBoxWithoutCommon::into_raw_ptr`]
+    /// (or derived from a reference returned by [`ThinBoxWithoutCommon::leak`]
+    /// for a box that uses the [`Global`] allocator) for the same trait object
+    /// type `V` and common data type `C`, and it can only be converted back
+    /// once.
+    pub unsafe fn from_raw_ptr(ptr: NonNull<ThinWithoutCommon<'erased_data, V, C>>) -> Self {
         Self {
-            vtable: StaticVTableRef(vtable.into()),
-            type_info: PhantomData,
+            ptr,
+            _allocator: PhantomData,
         }
     }
 }
-
-/// Specifies the VTable type that should be used and what auto traits should be
-/// enforced for all type erased object.
-///
-/// The [`define_v_table`] macro will implement this for the trait object type
-/// of the provided trait.
-///
-/// The `C` type parameter is the common data that is stored inside the type
-/// erased allocation. This is necessary since the common data affects how the
-/// allocation is freed so we need a different vtable for each common data type.
-pub trait ThinTrait<C> {
-    /// The type of the vtable.
-    type VTable: auto_traits::VTableEnforcedAutoTraits
-        + auto_traits::EnforceAutoTraits<
-            <Self::VTable as auto_traits::VTableEnforcedAutoTraits>::UncheckedAutoTraitMarker,
-        > + VTableDrop<C>;
-    /// Indicates what auto traits should be enforced for the erased type.
-    type AutoTraitConfig: auto_traits::AutoTraitConfig<
-        <Self::VTable as auto_traits::VTableEnforcedAutoTraits>::UncheckedAutoTraitMarker,
-    >;
+#[cfg(feature = "alloc")]
+impl<'erased_data, V, A> ThinBoxWithoutCommon<'erased_data, V, (), A>
+where
+    V: ThinTrait<()> + ?Sized,
+    A: Allocator,
+{
+    /// Create a new [`ThinBoxWithoutCommon`] that stores some data in a heap
+    /// allocation that is managed by `allocator`.
+    pub fn new_in<T>(x: T, allocator: A) -> Self
+    where
+        T: auto_traits::HasAutoTraits<V::AutoTraitConfig> + 'erased_data,
+        V::VTable: GetThinTraitVTable<T>,
+    {
+        Self::from_raw(
+            RawThinBox::new_in(x, (), allocator)
+                .free_common_data()
+                .with_auto_trait_config::<V::AutoTraitConfig>()
+                .erase(),
+        )
+    }
+    /// Create a new [`ThinBoxWithoutCommon`] that stores some data in a heap
+    /// allocation that is managed by `allocator`. The object is given back if
+    /// the allocation fails.
+    pub fn try_new_in<T>(x: T, allocator: A) -> Result<Self, T>
+    where
+        T: auto_traits::HasAutoTraits<V::AutoTraitConfig> + 'erased_data,
+        V::VTable: GetThinTraitVTable<T>,
+    {
+        match RawThinBox::try_new_in(x, (), allocator) {
+            Ok(raw) => Ok(Self::from_raw(
+                raw.free_common_data()
+                    .with_auto_trait_config::<V::AutoTraitConfig>()
+                    .erase(),
+            )),
+            Err((x, ())) => Err(x),
+        }
+    }
 }
+#[cfg(feature = "alloc")]
+impl<'erased_data, V, C, A> ThinBoxWithoutCommon<'erased_data, V, C, A>
+where
+    V: ThinTrait<C> + ?Sized,
+    A: Allocator,
+{
+    /// Put some common data into the heap allocation that stores the type erased
+    /// object.
+    pub fn put_common(this: Self, common: C) -> ThinBox<'erased_data, V, C, A> {
+        ThinBox::from_raw(Self::into_raw(this).put_common_data(common))
+    }
 
-/// The auto traits marker type for a `ThinTrait` implementor `V` for a certain
-/// "common" data type `C`.
-type ThinTraitAutoTraitsMarker<V, C> = auto_traits::AutoTraitConfigMarkerType<
-    <V as ThinTrait<C>>::VTable,
-    <V as ThinTrait<C>>::AutoTraitConfig,
->;
+    /// Upcast to a trait object of a supertrait without changing the auto
+    /// traits, see [`ThinBox::upcast`].
+    pub fn upcast<U>(this: Self) -> ThinBoxWithoutCommon<'erased_data, U, C, A>
+    where
+        U: ThinTrait<C, AutoTraitConfig = V::AutoTraitConfig> + ?Sized,
+        V::VTable: VTableUpcast<U::TraitObject, VTable = <U as ThinTrait<C>>::VTable>,
+        V::AutoTraitConfig: auto_traits::AutoTraitConfig<
+            <U::VTable as auto_traits::VTableEnforcedAutoTraits>::UncheckedAutoTraitMarker,
+        >,
+    {
+        let raw = Self::into_raw(this).upcast_vtable::<U::TraitObject>();
+        // Safety: the auto traits of a supertrait are implied by the auto traits
+        // of the subtrait and the auto trait configs are the same.
+        ThinBoxWithoutCommon::from_raw(unsafe { raw.cast_auto_traits_marker() })
+    }
 
-/// This trait should be implemented for a vtable to allow using it in a type's
-/// [`Drop`] implementation.
-pub trait VTableDrop<C>: Sized {
-    /// Drop an erased box.
+    /// The allocator that manages the heap allocation.
+    pub fn allocator(this: &Self) -> &A {
+        // Safety: the allocator is stored right before the `RawThin` value.
+        unsafe { &*(this.ptr.as_ptr() as *const A).sub(1) }
+    }
+
+    /// Convert a [`ThinBoxWithoutCommon`] to a [`RawThinBox`]. This allows for
+    /// a lower level, more powerful, API.
+    pub fn into_raw(
+        this: Self,
+    ) -> RawThinBox<
+        'erased_data,
+        <V as ThinTrait<C>>::VTable,
+        Taken<C>,
+        ThinTraitAutoTraitsMarker<V, C>,
+        (),
+        A,
+    > {
+        let mut this = ManuallyDrop::new(this);
+        // Safety: the `ManuallyDrop` wrapper ensures we never use `this` again.
+        unsafe { Self::take_raw(&mut *this) }
+    }
+
+    /// Take a [`ThinBoxWithoutCommon`] and create a [`RawThinBox`] instead. This
+    /// allows for a lower level, more powerful, API.
     ///
     /// # Safety
     ///
-    /// - The type erased box must contain an object with the same type as the one
-    /// that the vtable manages.
-    unsafe fn drop_erased_box(
-        &self,
-        erased_box: RawThinBox<'_, Self, Taken<C>, auto_traits::NoAutoTraits, ()>,
-    );
+    /// `Self` must never be used after this function has been called, this includes ensuring that
+    /// `Self` is not dropped.
+    pub unsafe fn take_raw(
+        this: &mut Self,
+    ) -> RawThinBox<
+        'erased_data,
+        <V as ThinTrait<C>>::VTable,
+        Taken<C>,
+        ThinTraitAutoTraitsMarker<V, C>,
+        (),
+        A,
+    > {
+        // Safety: `ThinWithoutCommon` is a `repr(transparent)` struct around `RawThin` and we ensured
+        // the types inside `ThinWithoutCommon` lines up with the ones inside RawThin.
+        // `RawThinBox` doesn't make any guarantees about the state of its
+        // content, so the user must assume that the wrapped value could already
+        // be dropped and can't do anything to it safely.
+        RawThinBox {
+            ptr: this.ptr.cast(),
+            _allocator: PhantomData,
+        }
+    }
+    /// Convert a [`RawThinBox`] to a [`ThinBoxWithoutCommon`]. This allows for
+    /// a more convent, more higher level API.
+    pub fn from_raw(
+        raw: RawThinBox<
+            'erased_data,
+            <V as ThinTrait<C>>::VTable,
+            Taken<C>,
+            ThinTraitAutoTraitsMarker<V, C>,
+            (),
+            A,
+        >,
+    ) -> Self {
+        // Safety: `ThinWithoutCommon` is a `repr(transparent)` struct around `RawThin` and we ensured
+        // the types inside `ThinWithoutCommon` lines up with the ones inside RawThin.
+        Self {
+            ptr: raw.ptr.cast(),
+            _allocator: PhantomData,
+        }
+    }
+    /// Leak the allocation so that it is never freed, see [`ThinBox::leak`].
+    pub fn leak<'a>(this: Self) -> &'a mut ThinWithoutCommon<'a, V, C>
+    where
+        'erased_data: 'a,
+        A: 'a,
+    {
+        let ptr = ManuallyDrop::new(this).ptr.as_ptr();
+        // Safety: the allocation is never freed and the erased object outlives
+        // the shorter lifetime.
+        unsafe { &mut *ptr.cast::<ThinWithoutCommon<'a, V, C>>() }
+    }
 }
-
-/// Gets a vtable with that has sensible behavior for the `T` type.
-pub trait GetThinTraitVTable<T>: Sized {
-    /// Get a vtable that has sensible behavior for the type `T`.
-    fn get_vtable() -> VTable<Self, T>;
+#[cfg(feature = "alloc")]
+impl<'erased_data, V, C> ThinBoxWithoutCommon<'erased_data, V, C>
+where
+    V: ThinTraitDyn<'erased_data, C> + ?Sized,
+{
+    /// Move the type erased object into an ordinary [`Box`].
+    pub fn into_box_dyn(this: Self) -> Box<V> {
+        let erased = Self::into_raw(this).weaken_auto_traits_marker();
+        let vtable = erased.raw().vtable.static_ref();
+        // Safety: the vtable is stored inside the type erased object.
+        unsafe { Box::from_raw(V::erased_into_box_dyn(vtable, erased)) }
+    }
 }
-
-pub mod auto_traits {
-    //! Specifies what auto traits a thin trait implements.
-
-    use core::marker::PhantomData;
-
-    /// Allows a vtable to guarantee that some auto traits will always be implemented.
-    /// The vtable type must also implement [`EnforceAutoTraits`] on itself to ensure
-    /// a [`VTable`](super::VTable) type can't be constructed for a type that doesn't
-    /// implement the correct auto traits.
-    pub trait VTableEnforcedAutoTraits {
-        /// A type that implements all auto traits that are enforced. If its possible
-        /// to construct a vtable for a type that doesn't implement all of these auto
-        /// traits then that can lead to unsound behavior. Ensure that `GetThinTraitVTable<V>`
-        /// is only implemented for types `V` that implements all of the auto traits.
-        ///
-        /// This is useful since a trait object implements all auto traits that are
-        /// added as supertraits to its trait. So we can set this type to a trait
-        /// object and then that trait will enforce the auto traits if we use it as
-        /// a trait bound in the [`GetThinTraitVTable`](super::GetThinTraitVTable)
-        /// implementation.
-        type UncheckedAutoTraitMarker: ?Sized;
+#[cfg(feature = "alloc")]
+impl<V, C, A> ThinBoxWithoutCommon<'static, V, C, A>
+where
+    V: ThinTrait<C> + ?Sized,
+    A: Allocator,
+{
+    /// Move the type erased object out of the heap allocation if it has the
+    /// type `T`. Otherwise the same [`ThinBoxWithoutCommon`] is returned as an
+    /// error.
+    pub fn downcast<T: 'static>(this: Self) -> Result<T, Self> {
+        if !ThinWithoutCommon::is::<T>(&this) {
+            return Err(this);
+        }
+        // Safety: we just checked that the erased object has the type `T`.
+        let unerased = unsafe { Self::into_raw(this).unerase::<T>() };
+        Ok(unerased.into_inner())
     }
+}
+#[cfg(feature = "alloc")]
+impl<'erased_data, V, C, A> Deref for ThinBoxWithoutCommon<'erased_data, V, C, A>
+where
+    V: ThinTrait<C> + ?Sized,
+    A: Allocator,
+{
+    type Target = ThinWithoutCommon<'erased_data, V, C>;
 
-    /// The auto traits marker type that implements the auto traits guaranteed by
-    /// a vtable (`VTable`) with a certain auto trait config (`Config`).
-    pub type AutoTraitConfigMarkerType<VTable, Config> = <Config as AutoTraitConfig<
-        <VTable as VTableEnforcedAutoTraits>::UncheckedAutoTraitMarker,
-    >>::MarkerType;
+    fn deref(&self) -> &Self::Target {
+        // Safety: the box owns the allocation.
+        unsafe { self.ptr.as_ref() }
+    }
+}
+#[cfg(feature = "alloc")]
+impl<'erased_data, V, C, A> DerefMut for ThinBoxWithoutCommon<'erased_data, V, C, A>
+where
+    V: ThinTrait<C> + ?Sized,
+    A: Allocator,
+{
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        // Safety: the box owns the allocation.
+        unsafe { self.ptr.as_mut() }
+    }
+}
+/// Only boxes that use the [`Global`] allocator can be cloned since the vtable
+/// allocates the clone.
+#[cfg(feature = "alloc")]
+impl<V, C> Clone for ThinBoxWithoutCommon<'_, V, C>
+where
+    V: ThinTrait<C> + ?Sized,
+    <V as ThinTrait<C>>::VTable: VTableClone<C>,
+{
+    fn clone(&self) -> Self {
+        Self::from_raw(ThinWithoutCommon::clone_erased(self))
+    }
+}
+#[cfg(feature = "alloc")]
+impl<V, C, A> fmt::Debug for ThinBoxWithoutCommon<'_, V, C, A>
+where
+    V: ThinTrait<C> + ?Sized,
+    A: Allocator,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple(get_type_name!(ThinBoxWithoutCommon))
+            .field(&**self)
+            .finish()
+    }
+}
+#[cfg(feature = "alloc")]
+impl<V, C, A> Drop for ThinBoxWithoutCommon<'_, V, C, A>
+where
+    V: ThinTrait<C> + ?Sized,
+    A: Allocator,
+{
+    fn drop(&mut self) {
+        // Safety: Taking self is safe since it won't be accessed after this point.
+        unsafe { Self::take_raw(self) }.free_via_vtable();
+    }
+}
 
-    /// Used to enforce that a type (`Self`) has the auto traits specified by an
-    /// [`AutoTraitConfig`] (specified with the `A` type parameter).
-    ///
-    /// # Safety
+/// A type erased object stored on the heap without using a fat pointer.
+///
+/// The heap allocation is managed by the allocator `A`, see the [`allocator`]
+/// module.
+#[cfg(feature = "alloc")]
+#[cfg_attr(feature = "docs", doc(cfg(feature = "alloc")))]
+#[repr(transparent)]
+pub struct ThinBox<'erased_data, V, C, A = Global>
+where
+    V: ThinTrait<C> + ?Sized,
+    A: Allocator,
+{
+    ptr: NonNull<Thin<'erased_data, V, C>>,
+    _allocator: PhantomData<A>,
+}
+// Safety: the box owns the type erased object and the allocator.
+#[cfg(feature = "alloc")]
+unsafe impl<'erased_data, V, C, A> Send for ThinBox<'erased_data, V, C, A>
+where
+    V: ThinTrait<C> + ?Sized,
+    A: Allocator + Send,
+    Thin<'erased_data, V, C>: Send,
+{
+}
+#[cfg(feature = "alloc")]
+unsafe impl<'erased_data, V, C, A> Sync for ThinBox<'erased_data, V, C, A>
+where
+    V: ThinTrait<C> + ?Sized,
+    A: Allocator + Sync,
+    Thin<'erased_data, V, C>: Sync,
+{
+}
+#[cfg(feature = "alloc")]
+#[cfg(feature = "std")]
+impl<'erased_data, V, C, A> std::panic::UnwindSafe for ThinBox<'erased_data, V, C, A>
+where
+    V: ThinTrait<C> + ?Sized,
+    A: Allocator + std::panic::UnwindSafe,
+    Thin<'erased_data, V, C>: std::panic::UnwindSafe,
+{
+}
+#[cfg(feature = "alloc")]
+impl<'erased_data, V, C> ThinBox<'erased_data, V, C>
+where
+    V: ThinTrait<C> + ?Sized,
+{
+    /// Create a new [`ThinBox`] that stores some data in a heap allocation.
+    pub fn new<T>(x: T, common: C) -> Self
+    where
+        T: auto_traits::HasAutoTraits<V::AutoTraitConfig> + 'erased_data,
+        V::VTable: GetThinTraitVTable<T>,
+    {
+        Self::new_in(x, common, Global)
+    }
+    /// Create a new [`ThinBox`] that stores some data in a heap allocation.
+    /// The object and the common data are given back if the allocation fails
+    /// instead of aborting the process.
+    pub fn try_new<T>(x: T, common: C) -> Result<Self, (T, C)>
+    where
+        T: auto_traits::HasAutoTraits<V::AutoTraitConfig> + 'erased_data,
+        V::VTable: GetThinTraitVTable<T>,
+    {
+        Self::try_new_in(x, common, Global)
+    }
+    /// Create a new [`ThinBox`] whose object is created by `f` after the heap
+    /// allocation has been made.
     ///
-    /// This trait must uphold the same safety invariants as the [`EnforceAutoTraits`]
-    /// trait.
-    pub unsafe trait HasAutoTraits<A: ?Sized> {}
-    unsafe impl<T: ?Sized, A: ?Sized> HasAutoTraits<A> for T where A: EnforceAutoTraits<T> {}
-
-    /// Implementation details for [`HasAutoTraits`]. This is implement on all
-    /// "auto trait config" types for types `T` that have the correct auto traits.
+    /// The result of `f` is written into the allocation, which the compiler
+    /// can often do without a copy on the stack. This is only an optimization
+    /// and isn't guaranteed, so use [`ThinBox::new_uninit`] to initialize
+    /// large objects directly inside the heap allocation.
+    pub fn new_with<T, F>(f: F, common: C) -> Self
+    where
+        T: auto_traits::HasAutoTraits<V::AutoTraitConfig> + 'erased_data,
+        V::VTable: GetThinTraitVTable<T>,
+        F: FnOnce() -> T,
+    {
+        Self::new_with_in(f, common, Global)
+    }
+    /// Allocate memory for an object of type `T` without initializing it. This
+    /// allows large objects to be written directly into the heap allocation
+    /// instead of being moved there through the stack.
+    pub fn new_uninit<T>(common: C) -> ThinBoxUninit<'erased_data, V, C, T>
+    where
+        T: auto_traits::HasAutoTraits<V::AutoTraitConfig> + 'erased_data,
+        V::VTable: GetThinTraitVTable<T>,
+    {
+        Self::new_uninit_in(common, Global)
+    }
+    /// Create a new [`ThinBox`] that stores a boxed trait object.
     ///
-    /// This trait is also implemented on vtable types for types `T` that uphold
-    /// the vtable default auto traits specified via the vtable type's
-    /// [`VTableEnforcedAutoTraits::UncheckedAutoTraitMarker`].
+    /// The [`Box`] is kept as is inside a new allocation, so the object is
+    /// reached through two pointers and two allocations are used. Prefer
+    /// [`ThinBox::new`] if the concrete type is known. Only traits whose
+    /// methods all take `self` by reference are supported, see [`BoxedDyn`].
+    pub fn from_box_dyn(boxed: Box<V>, common: C) -> Self
+    where
+        BoxedDyn<V>: auto_traits::HasAutoTraits<V::AutoTraitConfig> + 'erased_data,
+        V::VTable: GetThinTraitVTable<BoxedDyn<V>>,
+    {
+        Self::new(BoxedDyn(boxed), common)
+    }
+    /// Convert a [`ThinBox`] into a pointer to its allocation. The pointer is a
+    /// single word so it can be stored in a C `void*` or an
+    /// [`AtomicPtr`](core::sync::atomic::AtomicPtr). Use
+    /// [`ThinBox::from_raw_ptr`] to free the allocation later.
+    pub fn into_raw_ptr(this: Self) -> NonNull<Thin<'erased_data, V, C>> {
+        ManuallyDrop::new(this).ptr
+    }
+    /// Take ownership of an allocation that was converted into a pointer with
+    /// [`ThinBox::into_raw_ptr`].
     ///
     /// # Safety
     ///
-    /// If the `Self` type implements [`AutoTraitConfig`] then it must do so in
-    /// a way that ensures the resulting marker type ([`AutoTraitConfig::MarkerType`])
-    /// only implements the auto traits that are enforced/guaranteed by this trait.
-    pub unsafe trait EnforceAutoTraits<T: ?Sized> {}
+    /// The pointer must have been returned by [`ThinBox::into_raw_ptr`] (or
+    /// derived from a reference returned by [`ThinBox::leak`] for a box that
+    /// uses the [`Global`] allocator) for the same trait object type `V` and
+    /// common data type `C`, and it can only be converted back once.
+    pub unsafe fn from_raw_ptr(ptr: NonNull<Thin<'erased_data, V, C>>) -> Self {
+        Self {
+            ptr,
+            _allocator: PhantomData,
+        }
+    }
 
-    /// Implemented for config types that enforce that certain auto traits are
-    /// implemented for a type erased object.
+    /// Move a type erased value into a new heap allocation that is owned by a
+    /// [`ThinBox`].
     ///
     /// # Safety
     ///
-    /// The specified `MarkerType` must not implement auto traits that are not
-    /// enforced via the the [`EnforceAutoTraits`] trait.
-    pub unsafe trait AutoTraitConfig<T: ?Sized> {
-        /// A type that implements all auto traits of `T` and all auto traits
-        /// that are enforced by the auto traits config.
-        type MarkerType: ?Sized;
-    }
-
-    // Base case:
-    unsafe impl<T: ?Sized> EnforceAutoTraits<T> for () {}
-    unsafe impl<T: ?Sized> AutoTraitConfig<T> for () {
-        type MarkerType = T;
-    }
-
-    /// Use this to ensure we never construct a wrapper type.
-    #[derive(Debug)]
-    enum Never {}
-
-    macro_rules! define_auto_trait_wrappers {
-        ($( $( #[$attr:meta] )* $wrapper_name:ident => $(#[unsafe] $(=>;;; never $is_unsafe:ident)?)? $auto_trait:path ),* $(,)?) => {
-            $(
-                $(#[$attr])*
-                #[derive(Debug)]
-                pub struct $wrapper_name<I: ?Sized>(PhantomData<I>, Never);
-                unsafe impl<T: ?Sized, I: ?Sized> EnforceAutoTraits<T> for $wrapper_name<I>
-                where
-                    I: EnforceAutoTraits<T>,
-                    T: $auto_trait,
-                {
-                }
-                unsafe impl<T: ?Sized, I: ?Sized> AutoTraitConfig<T> for $wrapper_name<I> where I: AutoTraitConfig<T> {
-                    type MarkerType = $wrapper_name<<I as AutoTraitConfig<T>>::MarkerType>;
-                }
-                define_auto_trait_wrappers!(@if ($(true $($is_unsafe)?)?) {
-                    unsafe impl<I: ?Sized> $auto_trait for $wrapper_name<I> {}
-                } else {
-                    impl<I: ?Sized> $auto_trait for $wrapper_name<I> {}
-                });
-            )*
-        };
-        (@if (true $($condition:tt)*) { $($true:tt)* } else { $($false:tt)* } ) => { $($true)* };
-        (@if ($($condition:tt)*) { $($true:tt)* } else { $($false:tt)* } ) => { $($false)* };
-    }
-    define_auto_trait_wrappers!(
-        /// Ensures that an erased type implements the [`Send`] auto trait.
-        HasSend => #[unsafe] Send,
-        /// Ensures that an erased type implements the [`Sync`] auto trait.
-        HasSync => #[unsafe] Sync,
-        /// Ensures that an erased type implements the [`Unpin`] auto trait.
-        HasUnpin => Unpin,
-    );
-
-    #[cfg(feature = "std")]
-    define_auto_trait_wrappers!(
-        /// Ensures that an erased type implements the [`std::panic::UnwindSafe`] auto trait.
-        HasUnwindSafe => std::panic::UnwindSafe,
-        /// Ensures that an erased type implements the [`std::panic::RefUnwindSafe`] auto trait.
-        HasRefUnwindSafe => std::panic::RefUnwindSafe,
-    );
-
-    /// Trait objects for a trait without supertraits don't implement any auto traits.
-    trait NoAutoTraitsTrait {}
-
-    /// This type relaxes all auto trait requirements. The marker type indicates
-    /// that no auto trait should be implemented for a type erased object.
-    #[derive(Debug)]
-    pub struct NoAutoTraits(PhantomData<dyn NoAutoTraitsTrait>, Never);
-    unsafe impl<T: ?Sized> EnforceAutoTraits<T> for NoAutoTraits {}
-    unsafe impl<T: ?Sized> AutoTraitConfig<T> for NoAutoTraits {
-        type MarkerType = NoAutoTraits;
+    /// The pointer must point to a valid [`Thin`] value that is owned by the
+    /// caller. The value must not be used or dropped after this call but the
+    /// caller is still responsible for freeing its memory.
+    unsafe fn move_from_thin(this: *mut Thin<'erased_data, V, C>) -> Self {
+        let layout = Thin::raw_layout(this);
+        // The `Global` allocator is zero sized so it doesn't need to be written
+        // to the allocation.
+        let new = alloc_raw_thin_with_header_in::<Global, _>(layout, &Global);
+        ptr::copy_nonoverlapping(this as *const u8, new.as_ptr(), layout.size());
+        Self {
+            ptr: new.cast(),
+            _allocator: PhantomData,
+        }
     }
 }
-
-/// The same as [`ThinBox`] except the common data has been moved out and is no
-/// longer available.
-#[repr(transparent)]
-pub struct ThinBoxWithoutCommon<'erased_data, V, C>
+#[cfg(feature = "alloc")]
+impl<'erased_data, V, C, A> ThinBox<'erased_data, V, C, A>
 where
     V: ThinTrait<C> + ?Sized,
+    A: Allocator,
 {
-    inner: ManuallyDrop<Box<ThinWithoutCommon<'erased_data, V, C>>>,
-}
-impl<'erased_data, V> ThinBoxWithoutCommon<'erased_data, V, ()>
-where
-    V: ThinTrait<()> + ?Sized,
-{
-    /// Create a new [`ThinBoxWithoutCommon`] that stores some data in a heap allocation.
-    pub fn new<T>(x: T) -> Self
+    /// Create a new [`ThinBox`] that stores some data in a heap allocation that
+    /// is managed by `allocator`.
+    pub fn new_in<T>(x: T, common: C, allocator: A) -> Self
     where
         T: auto_traits::HasAutoTraits<V::AutoTraitConfig> + 'erased_data,
         V::VTable: GetThinTraitVTable<T>,
     {
         Self::from_raw(
-            RawThinBox::new(x, ())
-                .free_common_data()
+            RawThinBox::new_in(x, common, allocator)
                 .with_auto_trait_config::<V::AutoTraitConfig>()
                 .erase(),
         )
     }
-}
-impl<'erased_data, V, C> ThinBoxWithoutCommon<'erased_data, V, C>
-where
-    V: ThinTrait<C> + ?Sized,
-{
-    /// Put some common data into the heap allocation that stores the type erased
-    /// object.
-    pub fn put_common(this: Self, common: C) -> ThinBox<'erased_data, V, C> {
-        ThinBox::from_raw(Self::into_raw(this).put_common_data(common))
+    /// Create a new [`ThinBox`] that stores some data in a heap allocation that
+    /// is managed by `allocator`. The object and the common data are given
+    /// back if the allocation fails.
+    pub fn try_new_in<T>(x: T, common: C, allocator: A) -> Result<Self, (T, C)>
+    where
+        T: auto_traits::HasAutoTraits<V::AutoTraitConfig> + 'erased_data,
+        V::VTable: GetThinTraitVTable<T>,
+    {
+        RawThinBox::try_new_in(x, common, allocator)
+            .map(|raw| Self::from_raw(raw.with_auto_trait_config::<V::AutoTraitConfig>().erase()))
+    }
+    /// Create a new [`ThinBox`] whose object is created by `f` after a heap
+    /// allocation that is managed by `allocator` has been made. Like
+    /// [`ThinBox::new_with`] this doesn't guarantee that the object isn't
+    /// copied through the stack, see [`ThinBox::new_uninit_in`].
+    pub fn new_with_in<T, F>(f: F, common: C, allocator: A) -> Self
+    where
+        T: auto_traits::HasAutoTraits<V::AutoTraitConfig> + 'erased_data,
+        V::VTable: GetThinTraitVTable<T>,
+        F: FnOnce() -> T,
+    {
+        let mut uninit = Self::new_uninit_in(common, allocator);
+        // Safety: the pointer is valid for writes. If `f` panics the object
+        // isn't initialized and the handle only frees the common data.
+        unsafe {
+            ptr::write(uninit.as_mut_ptr(), f());
+            ThinBoxUninit::assume_init(uninit)
+        }
+    }
+    /// Allocate memory for an object of type `T` with `allocator` without
+    /// initializing it, see [`ThinBox::new_uninit`].
+    pub fn new_uninit_in<T>(common: C, allocator: A) -> ThinBoxUninit<'erased_data, V, C, T, A>
+    where
+        T: auto_traits::HasAutoTraits<V::AutoTraitConfig> + 'erased_data,
+        V::VTable: GetThinTraitVTable<T>,
+    {
+        let raw_ptr = alloc_raw_thin_with_header_in::<A, A>(
+            Layout::new::<RawThin<'erased_data, V::VTable, C, ThinTraitAutoTraitsMarker<V, C>, T>>(
+            ),
+            &allocator,
+        )
+        .cast::<RawThin<
+            'erased_data,
+            V::VTable,
+            C,
+            ThinTraitAutoTraitsMarker<V, C>,
+            MaybeUninit<T>,
+        >>();
+        // Safety: `raw_thin_layout_with_header` ensures that the allocator and
+        // the `RawThin` fields are aligned and fit inside the allocation. The
+        // fields are written one at a time so the object is never copied. The
+        // `HasAutoTraits` bound ensures that the object implements the auto
+        // traits of the marker type.
+        unsafe {
+            let raw = raw_ptr.as_ptr();
+            ptr::addr_of_mut!((*raw).vtable)
+                .write(<V::VTable as GetThinTraitVTable<T>>::get_vtable().vtable);
+            ptr::addr_of_mut!((*raw).common).write(common);
+            ptr::write((raw as *mut A).sub(1), allocator);
+        }
+        ThinBoxUninit {
+            ptr: raw_ptr,
+            _owned: PhantomData,
+        }
     }
 
-    /// Convert a [`ThinBoxWithoutCommon`] to a [`RawThinBox`]. This allows for
-    /// a lower level, more powerful, API.
+    /// Take the common data that is stored for this object out of the heap
+    /// allocation.
+    pub fn take_common(this: Self) -> (ThinBoxWithoutCommon<'erased_data, V, C, A>, C)
+    where
+        V: ThinTrait<Taken<C>>,
+    {
+        let (this, common) = Self::into_raw(this).take_common_data();
+        let this = ThinBoxWithoutCommon::from_raw(this);
+        (this, common)
+    }
+
+    /// Upcast to a trait object of a supertrait without changing the auto
+    /// traits. The supertrait must be listed in a `#[thin_supertraits(..)]`
+    /// attribute, its vtable is then stored inside this object's vtable so the
+    /// heap allocation is reused.
+    pub fn upcast<U>(this: Self) -> ThinBox<'erased_data, U, C, A>
+    where
+        U: ThinTrait<C, AutoTraitConfig = V::AutoTraitConfig> + ?Sized,
+        V::VTable: VTableUpcast<U::TraitObject, VTable = <U as ThinTrait<C>>::VTable>,
+        V::AutoTraitConfig: auto_traits::AutoTraitConfig<
+            <U::VTable as auto_traits::VTableEnforcedAutoTraits>::UncheckedAutoTraitMarker,
+        >,
+    {
+        let raw = Self::into_raw(this).upcast_vtable::<U::TraitObject>();
+        // Safety: the auto traits of a supertrait are implied by the auto traits
+        // of the subtrait and the auto trait configs are the same.
+        ThinBox::from_raw(unsafe { raw.cast_auto_traits_marker() })
+    }
+
+    /// The allocator that manages the heap allocation.
+    pub fn allocator(this: &Self) -> &A {
+        // Safety: the allocator is stored right before the `RawThin` value.
+        unsafe { &*(this.ptr.as_ptr() as *const A).sub(1) }
+    }
+
+    /// Convert a [`ThinBox`] to a [`RawThinBox`]. This allows for a lower level,
+    /// more powerful, API.
     pub fn into_raw(
         this: Self,
     ) -> RawThinBox<
         'erased_data,
         <V as ThinTrait<C>>::VTable,
-        Taken<C>,
+        C,
         ThinTraitAutoTraitsMarker<V, C>,
         (),
+        A,
     > {
         let mut this = ManuallyDrop::new(this);
         // Safety: the `ManuallyDrop` wrapper ensures we never use `this` again.
         unsafe { Self::take_raw(&mut *this) }
     }
-
-    /// Take a [`ThinBoxWithoutCommon`] and create a [`RawThinBox`] instead. This
-    /// allows for a lower level, more powerful, API.
+    /// Take a [`ThinBox`] and create a [`RawThinBox`] instead. This allows for
+    /// a lower level, more powerful, API.
     ///
     /// # Safety
     ///
@@ -1817,274 +4909,352 @@ where
     ) -> RawThinBox<
         'erased_data,
         <V as ThinTrait<C>>::VTable,
-        Taken<C>,
+        C,
         ThinTraitAutoTraitsMarker<V, C>,
         (),
+        A,
     > {
-        // Safety: the read value won't ever be used from a safe function `RawThinBox`
-        // doesn't make any guarantees about the state of its content, so the user
-        // must assume that the wrapped value could already be dropped and can't do
-        // anything to it safely.
-        let inner: ManuallyDrop<Box<ThinWithoutCommon<'erased_data, V, C>>> =
-            ptr::read(&this.inner);
-
-        // Safety: `ThinWithoutCommon` is a `repr(transparent)` struct around `RawThin` and we ensured
-        // the types inside `ThinWithoutCommon` lines up with the ones inside RawThin.
-        let inner = mem::transmute::<
-            ManuallyDrop<Box<ThinWithoutCommon<'erased_data, V, C>>>,
-            ManuallyDrop<
-                Box<
-                    RawThin<
-                        'erased_data,
-                        <V as ThinTrait<C>>::VTable,
-                        Taken<C>,
-                        ThinTraitAutoTraitsMarker<V, C>,
-                        (),
-                    >,
-                >,
-            >,
-        >(inner);
-        RawThinBox { inner }
+        // Safety: `Thin` is a `repr(transparent)` struct around `RawThin` and we ensured
+        // the types inside `Thin` lines up with the ones inside RawThin.
+        // `RawThinBox` doesn't make any guarantees about the state of its
+        // content, so the user must assume that the wrapped value could already
+        // be dropped and can't do anything to it safely.
+        RawThinBox {
+            ptr: this.ptr.cast(),
+            _allocator: PhantomData,
+        }
     }
-    /// Convert a [`RawThinBox`] to a [`ThinBoxWithoutCommon`]. This allows for
-    /// a more convent, more higher level API.
+    /// Convert a [`RawThinBox`] to a [`ThinBox`]. This allows for a more convent,
+    /// more higher level API.
     pub fn from_raw(
         raw: RawThinBox<
             'erased_data,
             <V as ThinTrait<C>>::VTable,
-            Taken<C>,
+            C,
             ThinTraitAutoTraitsMarker<V, C>,
             (),
+            A,
         >,
     ) -> Self {
-        let inner = raw.inner;
-        // Safety: `ThinWithoutCommon` is a `repr(transparent)` struct around `RawThin` and we ensured
-        // the types inside `ThinWithoutCommon` lines up with the ones inside RawThin.
-        let inner = unsafe {
-            mem::transmute::<
-                ManuallyDrop<
-                    Box<
-                        RawThin<
-                            'erased_data,
-                            <V as ThinTrait<C>>::VTable,
-                            Taken<C>,
-                            ThinTraitAutoTraitsMarker<V, C>,
-                            (),
-                        >,
-                    >,
-                >,
-                ManuallyDrop<Box<ThinWithoutCommon<'erased_data, V, C>>>,
-            >(inner)
-        };
-        Self { inner }
+        // Safety: `Thin` is a `repr(transparent)` struct around `RawThin` and we ensured
+        // the types inside `Thin` lines up with the ones inside RawThin.
+        Self {
+            ptr: raw.ptr.cast(),
+            _allocator: PhantomData,
+        }
+    }
+    /// Leak the allocation so that it is never freed and the erased object is
+    /// never dropped, just like [`Box::leak`]. The allocator must outlive the
+    /// returned reference since it owns the leaked memory.
+    pub fn leak<'a>(this: Self) -> &'a mut Thin<'a, V, C>
+    where
+        'erased_data: 'a,
+        A: 'a,
+    {
+        let ptr = ManuallyDrop::new(this).ptr.as_ptr();
+        // Safety: the allocation is never freed and the erased object outlives
+        // the shorter lifetime.
+        unsafe { &mut *ptr.cast::<Thin<'a, V, C>>() }
+    }
+
+    /// The layout of the heap allocation that stores the allocator, the common
+    /// data and the type erased object.
+    pub fn allocation_layout(this: &Self) -> Layout {
+        // Safety: the pointer comes from a reference so it points to a valid `Thin`.
+        let raw_layout = unsafe { Thin::raw_layout(&**this) };
+        raw_thin_layout_with_header::<A>(raw_layout).0
+    }
+}
+#[cfg(feature = "alloc")]
+impl<'erased_data, V, C> ThinBox<'erased_data, V, C>
+where
+    V: ThinTraitDyn<'erased_data, C> + ?Sized,
+{
+    /// Move the type erased object into an ordinary [`Box`] and move the common
+    /// data out of the heap allocation.
+    pub fn into_box_dyn(this: Self) -> (Box<V>, C) {
+        let (erased, common) = Self::into_raw(this)
+            .weaken_auto_traits_marker()
+            .take_common_data();
+        let vtable = erased.raw().vtable.static_ref();
+        // Safety: the vtable is stored inside the type erased object.
+        let boxed = unsafe { Box::from_raw(V::erased_into_box_dyn(vtable, erased)) };
+        (boxed, common)
     }
 }
-impl<'erased_data, V, C> Deref for ThinBoxWithoutCommon<'erased_data, V, C>
+#[cfg(feature = "alloc")]
+impl<V, C, A> ThinBox<'static, V, C, A>
 where
     V: ThinTrait<C> + ?Sized,
+    A: Allocator,
 {
-    type Target = ThinWithoutCommon<'erased_data, V, C>;
+    /// Move the type erased object and the common data out of the heap
+    /// allocation if the object has the type `T`. Otherwise the same
+    /// [`ThinBox`] is returned as an error.
+    pub fn downcast<T: 'static>(this: Self) -> Result<(T, C), Self> {
+        if !Thin::is::<T>(&this) {
+            return Err(this);
+        }
+        // Safety: we just checked that the erased object has the type `T`.
+        let unerased = unsafe { Self::into_raw(this).unerase::<T>() };
+        let (unerased, common) = unerased.take_common_data();
+        Ok((unerased.into_inner(), common))
+    }
+}
+#[cfg(feature = "alloc")]
+impl<'erased_data, V, C, A> Deref for ThinBox<'erased_data, V, C, A>
+where
+    V: ThinTrait<C> + ?Sized,
+    A: Allocator,
+{
+    type Target = Thin<'erased_data, V, C>;
 
     fn deref(&self) -> &Self::Target {
-        &**self.inner
+        // Safety: the box owns the allocation.
+        unsafe { self.ptr.as_ref() }
     }
 }
-impl<'erased_data, V, C> DerefMut for ThinBoxWithoutCommon<'erased_data, V, C>
+#[cfg(feature = "alloc")]
+impl<'erased_data, V, C, A> DerefMut for ThinBox<'erased_data, V, C, A>
 where
     V: ThinTrait<C> + ?Sized,
+    A: Allocator,
 {
     fn deref_mut(&mut self) -> &mut Self::Target {
-        &mut **self.inner
+        // Safety: the box owns the allocation.
+        unsafe { self.ptr.as_mut() }
+    }
+}
+/// Only boxes that use the [`Global`] allocator can be cloned since the vtable
+/// allocates the clone.
+#[cfg(feature = "alloc")]
+impl<V, C> Clone for ThinBox<'_, V, C>
+where
+    V: ThinTrait<C> + ?Sized,
+    <V as ThinTrait<C>>::VTable: VTableClone<C>,
+    C: Clone,
+{
+    fn clone(&self) -> Self {
+        // Clone the common data first so that it is dropped if cloning the
+        // object panics:
+        let (_, common) = Thin::split_common(self);
+        let common = common.clone();
+        Self::from_raw(ThinWithoutCommon::clone_erased(self).put_common_data(common))
     }
 }
-impl<V, C> fmt::Debug for ThinBoxWithoutCommon<'_, V, C>
+#[cfg(feature = "alloc")]
+impl<V, C, A> fmt::Debug for ThinBox<'_, V, C, A>
 where
     V: ThinTrait<C> + ?Sized,
+    C: fmt::Debug,
+    A: Allocator,
 {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.debug_tuple(get_type_name!(ThinBoxWithoutCommon))
-            .field(&**self.inner)
+        f.debug_tuple(get_type_name!(ThinBox))
+            .field(&**self)
             .finish()
     }
 }
-impl<V, C> Drop for ThinBoxWithoutCommon<'_, V, C>
+#[cfg(feature = "alloc")]
+impl<V, C, A> Drop for ThinBox<'_, V, C, A>
 where
     V: ThinTrait<C> + ?Sized,
+    A: Allocator,
 {
     fn drop(&mut self) {
         // Safety: Taking self is safe since it won't be accessed after this point.
-        unsafe { Self::take_raw(self) }.free_via_vtable();
+        unsafe { Self::take_raw(self) }
+            .free_common_data()
+            .free_via_vtable();
     }
 }
-
-/// A type erased object stored on the heap without using a fat pointer.
-#[repr(transparent)]
-pub struct ThinBox<'erased_data, V, C>
+
+/// A heap allocation for a [`ThinBox`] whose object hasn't been initialized
+/// yet, created by [`ThinBox::new_uninit`].
+///
+/// The object can be written through the [`MaybeUninit`] reference that this
+/// handle derefs to. Afterwards [`ThinBoxUninit::assume_init`] converts the
+/// handle into a [`ThinBox`]. If the handle is dropped then only the common
+/// data is dropped.
+///
+/// # Examples
+///
+/// ```
+/// use thin_trait_object::{define_v_table, ThinBox, ThinBoxUninit};
+///
+/// define_v_table!(
+///     trait Sum {
+///         fn sum(&self) -> u64;
+///     }
+/// );
+/// struct Large([u64; 1024]);
+/// impl Sum for Large {
+///     fn sum(&self) -> u64 { self.0.iter().sum() }
+/// }
+///
+/// let mut uninit = ThinBox::<dyn Sum, ()>::new_uninit::<Large>(());
+/// let large = uninit.as_mut_ptr();
+/// for i in 0..1024 {
+///     // Safety: the pointer is valid for writes and the array is written
+///     // without creating a reference to uninitialized memory.
+///     unsafe { core::ptr::addr_of_mut!((*large).0[i]).write(1) };
+/// }
+/// // Safety: every element of the array was initialized.
+/// let boxed = unsafe { ThinBoxUninit::assume_init(uninit) };
+/// assert_eq!(boxed.sum(), 1024);
+/// ```
+#[cfg(feature = "alloc")]
+#[cfg_attr(feature = "docs", doc(cfg(feature = "alloc")))]
+pub struct ThinBoxUninit<'erased_data, V, C, T, A = Global>
+where
+    V: ThinTrait<C> + ?Sized,
+    A: Allocator,
+{
+    ptr: NonNull<
+        RawThin<
+            'erased_data,
+            <V as ThinTrait<C>>::VTable,
+            C,
+            ThinTraitAutoTraitsMarker<V, C>,
+            MaybeUninit<T>,
+        >,
+    >,
+    _owned: PhantomData<(ThinBox<'erased_data, V, C, A>, T)>,
+}
+// Safety: the handle owns the common data, the allocator and maybe the object.
+#[cfg(feature = "alloc")]
+unsafe impl<'erased_data, V, C, T, A> Send for ThinBoxUninit<'erased_data, V, C, T, A>
 where
     V: ThinTrait<C> + ?Sized,
+    A: Allocator + Send,
+    Thin<'erased_data, V, C>: Send,
 {
-    inner: ManuallyDrop<Box<Thin<'erased_data, V, C>>>,
 }
-impl<'erased_data, V, C> ThinBox<'erased_data, V, C>
+#[cfg(feature = "alloc")]
+unsafe impl<'erased_data, V, C, T, A> Sync for ThinBoxUninit<'erased_data, V, C, T, A>
 where
     V: ThinTrait<C> + ?Sized,
+    A: Allocator + Sync,
+    Thin<'erased_data, V, C>: Sync,
 {
-    /// Create a new [`ThinBox`] that stores some data in a heap allocation.
-    pub fn new<T>(x: T, common: C) -> Self
-    where
-        T: auto_traits::HasAutoTraits<V::AutoTraitConfig> + 'erased_data,
-        V::VTable: GetThinTraitVTable<T>,
-    {
-        Self::from_raw(
-            RawThinBox::new(x, common)
-                .with_auto_trait_config::<V::AutoTraitConfig>()
-                .erase(),
-        )
-    }
 }
-impl<'erased_data, V, C> ThinBox<'erased_data, V, C>
+#[cfg(feature = "alloc")]
+impl<'erased_data, V, C, T, A> ThinBoxUninit<'erased_data, V, C, T, A>
 where
     V: ThinTrait<C> + ?Sized,
+    A: Allocator,
 {
-    /// Take the common data that is stored for this object out of the heap
-    /// allocation.
-    pub fn take_common(this: Self) -> (ThinBoxWithoutCommon<'erased_data, V, C>, C)
-    where
-        V: ThinTrait<Taken<C>>,
-    {
-        let (this, common) = Self::into_raw(this).take_common_data();
-        let this = ThinBoxWithoutCommon::from_raw(this);
-        (this, common)
-    }
-
-    /// Convert a [`ThinBox`] to a [`RawThinBox`]. This allows for a lower level,
-    /// more powerful, API.
-    pub fn into_raw(
-        this: Self,
-    ) -> RawThinBox<'erased_data, <V as ThinTrait<C>>::VTable, C, ThinTraitAutoTraitsMarker<V, C>, ()>
-    {
-        let mut this = ManuallyDrop::new(this);
-        // Safety: the `ManuallyDrop` wrapper ensures we never use `this` again.
-        unsafe { Self::take_raw(&mut *this) }
-    }
-    /// Take a [`ThinBox`] and create a [`RawThinBox`] instead. This allows for
-    /// a lower level, more powerful, API.
+    /// Convert the handle into a [`ThinBox`] that owns the object.
     ///
     /// # Safety
     ///
-    /// `Self` must never be used after this function has been called, this includes ensuring that
-    /// `Self` is not dropped.
-    pub unsafe fn take_raw(
-        this: &mut Self,
-    ) -> RawThinBox<'erased_data, <V as ThinTrait<C>>::VTable, C, ThinTraitAutoTraitsMarker<V, C>, ()>
-    {
-        // Safety: the read value won't ever be used from a safe function `RawThinBox`
-        // doesn't make any guarantees about the state of its content, so the user
-        // must assume that the wrapped value could already be dropped and can't do
-        // anything to it safely.
-        let inner: ManuallyDrop<Box<Thin<'erased_data, V, C>>> = ptr::read(&this.inner);
-
-        // Safety: `Thin` is a `repr(transparent)` struct around `RawThin` and we ensured
-        // the types inside `Thin` lines up with the ones inside RawThin.
-        let inner = mem::transmute::<
-            ManuallyDrop<Box<Thin<'erased_data, V, C>>>,
-            ManuallyDrop<
-                Box<
-                    RawThin<
-                        'erased_data,
-                        <V as ThinTrait<C>>::VTable,
-                        C,
-                        ThinTraitAutoTraitsMarker<V, C>,
-                        (),
-                    >,
-                >,
-            >,
-        >(inner);
-        RawThinBox { inner }
+    /// The object must have been initialized.
+    pub unsafe fn assume_init(this: Self) -> ThinBox<'erased_data, V, C, A> {
+        // Safety: `MaybeUninit<T>` has the same layout as `T` and the vtable
+        // was created for `T`. The `ManuallyDrop` wrapper ensures the handle
+        // doesn't free the allocation.
+        ThinBox {
+            ptr: ManuallyDrop::new(this).ptr.cast(),
+            _allocator: PhantomData,
+        }
     }
-    /// Convert a [`RawThinBox`] to a [`ThinBox`]. This allows for a more convent,
-    /// more higher level API.
-    pub fn from_raw(
-        raw: RawThinBox<
-            'erased_data,
-            <V as ThinTrait<C>>::VTable,
-            C,
-            ThinTraitAutoTraitsMarker<V, C>,
-            (),
-        >,
-    ) -> Self {
-        let inner = raw.inner;
-        // Safety: `Thin` is a `repr(transparent)` struct around `RawThin` and we ensured
-        // the types inside `Thin` lines up with the ones inside RawThin.
-        let inner = unsafe {
-            mem::transmute::<
-                ManuallyDrop<
-                    Box<
-                        RawThin<
-                            'erased_data,
-                            <V as ThinTrait<C>>::VTable,
-                            C,
-                            ThinTraitAutoTraitsMarker<V, C>,
-                            (),
-                        >,
-                    >,
-                >,
-                ManuallyDrop<Box<Thin<'erased_data, V, C>>>,
-            >(inner)
-        };
-        Self { inner }
+    /// Get a mutable reference to the common data.
+    pub fn common_mut(this: &mut Self) -> &mut C {
+        // Safety: the common data is initialized and owned by the handle.
+        unsafe { &mut (*this.ptr.as_ptr()).common }
     }
 }
-impl<'erased_data, V, C> Deref for ThinBox<'erased_data, V, C>
+#[cfg(feature = "alloc")]
+impl<V, C, T, A> Deref for ThinBoxUninit<'_, V, C, T, A>
 where
     V: ThinTrait<C> + ?Sized,
+    A: Allocator,
 {
-    type Target = Thin<'erased_data, V, C>;
+    type Target = MaybeUninit<T>;
 
     fn deref(&self) -> &Self::Target {
-        &**self.inner
+        // Safety: the handle owns the allocation.
+        unsafe { &(*self.ptr.as_ptr())._object }
     }
 }
-impl<'erased_data, V, C> DerefMut for ThinBox<'erased_data, V, C>
+#[cfg(feature = "alloc")]
+impl<V, C, T, A> DerefMut for ThinBoxUninit<'_, V, C, T, A>
 where
     V: ThinTrait<C> + ?Sized,
+    A: Allocator,
 {
     fn deref_mut(&mut self) -> &mut Self::Target {
-        &mut **self.inner
+        // Safety: the handle owns the allocation.
+        unsafe { &mut (*self.ptr.as_ptr())._object }
     }
 }
-impl<V, C> fmt::Debug for ThinBox<'_, V, C>
+#[cfg(feature = "alloc")]
+impl<V, C, T, A> fmt::Debug for ThinBoxUninit<'_, V, C, T, A>
 where
     V: ThinTrait<C> + ?Sized,
-    C: fmt::Debug,
+    A: Allocator,
 {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.debug_tuple(get_type_name!(ThinBox))
-            .field(&**self.inner)
-            .finish()
+        f.debug_struct(get_type_name!(ThinBoxUninit)).finish()
     }
 }
-impl<V, C> Drop for ThinBox<'_, V, C>
+#[cfg(feature = "alloc")]
+impl<V, C, T, A> Drop for ThinBoxUninit<'_, V, C, T, A>
 where
     V: ThinTrait<C> + ?Sized,
+    A: Allocator,
 {
     fn drop(&mut self) {
-        // Safety: Taking self is safe since it won't be accessed after this point.
-        unsafe { Self::take_raw(self) }
-            .free_common_data()
-            .free_via_vtable();
+        let raw_ptr = self.ptr.as_ptr();
+        // Safety: the common data and the allocator are initialized and never
+        // used again. The object might not be initialized so it isn't dropped.
+        unsafe {
+            ptr::drop_in_place(ptr::addr_of_mut!((*raw_ptr).common));
+            let allocator = ptr::read((raw_ptr as *mut A).sub(1));
+            dealloc_raw_thin_with_header_in::<A, A>(
+                raw_ptr as *mut u8,
+                Layout::new::<
+                    RawThin<'_, <V as ThinTrait<C>>::VTable, C, ThinTraitAutoTraitsMarker<V, C>, T>,
+                >(),
+                &allocator,
+            );
+        }
     }
 }
 
 /// A lower level API for [`ThinBox`]. Note that if this is dropped then the
 /// underlying memory won't be freed.
+///
+/// The allocator `A` is stored inside the heap allocation right before the
+/// [`RawThin`] value, see the [`allocator`] module.
+#[cfg(feature = "alloc")]
+#[cfg_attr(feature = "docs", doc(cfg(feature = "alloc")))]
 #[repr(transparent)]
-pub struct RawThinBox<'erased_data, V, C, M, D>
+pub struct RawThinBox<'erased_data, V, C, M, D, A = Global>
+where
+    M: ?Sized,
+{
+    ptr: NonNull<RawThin<'erased_data, V, C, M, D>>,
+    _allocator: PhantomData<A>,
+}
+// Safety: the box owns the `RawThin` value and the allocator stored before it.
+#[cfg(feature = "alloc")]
+unsafe impl<'erased_data, V, C, M, D, A> Send for RawThinBox<'erased_data, V, C, M, D, A>
+where
+    M: ?Sized,
+    RawThin<'erased_data, V, C, M, D>: Send,
+    A: Send,
+{
+}
+#[cfg(feature = "alloc")]
+unsafe impl<'erased_data, V, C, M, D, A> Sync for RawThinBox<'erased_data, V, C, M, D, A>
 where
     M: ?Sized,
+    RawThin<'erased_data, V, C, M, D>: Sync,
+    A: Sync,
 {
-    inner: ManuallyDrop<Box<RawThin<'erased_data, V, C, M, D>>>,
 }
+#[cfg(feature = "alloc")]
 impl<'erased_data, V, C, D>
     RawThinBox<'erased_data, V, C, auto_traits::AutoTraitConfigMarkerType<V, ()>, Unerased<D>>
 where
@@ -2097,20 +5267,53 @@ where
     /// Note that [`RawThinBox`] is quite a low level API so prefer [`ThinBox::new`]
     /// or [`ThinBoxWithoutCommon::new`].
     pub fn new(x: D, common: C) -> Self {
-        Self {
-            inner: ManuallyDrop::new(Box::new(RawThin {
-                vtable: V::get_vtable().vtable,
-                common,
-                // Safety: we use the default auto trait config marker for this
-                // vtable which should always be safe.
-                _not_send_or_sync: PhantomData,
-                _lifetime: PhantomData,
-                _object: Unerased::new(x),
-            })),
+        Self::new_in(x, common, Global)
+    }
+    /// Create a new [`RawThinBox`] that stores some data in a heap allocation.
+    /// The object and the common data are given back if the allocation fails.
+    pub fn try_new(x: D, common: C) -> Result<Self, (D, C)> {
+        Self::try_new_in(x, common, Global)
+    }
+}
+#[cfg(feature = "alloc")]
+impl<'erased_data, V, C, D, A>
+    RawThinBox<'erased_data, V, C, auto_traits::AutoTraitConfigMarkerType<V, ()>, Unerased<D>, A>
+where
+    V: auto_traits::VTableEnforcedAutoTraits,
+    V: GetThinTraitVTable<D>,
+    D: 'erased_data,
+    A: Allocator,
+{
+    /// Create a new [`RawThinBox`] that stores some data in a heap allocation
+    /// that is managed by `allocator`.
+    pub fn new_in(x: D, common: C, allocator: A) -> Self {
+        Self::alloc_in(Self::new_raw(x, common), allocator)
+    }
+    /// Create a new [`RawThinBox`] that stores some data in a heap allocation
+    /// that is managed by `allocator`. The object and the common data are
+    /// given back if the allocation fails.
+    pub fn try_new_in(x: D, common: C, allocator: A) -> Result<Self, (D, C)> {
+        Self::try_alloc_in(Self::new_raw(x, common), allocator)
+            .map_err(|(raw, _)| (raw._object.into_inner(), raw.common))
+    }
+    fn new_raw(
+        x: D,
+        common: C,
+    ) -> RawThin<'erased_data, V, C, auto_traits::AutoTraitConfigMarkerType<V, ()>, Unerased<D>>
+    {
+        RawThin {
+            vtable: V::get_vtable().vtable,
+            common,
+            // Safety: we use the default auto trait config marker for this
+            // vtable which should always be safe.
+            _not_send_or_sync: PhantomData,
+            _lifetime: PhantomData,
+            _object: Unerased::new(x),
         }
     }
 }
-impl<V, C, M, D> fmt::Debug for RawThinBox<'_, V, C, M, D>
+#[cfg(feature = "alloc")]
+impl<V, C, M, D, A> fmt::Debug for RawThinBox<'_, V, C, M, D, A>
 where
     M: ?Sized,
 {
@@ -2118,12 +5321,85 @@ where
         f.debug_struct(get_type_name!(RawThinBox)).finish()
     }
 }
-impl<'erased_data, V, C, M, D> RawThinBox<'erased_data, V, C, M, D>
+#[cfg(feature = "alloc")]
+impl<'erased_data, V, C, M, D, A> RawThinBox<'erased_data, V, C, M, D, A>
+where
+    M: ?Sized,
+    A: Allocator,
+{
+    /// Move a [`RawThin`] value into a new heap allocation that is managed by
+    /// `allocator`.
+    fn alloc_in(raw: RawThin<'erased_data, V, C, M, D>, allocator: A) -> Self {
+        match Self::try_alloc_in(raw, allocator) {
+            Ok(this) => this,
+            Err(_) => {
+                alloc::alloc::handle_alloc_error(
+                    raw_thin_layout_with_header::<A>(Layout::new::<
+                        RawThin<'erased_data, V, C, M, D>,
+                    >())
+                    .0,
+                )
+            }
+        }
+    }
+    /// Move a [`RawThin`] value into a new heap allocation that is managed by
+    /// `allocator`. Both values are given back if the allocation fails.
+    fn try_alloc_in(
+        raw: RawThin<'erased_data, V, C, M, D>,
+        allocator: A,
+    ) -> Result<Self, (RawThin<'erased_data, V, C, M, D>, A)> {
+        let raw_ptr = match try_alloc_raw_thin_with_header_in::<A, A>(
+            Layout::new::<RawThin<'erased_data, V, C, M, D>>(),
+            &allocator,
+        ) {
+            Ok(raw_ptr) => raw_ptr.cast::<RawThin<'erased_data, V, C, M, D>>(),
+            Err(AllocError) => return Err((raw, allocator)),
+        };
+        // Safety: `raw_thin_layout_with_header` ensures that both values are
+        // aligned and fit inside the allocation.
+        unsafe {
+            ptr::write(raw_ptr.as_ptr(), raw);
+            ptr::write((raw_ptr.as_ptr() as *mut A).sub(1), allocator);
+        }
+        Ok(Self {
+            ptr: raw_ptr,
+            _allocator: PhantomData,
+        })
+    }
+    /// Free the heap allocation with the allocator that is stored inside it.
+    ///
+    /// # Safety
+    ///
+    /// The [`RawThin`] value must have been dropped or moved out of the
+    /// allocation and `raw_layout` must be its layout.
+    unsafe fn dealloc(self, raw_layout: Layout) {
+        let raw_ptr = self.ptr.as_ptr() as *mut u8;
+        let allocator = ptr::read((raw_ptr as *mut A).sub(1));
+        dealloc_raw_thin_with_header_in::<A, A>(raw_ptr, raw_layout, &allocator);
+    }
+}
+#[cfg(feature = "alloc")]
+impl<'erased_data, V, C, M, D, A> RawThinBox<'erased_data, V, C, M, D, A>
 where
     M: ?Sized,
 {
+    /// The allocator that manages the heap allocation.
+    pub fn allocator(&self) -> &A {
+        // Safety: the allocator is stored right before the `RawThin` value.
+        unsafe { &*(self.ptr.as_ptr() as *const A).sub(1) }
+    }
+    fn raw(&self) -> &RawThin<'erased_data, V, C, M, D> {
+        // Safety: the box owns the allocation. `RawThinBox` doesn't guarantee
+        // that the object isn't dropped, so its methods only access it if
+        // that is known (for example because of the `Unerased` wrapper).
+        unsafe { self.ptr.as_ref() }
+    }
+    fn raw_mut(&mut self) -> &mut RawThin<'erased_data, V, C, M, D> {
+        // Safety: see `raw`.
+        unsafe { self.ptr.as_mut() }
+    }
     /// Erase the type of the stored object.
-    pub fn erase(self) -> RawThinBox<'erased_data, V, C, M, ()> {
+    pub fn erase(self) -> RawThinBox<'erased_data, V, C, M, (), A> {
         // Static assertions about the `()` type for clarity:
         const _: [(); 0] = [(); mem::size_of::<()>()];
         const _: [(); 1] = [(); mem::align_of::<()>()];
@@ -2138,7 +5414,7 @@ where
     ///
     /// The returned type can be kept around longer than the erased data will
     /// be alive and allow for use-after-free bugs (via dangling pointers).
-    pub unsafe fn forget_lifetime(self) -> RawThinBox<'static, V, C, M, D> {
+    pub unsafe fn forget_lifetime(self) -> RawThinBox<'static, V, C, M, D, A> {
         // Safety:
         // we are only transmuting a lifetime so the layout of the type will be
         // the same.
@@ -2148,7 +5424,7 @@ where
     /// type won't implement any auto traits even if it would be safe to do so.
     pub fn weaken_auto_traits_marker(
         self,
-    ) -> RawThinBox<'erased_data, V, C, auto_traits::NoAutoTraits, D> {
+    ) -> RawThinBox<'erased_data, V, C, auto_traits::NoAutoTraits, D, A> {
         // Safety:
         // The marker type is stored inside a `PhantomData` type so it never
         // affects the layout of the `RawThin` type.
@@ -2160,12 +5436,43 @@ where
         // doesn't implement any auto traits even if it would be safe to do so.
         unsafe { mem::transmute(self) }
     }
+    /// Change the auto traits marker type.
+    ///
+    /// # Safety
+    ///
+    /// The erased object must implement all auto traits that are implemented by
+    /// the new marker type.
+    unsafe fn cast_auto_traits_marker<M2: ?Sized>(
+        self,
+    ) -> RawThinBox<'erased_data, V, C, M2, D, A> {
+        // Safety: the marker type is stored inside a `PhantomData` type so it
+        // never affects the layout of the `RawThin` type.
+        mem::transmute(self)
+    }
+    /// Replace the vtable with the vtable of a supertrait that is stored inside
+    /// of it, see [`VTableUpcast`]. The heap allocation isn't changed.
+    pub fn upcast_vtable<Id>(self) -> RawThinBox<'erased_data, V::VTable, C, M, D, A>
+    where
+        V: VTableUpcast<Id>,
+        Id: ?Sized,
+    {
+        let vtable = VTableUpcast::<Id>::upcast_vtable(self.raw().vtable.static_ref());
+        // Safety: the vtable is stored as a pointer so the layout doesn't depend on
+        // its type. It is overwritten below.
+        let mut this: RawThinBox<'erased_data, V::VTable, C, M, D, A> =
+            unsafe { mem::transmute(self) };
+        // The supertrait's vtable is stored inside the `'static` vtable of the
+        // subtrait and `VTableUpcast` guarantees it has sensible behavior for the
+        // erased object.
+        this.raw_mut().vtable = StaticVTableRef(vtable.into());
+        this
+    }
     /// Drop the common data in place.
-    pub fn free_common_data(self) -> RawThinBox<'erased_data, V, Taken<C>, M, D> {
+    pub fn free_common_data(self) -> RawThinBox<'erased_data, V, Taken<C>, M, D, A> {
         // Safety: `Taken<C>` is a `repr(transparent)` wrapper around `C`
-        let mut taken: RawThinBox<'erased_data, V, Taken<C>, M, D> =
+        let mut taken: RawThinBox<'erased_data, V, Taken<C>, M, D, A> =
             unsafe { mem::transmute(self) };
-        let wrapper: &mut Taken<_> = &mut taken.inner.common;
+        let wrapper: &mut Taken<_> = &mut taken.raw_mut().common;
         // Safety: we just owned `C` so it is safe to drop it, `Taken` will ensure
         // we never touch it again. If `C` is already a `Taken` struct then it doesn't
         // do anything when dropped and this would be a noop.
@@ -2173,11 +5480,11 @@ where
         taken
     }
     /// Take the common data from the allocation.
-    pub fn take_common_data(self) -> (RawThinBox<'erased_data, V, Taken<C>, M, D>, C) {
+    pub fn take_common_data(self) -> (RawThinBox<'erased_data, V, Taken<C>, M, D, A>, C) {
         // Safety: `Taken<C>` is a `repr(transparent)` wrapper around `C`
-        let mut taken: RawThinBox<'erased_data, V, Taken<C>, M, D> =
+        let mut taken: RawThinBox<'erased_data, V, Taken<C>, M, D, A> =
             unsafe { mem::transmute(self) };
-        let wrapper: &mut Taken<_> = &mut taken.inner.common;
+        let wrapper: &mut Taken<_> = &mut taken.raw_mut().common;
         // Safety: we just owned `C` so it is safe to take it, `Taken` will ensure
         // we never touch it again.
         let common = unsafe { ManuallyDrop::take(&mut wrapper.0) };
@@ -2185,21 +5492,23 @@ where
     }
 }
 /// Methods that are only available after the common data has been taken or freed.
-impl<'erased_data, V, C, M, D> RawThinBox<'erased_data, V, Taken<C>, M, D>
+#[cfg(feature = "alloc")]
+impl<'erased_data, V, C, M, D, A> RawThinBox<'erased_data, V, Taken<C>, M, D, A>
 where
     M: ?Sized,
 {
     /// Put some common data into the allocation.
-    pub fn put_common_data(mut self, common: C) -> RawThinBox<'erased_data, V, C, M, D> {
+    pub fn put_common_data(mut self, common: C) -> RawThinBox<'erased_data, V, C, M, D, A> {
         // Safety: we write some valid common data to the allocation and then
         // we remove the `repr(transparent)` wrapper `Taken` to indicate that
         // the common data is in a state where it can be used.
         unsafe {
-            ptr::write(&mut self.inner.common.0, ManuallyDrop::new(common));
+            ptr::write(&mut self.raw_mut().common.0, ManuallyDrop::new(common));
             mem::transmute(self)
         }
     }
-    /// Use the vtable to free the wrapped box.
+    /// Use the vtable to drop the type erased object and then free the
+    /// allocation with the allocator that is stored inside it.
     ///
     /// The common data must have been freed before the vtable is used to free the
     /// allocation. This is to support taking tha common data out of the allocation.
@@ -2208,27 +5517,28 @@ where
     pub fn free_via_vtable(self)
     where
         V: VTableDrop<C>,
+        A: Allocator,
     {
         // Get the vtable before we free the allocation:
-        let vtable = self.inner.vtable.static_ref();
+        let vtable = self.raw().vtable.static_ref();
+        let raw_layout = raw_thin_layout::<V, C>(vtable.erased_layout());
 
         // Safety: the vtable manages a type that is the same as the one in `self`.
+        // The common data has been freed or taken so it is marked as `Split` to
+        // ensure the vtable doesn't access it. We forget the auto traits marker
+        // since it is only a zero sized `PhantomData` and shouldn't affect the
+        // type's layout. (We don't want to generate one vtable per auto traits
+        // marker type.)
         unsafe {
-            V::drop_erased_box(
-                vtable,
-                self
-                    // We forget the auto traits marker since it is only a zero sized `PhantomData`
-                    // and shouldn't affect the type's layout. (We don't want to generate one vtable per
-                    // auto traits marker type.)
-                    .weaken_auto_traits_marker()
-                    // The object type is probably already erased, but this call
-                    // allows this method to be called even when it isn't.
-                    .erase(),
-            )
-        };
+            let erased = self.ptr.as_ptr()
+                as *mut RawThin<'erased_data, V, Split<C>, auto_traits::NoAutoTraits, ()>;
+            vtable.drop_erased_in_place(&mut *erased);
+            self.dealloc(raw_layout);
+        }
     }
 }
-impl<'erased_data, V, C, M> RawThinBox<'erased_data, V, C, M, ()>
+#[cfg(feature = "alloc")]
+impl<'erased_data, V, C, M, A> RawThinBox<'erased_data, V, C, M, (), A>
 where
     M: ?Sized,
 {
@@ -2238,66 +5548,86 @@ where
     ///
     /// The type specified via the `D2` type parameter must be the actual type of
     /// the type erased object that is stored inside this allocation.
-    pub unsafe fn unerase<D2>(self) -> RawThinBox<'erased_data, V, C, M, Unerased<D2>> {
+    pub unsafe fn unerase<D2>(self) -> RawThinBox<'erased_data, V, C, M, Unerased<D2>, A> {
         mem::transmute(self)
     }
 }
 /// These methods require that the object's type is known.
-impl<'erased_data, V, C, M, D> RawThinBox<'erased_data, V, Taken<C>, M, Unerased<D>>
+#[cfg(feature = "alloc")]
+impl<'erased_data, V, C, M, D, A> RawThinBox<'erased_data, V, Taken<C>, M, Unerased<D>, A>
 where
     M: ?Sized,
+    A: Allocator,
 {
     /// Take the wrapped object out and then free the allocation.
     pub fn into_inner(self) -> D {
         // Safety: `Taken` is `repr(transparent)`
         let mut this = unsafe {
             mem::transmute::<
-                RawThinBox<'erased_data, V, Taken<C>, M, Unerased<D>>,
-                RawThinBox<'erased_data, V, Taken<C>, M, Unerased<Taken<D>>>,
+                RawThinBox<'erased_data, V, Taken<C>, M, Unerased<D>, A>,
+                RawThinBox<'erased_data, V, Taken<C>, M, Unerased<Taken<D>>, A>,
             >(self)
         };
         // Safety: the `Taken` wrapper ensures that the object won't be touched again,
         // even when the box is freed.
-        let object = unsafe { ManuallyDrop::take(&mut (this.inner._object.0).0) };
+        let object = unsafe { ManuallyDrop::take(&mut (this.raw_mut()._object.0).0) };
         this.free();
         object
     }
+    /// Move the wrapped object into a new [`Box`] and then free the allocation.
+    pub fn into_box(self) -> Box<D> {
+        Box::new(self.into_inner())
+    }
 }
 /// These methods require that the object's type is known.
-impl<'erased_data, V, C, M, D> RawThinBox<'erased_data, V, C, M, Unerased<D>>
+#[cfg(feature = "alloc")]
+impl<'erased_data, V, C, M, D, A> RawThinBox<'erased_data, V, C, M, Unerased<D>, A>
 where
     M: ?Sized,
 {
     /// Change the auto traits config that determines what auto traits are
     /// implemented.
-    pub fn with_auto_trait_config<A>(
+    pub fn with_auto_trait_config<Config>(
         self,
-    ) -> RawThinBox<'erased_data, V, C, auto_traits::AutoTraitConfigMarkerType<V, A>, Unerased<D>>
+    ) -> RawThinBox<
+        'erased_data,
+        V,
+        C,
+        auto_traits::AutoTraitConfigMarkerType<V, Config>,
+        Unerased<D>,
+        A,
+    >
     where
         // Allows us to get a type that implements some default auto traits that
         // are required by the vtable (supertraits of the vtable trait):
         V: auto_traits::VTableEnforcedAutoTraits,
         // Ensure we can get the marker type for the auto trait config:
-        A: auto_traits::AutoTraitConfig<V::UncheckedAutoTraitMarker>,
+        Config: auto_traits::AutoTraitConfig<V::UncheckedAutoTraitMarker>,
         // Enforces any extra auto traits from the auto trait config for the
         // stored type:
-        D: auto_traits::HasAutoTraits<A>,
+        D: auto_traits::HasAutoTraits<Config>,
     {
         // Safety:
         // The marker type that we are changing with this transmute is wrapped
         // inside a `PhantomData` and so won't affect the type's layout.
         //
-        // the `D: auto_traits::HasAutoTraits<A>` trait bound ensures that
+        // the `D: auto_traits::HasAutoTraits<Config>` trait bound ensures that
         // the the stored type implements the auto traits that the marker type
         // requires.
         unsafe { mem::transmute(self) }
     }
     /// Free the wrapped box.
-    pub fn free(mut self) {
+    pub fn free(self)
+    where
+        A: Allocator,
+    {
         // Safety: the wrapped type must have been unerased before calling this
         // for example using the `unerase` method, otherwise the `D` type couldn't
         // be wrapped in `Unerased`.
-        unsafe { ManuallyDrop::drop(&mut self.inner) }
+        unsafe {
+            ptr::drop_in_place(self.ptr.as_ptr());
+            self.dealloc(Layout::new::<RawThin<'erased_data, V, C, M, Unerased<D>>>());
+        }
     }
 }
 
@@ -2306,6 +5636,7 @@ where
 #[derive(Debug)]
 pub struct Unerased<T>(T);
 impl<T> Unerased<T> {
+    #[cfg(feature = "alloc")]
     fn new(value: T) -> Self {
         Self(value)
     }
@@ -2358,14 +5689,17 @@ where
 {
     /// Create a wrapper around some data that allows that data to be borrowed as
     /// a thin pointer even though the type of the stored data is erased.
-    pub fn new(x: T, common: C) -> Self
+    ///
+    /// This is a `const fn` so the wrapper can be stored in a `static`, see
+    /// [`OwnedThin::as_thin`].
+    pub const fn new(x: T, common: C) -> Self
     where
         T: auto_traits::HasAutoTraits<V::AutoTraitConfig>,
         V::VTable: GetThinTraitVTable<T>,
     {
         Self {
             inner: RawThin {
-                vtable: V::VTable::get_vtable().vtable,
+                vtable: <V::VTable as GetThinTraitVTable<T>>::VTABLE.vtable,
                 common,
                 _not_send_or_sync: PhantomData,
                 _lifetime: PhantomData,
@@ -2377,6 +5711,46 @@ where
     pub fn into_inner(self) -> (T, C) {
         (self.inner._object, self.inner.common)
     }
+    /// Borrow the stored data as a thin pointer. Unlike the [`Deref`]
+    /// implementation this can be used in const contexts.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use thin_trait_object::{define_v_table, OwnedThin, Thin};
+    ///
+    /// define_v_table!(
+    ///     trait Handler {
+    ///         fn handle(&self, request: u32) -> Option<u32>;
+    ///     }
+    /// );
+    /// struct Double;
+    /// impl Handler for Double {
+    ///     fn handle(&self, request: u32) -> Option<u32> {
+    ///         request.checked_mul(2)
+    ///     }
+    /// }
+    /// struct Fixed(u32);
+    /// impl Handler for Fixed {
+    ///     fn handle(&self, _: u32) -> Option<u32> {
+    ///         Some(self.0)
+    ///     }
+    /// }
+    ///
+    /// static DOUBLE: OwnedThin<dyn Handler + Sync, &str, Double> = OwnedThin::new(Double, "double");
+    /// static FIXED: OwnedThin<dyn Handler + Sync, &str, Fixed> = OwnedThin::new(Fixed(7), "fixed");
+    /// static HANDLERS: [&Thin<'_, dyn Handler + Sync, &str>; 2] =
+    ///     [OwnedThin::as_thin(&DOUBLE), OwnedThin::as_thin(&FIXED)];
+    ///
+    /// let results: Vec<_> = HANDLERS.iter().map(|handler| handler.handle(4)).collect();
+    /// assert_eq!(results, [Some(8), Some(7)]);
+    /// assert_eq!(*Thin::split_common(HANDLERS[1]).1, "fixed");
+    /// ```
+    pub const fn as_thin(this: &Self) -> &Thin<'static, V, C> {
+        // Safety: `Thin` is a transparent wrapper around `Inner` where the type that
+        // is stored is erased.
+        unsafe { &*(&this.inner as *const _ as *const Thin<'static, V, C>) }
+    }
 }
 impl<V, C, T> Deref for OwnedThin<V, C, T>
 where
@@ -2385,10 +5759,7 @@ where
     type Target = Thin<'static, V, C>;
 
     fn deref(&self) -> &Self::Target {
-        // Safety: `Thin` is a transparent wrapper around `Inner` where the type that
-        // is stored is erased.
-        let thin = &self.inner as *const _ as *const Thin<'static, V, C>;
-        unsafe { &*thin }
+        Self::as_thin(self)
     }
 }
 impl<V, C, T> DerefMut for OwnedThin<V, C, T>
@@ -2468,6 +5839,110 @@ where
         );
         unsafe { (&mut *thin, &mut *common) }
     }
+
+    /// The size of the type erased object in bytes.
+    pub fn size_of_erased(this: &Self) -> usize {
+        ThinWithoutCommon::size_of_erased(this)
+    }
+    /// The minimum alignment of the type erased object in bytes.
+    pub fn align_of_erased(this: &Self) -> usize {
+        ThinWithoutCommon::align_of_erased(this)
+    }
+    /// The name of the type erased object's type, as returned by
+    /// [`core::any::type_name`].
+    pub fn erased_type_name(this: &Self) -> &'static str {
+        ThinWithoutCommon::erased_type_name(this)
+    }
+
+    /// Upcast to a trait object of a supertrait without changing the auto
+    /// traits. Only the first trait listed in a `#[thin_supertraits(..)]`
+    /// attribute can be used since its vtable is stored at the start of this
+    /// object's vtable (see [`VTablePrefix`]), [`ThinBox::upcast`] supports any
+    /// forwarded supertrait.
+    pub fn upcast<U>(this: &Self) -> &Thin<'erased_data, U, C>
+    where
+        U: ThinTrait<C, AutoTraitConfig = V::AutoTraitConfig> + ?Sized,
+        V::VTable: VTablePrefix<U::TraitObject>
+            + VTableUpcast<U::TraitObject, VTable = <U as ThinTrait<C>>::VTable>,
+    {
+        // Safety: `Thin` is a `repr(transparent)` struct around `RawThin` and
+        // the vtable pointer is also a valid pointer to the supertrait's vtable
+        // (guaranteed by `VTablePrefix`). The vtables were created for the same
+        // erased type so the supertrait's vtable has sensible behavior for it.
+        // The auto traits marker type only changes which trait object its auto
+        // traits are checked against, the auto traits of a supertrait are
+        // implied by the auto traits of the subtrait.
+        unsafe { &*(this as *const Self as *const Thin<'erased_data, U, C>) }
+    }
+
+    /// The layout of the [`RawThin`] value that stores the common data and
+    /// the type erased object.
+    ///
+    /// # Safety
+    ///
+    /// The pointer must point to a [`Thin`] value. The common data and the
+    /// object may have been dropped.
+    #[cfg(feature = "alloc")]
+    unsafe fn raw_layout(this: *const Self) -> Layout {
+        let vtable: &<V as ThinTrait<C>>::VTable = (*this).inner.vtable.static_ref();
+        raw_thin_layout::<<V as ThinTrait<C>>::VTable, C>(vtable.erased_layout())
+    }
+    /// Drop the common data and the type erased object without freeing the
+    /// memory they are stored in.
+    ///
+    /// # Safety
+    ///
+    /// The pointer must point to a valid [`Thin`] value that is owned by the
+    /// caller. After this call the value must only be used to get its vtable
+    /// (for example via [`raw_layout`](Self::raw_layout)) or to free its memory.
+    unsafe fn drop_in_place(this: *mut Self) {
+        ptr::drop_in_place(&mut (*this).inner.common);
+
+        // Don't create a reference to the common data since it is dropped:
+        let erased = &mut *(this as *mut ThinWithoutCommon<'erased_data, V, C>);
+        let vtable = ThinWithoutCommon::get_vtable(erased);
+        vtable.drop_erased_in_place(RawThin::as_weaker_auto_traits_marker_mut(
+            ThinWithoutCommon::as_raw_mut(erased),
+        ));
+    }
+}
+impl<'erased_data, V, C> Thin<'erased_data, V, C>
+where
+    V: ThinTraitDyn<'erased_data, C> + ?Sized,
+{
+    /// Get an ordinary trait object reference to the type erased object.
+    pub fn as_dyn<'a>(this: &'a Self) -> &'a V
+    where
+        V: 'a,
+    {
+        ThinWithoutCommon::as_dyn(this)
+    }
+    /// Get an ordinary mutable trait object reference to the type erased object.
+    pub fn as_dyn_mut<'a>(this: &'a mut Self) -> &'a mut V
+    where
+        V: 'a,
+    {
+        ThinWithoutCommon::as_dyn_mut(this)
+    }
+}
+/// Downcasting is only possible when the type erased object is `'static` since
+/// otherwise its lifetimes can't be checked.
+impl<V, C> Thin<'static, V, C>
+where
+    V: ThinTrait<C> + ?Sized,
+{
+    /// Returns `true` if the type erased object has the type `T`.
+    pub fn is<T: 'static>(this: &Self) -> bool {
+        ThinWithoutCommon::is::<T>(this)
+    }
+    /// Get a reference to the type erased object if it has the type `T`.
+    pub fn downcast_ref<T: 'static>(this: &Self) -> Option<&T> {
+        ThinWithoutCommon::downcast_ref(this)
+    }
+    /// Get a mutable reference to the type erased object if it has the type `T`.
+    pub fn downcast_mut<T: 'static>(this: &mut Self) -> Option<&mut T> {
+        ThinWithoutCommon::downcast_mut(this)
+    }
 }
 impl<'erased_data, V, C> Deref for Thin<'erased_data, V, C>
 where
@@ -2503,12 +5978,53 @@ where
 {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let (_, common) = Self::split_common(self);
-        f.debug_struct(get_type_name!(Thin))
-            .field("common", &common)
-            .finish()
+        let mut f = f.debug_struct(get_type_name!(Thin));
+        match self.inner.vtable.static_ref().erased_debug_vtable() {
+            Some(vtable) => f.field("erased", &ThinWithoutCommon::erased_fmt(self, vtable)),
+            None => f.field("erased_type", &Self::erased_type_name(self)),
+        };
+        f.field("common", &common).finish()
+    }
+}
+impl<V, C> fmt::Display for Thin<'_, V, C>
+where
+    V: ThinTrait<C> + ?Sized,
+    V::VTable: VTableUpcast<dyn fmt::Display, VTable = DisplayVTable>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let vtable = self.inner.vtable.static_ref().upcast_vtable();
+        fmt::Display::fmt(&ThinWithoutCommon::erased_fmt(self, vtable), f)
     }
 }
 
+impl<'erased_data, V, C> ThinWithoutCommon<'erased_data, V, C>
+where
+    V: ThinTraitDyn<'erased_data, C> + ?Sized,
+{
+    /// Get an ordinary trait object reference to the type erased object.
+    pub fn as_dyn<'a>(this: &'a Self) -> &'a V
+    where
+        V: 'a,
+    {
+        // Safety: the vtable is stored inside the type erased object and the
+        // returned pointer is only used to create a shared reference.
+        unsafe {
+            let vtable = Self::get_vtable(this);
+            &*V::erased_as_dyn(vtable, Self::object_ptr(this as *const Self as *mut Self))
+        }
+    }
+    /// Get an ordinary mutable trait object reference to the type erased object.
+    pub fn as_dyn_mut<'a>(this: &'a mut Self) -> &'a mut V
+    where
+        V: 'a,
+    {
+        // Safety: the vtable is stored inside the type erased object.
+        unsafe {
+            let vtable = Self::get_vtable(this);
+            &mut *V::erased_as_dyn(vtable, Self::object_ptr(this))
+        }
+    }
+}
 /// A value whose type has been erased. This can only be used through a reference.
 /// The common data stored with the type erased object has been taken (moved out
 /// of the allocation) or is being accessed through another reference and can not
@@ -2562,13 +6078,110 @@ where
     > {
         &mut this.inner
     }
+    /// The size of the type erased object in bytes.
+    pub fn size_of_erased(this: &Self) -> usize {
+        Self::erased_layout(this).size()
+    }
+    /// The minimum alignment of the type erased object in bytes.
+    pub fn align_of_erased(this: &Self) -> usize {
+        Self::erased_layout(this).align()
+    }
+    /// The name of the type erased object's type, as returned by
+    /// [`core::any::type_name`].
+    pub fn erased_type_name(this: &Self) -> &'static str {
+        this.inner.vtable.static_ref().erased_type_name()
+    }
+    fn erased_layout(this: &Self) -> Layout {
+        this.inner.vtable.static_ref().erased_layout()
+    }
+    /// A pointer to the type erased object, which is stored after the common
+    /// data with the alignment of its type.
+    ///
+    /// # Safety
+    ///
+    /// The pointer must point to a valid [`ThinWithoutCommon`] value.
+    unsafe fn object_ptr(this: *mut Self) -> *mut u8 {
+        let after_common =
+            RawThin::offset_to_common(ptr::addr_of_mut!((*this).inner)).wrapping_add(1) as *mut u8;
+        let align = (*this).inner.vtable.static_ref().erased_layout().align();
+        after_common.wrapping_add(after_common.align_offset(align))
+    }
+    /// Format the type erased object with the vtable of a forwarded formatting
+    /// trait.
+    fn erased_fmt<'a, F>(this: &'a Self, vtable: &'a F) -> ErasedFmt<'a, F> {
+        ErasedFmt {
+            vtable,
+            // Safety: the pointer comes from a reference.
+            object: unsafe { Self::object_ptr(this as *const Self as *mut Self) },
+        }
+    }
+    /// Clone the type erased object into a new allocation that doesn't contain
+    /// any common data.
+    #[cfg(feature = "alloc")]
+    fn clone_erased(
+        this: &Self,
+    ) -> RawThinBox<
+        'erased_data,
+        <V as ThinTrait<C>>::VTable,
+        Taken<C>,
+        ThinTraitAutoTraitsMarker<V, C>,
+        (),
+    >
+    where
+        <V as ThinTrait<C>>::VTable: VTableClone<C>,
+    {
+        // Safety: the vtable is only used to clone the object it manages.
+        let vtable = unsafe { Self::get_vtable(this) };
+        let erased = RawThin::as_weaker_auto_traits_marker(Self::as_raw(this));
+        let cloned = unsafe { vtable.clone_erased_box(erased) };
+        // Safety: the marker type is stored inside a `PhantomData` so it doesn't
+        // affect the layout. The clone has the same type as the original object
+        // so it implements the same auto traits.
+        unsafe { mem::transmute(cloned) }
+    }
+}
+/// Downcasting is only possible when the type erased object is `'static` since
+/// otherwise its lifetimes can't be checked.
+impl<V, C> ThinWithoutCommon<'static, V, C>
+where
+    V: ThinTrait<C> + ?Sized,
+{
+    /// Returns `true` if the type erased object has the type `T`.
+    pub fn is<T: 'static>(this: &Self) -> bool {
+        // Safety: we only use the vtable to get the type id of the erased object.
+        let vtable = unsafe { Self::get_vtable(this) };
+        vtable.erased_type_id() == TypeId::of::<T>()
+    }
+    /// Get a reference to the type erased object if it has the type `T`.
+    pub fn downcast_ref<T: 'static>(this: &Self) -> Option<&T> {
+        if !Self::is::<T>(this) {
+            return None;
+        }
+        // Safety: we just checked that the erased object has the type `T`.
+        let unerased = unsafe { this.inner.as_unerase::<T>() };
+        Some(unerased.as_object())
+    }
+    /// Get a mutable reference to the type erased object if it has the type `T`.
+    pub fn downcast_mut<T: 'static>(this: &mut Self) -> Option<&mut T> {
+        if !Self::is::<T>(this) {
+            return None;
+        }
+        // Safety: we just checked that the erased object has the type `T`.
+        let unerased = unsafe { this.inner.as_unerase_mut::<T>() };
+        Some(unerased.as_object_mut())
+    }
 }
 impl<V, C> fmt::Debug for ThinWithoutCommon<'_, V, C>
 where
     V: ThinTrait<C> + ?Sized,
 {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.debug_struct(get_type_name!(ThinWithoutCommon)).finish()
+        let mut f = f.debug_struct(get_type_name!(ThinWithoutCommon));
+        match self.inner.vtable.static_ref().erased_debug_vtable() {
+            Some(vtable) => f.field("erased", &Self::erased_fmt(self, vtable)),
+            None => f.field("erased_type", &Self::erased_type_name(self)),
+        };
+        f.finish()
     }
 }
 
@@ -2584,7 +6197,11 @@ where
 ///   respected.
 /// - `D` is the type that is erased and is later only accessible via the vtable.
 ///
-/// `repr(C)` to ensure that D remains in the final position.
+/// `repr(C)` to ensure that D remains in the final position. The layout is
+/// guaranteed to be a pointer to the vtable, followed by the common data `C`
+/// and then the erased object, so C code can read the vtable pointer and the
+/// common data of a thin pointer (the vtables of traits that are defined with
+/// `extern "C"` also have a guaranteed layout, see [`define_v_table`]).
 #[repr(C)]
 pub struct RawThin<'erased_data, V, C, M, D>
 where
@@ -2629,6 +6246,12 @@ where
     pub unsafe fn as_unerase_mut<D2>(&mut self) -> &mut RawThin<'erased_data, V, C, M, Split<D2>> {
         &mut *((self as *mut Self) as *mut RawThin<'erased_data, V, C, M, Split<D2>>)
     }
+    /// Cast a pointer to a type erased object to a pointer with the unerased
+    /// type. The pointer can only be dereferenced if the type specified via the
+    /// `D2` type parameter is the actual type of the type erased object.
+    pub fn as_unerase_ptr<D2>(this: *mut Self) -> *mut RawThin<'erased_data, V, C, M, Split<D2>> {
+        this as *mut RawThin<'erased_data, V, C, M, Split<D2>>
+    }
 }
 impl<'erased_data, V, C, M, D> RawThin<'erased_data, V, Split<C>, M, D>
 where
@@ -2642,6 +6265,38 @@ where
         after_vtable.wrapping_add(offset_to_common) as *mut C
     }
 }
+impl<'erased_data, V, C, M> RawThin<'erased_data, V, Split<C>, M, ()>
+where
+    M: ?Sized,
+{
+    /// Clone the type erased object into a new heap allocation that uses the
+    /// same vtable. The common data isn't cloned, the new allocation only has
+    /// space for it which can be filled using [`RawThinBox::put_common_data`].
+    ///
+    /// # Safety
+    ///
+    /// The type specified via the `D` type parameter must be the actual type of
+    /// the type erased object that is stored inside this allocation.
+    #[cfg(feature = "alloc")]
+    pub unsafe fn clone_erased<D: Clone>(&self) -> RawThinBox<'erased_data, V, Taken<C>, M, ()> {
+        let object = self.as_unerase::<D>().as_object().clone();
+        let raw = RawThin::<'erased_data, V, MaybeUninit<C>, M, D> {
+            vtable: StaticVTableRef(self.vtable.0),
+            common: MaybeUninit::uninit(),
+            _not_send_or_sync: PhantomData,
+            _lifetime: PhantomData,
+            _object: object,
+        };
+        // Safety: `Taken<C>` is a `repr(transparent)` wrapper around
+        // `ManuallyDrop<C>` which has the same layout as `MaybeUninit<C>`. The
+        // `Taken` wrapper ensures that the uninitialized common data is never used.
+        let cloned = mem::transmute::<
+            RawThinBox<'erased_data, V, MaybeUninit<C>, M, D>,
+            RawThinBox<'erased_data, V, Taken<C>, M, D>,
+        >(RawThinBox::alloc_in(raw, Global));
+        cloned.erase()
+    }
+}
 impl<'erased_data, V, C, M, D> RawThin<'erased_data, V, Split<C>, M, Split<D>>
 where
     M: ?Sized,
@@ -2739,6 +6394,9 @@ where
 /// This should be equivalent to `&'static V`. The reason for this struct is that
 /// the type system doesn't like `'static` references to types that contain lifetimes.
 /// This might happen if the vtable is for a trait that has lifetimes.
+///
+/// `repr(transparent)` so that [`RawThin`] starts with a plain pointer.
+#[repr(transparent)]
 struct StaticVTableRef<V>(NonNull<V>);
 impl<V> StaticVTableRef<V> {
     fn static_ref<'a>(&self) -> &'a V {
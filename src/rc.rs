@@ -0,0 +1,305 @@
+//! Single-threaded reference-counted thin pointers.
+//!
+//! See the [`sync`](crate::sync) module for a thread-safe alternative.
+
+use super::{
+    alloc_raw_thin_with_header, auto_traits, dealloc_raw_thin_with_header, GetThinTraitVTable,
+    RawThin, Thin, ThinBox, ThinTrait, ThinTraitAutoTraitsMarker,
+};
+
+use core::{cell::Cell, fmt, marker::PhantomData, mem::ManuallyDrop, ops::Deref, ptr::NonNull};
+
+/// The reference counts that are stored right before the [`RawThin`] value.
+struct RcHeader {
+    strong: Cell<usize>,
+    /// All strong pointers collectively hold one weak reference that is
+    /// released when the last strong pointer is dropped.
+    weak: Cell<usize>,
+}
+impl RcHeader {
+    /// Get the header that is stored right before a [`RawThin`] value.
+    ///
+    /// # Safety
+    ///
+    /// The pointer must point to an allocation that was created by [`ThinRc`]
+    /// and that hasn't been freed yet.
+    unsafe fn get<'a, V, C>(ptr: NonNull<Thin<'_, V, C>>) -> &'a Self
+    where
+        V: ThinTrait<C> + ?Sized,
+    {
+        &*(ptr.as_ptr() as *const Self).sub(1)
+    }
+
+    fn increment(count: &Cell<usize>) {
+        count.set(
+            count
+                .get()
+                .checked_add(1)
+                .expect("the reference count of a `ThinRc` overflowed"),
+        );
+    }
+}
+
+/// A single-threaded reference-counted thin pointer to a type erased object.
+///
+/// The reference counts, the vtable pointer, the common data and the type erased
+/// object are all stored in a single heap allocation. Cloning a [`ThinRc`]
+/// only increments the reference count. Use [`ThinRc::downgrade`] to get a
+/// [`ThinWeak`] pointer that doesn't keep the object alive.
+///
+/// The user's trait is only implemented for [`ThinRc`] if all of its methods
+/// take `&self`. Otherwise the methods can still be called on the [`Thin`]
+/// object that the pointer derefs to and [`ThinRc::get_mut`] gives mutable
+/// access while the pointer is unique.
+pub struct ThinRc<'erased_data, V, C>
+where
+    V: ThinTrait<C> + ?Sized,
+{
+    ptr: NonNull<Thin<'erased_data, V, C>>,
+    _owned: PhantomData<Thin<'erased_data, V, C>>,
+}
+impl<'erased_data, V, C> ThinRc<'erased_data, V, C>
+where
+    V: ThinTrait<C> + ?Sized,
+{
+    /// Create a new [`ThinRc`] that stores some data in a heap allocation.
+    pub fn new<T>(x: T, common: C) -> Self
+    where
+        T: auto_traits::HasAutoTraits<V::AutoTraitConfig> + 'erased_data,
+        V::VTable: GetThinTraitVTable<T>,
+    {
+        let raw: RawThin<'erased_data, _, _, ThinTraitAutoTraitsMarker<V, C>, _> = RawThin {
+            vtable: V::VTable::get_vtable().vtable,
+            common,
+            _not_send_or_sync: PhantomData,
+            _lifetime: PhantomData,
+            _object: x,
+        };
+        let header = RcHeader {
+            strong: Cell::new(1),
+            weak: Cell::new(1),
+        };
+        let raw = alloc_raw_thin_with_header(header, raw);
+        Self {
+            ptr: raw.cast(),
+            _owned: PhantomData,
+        }
+    }
+}
+impl<'erased_data, V, C> ThinRc<'erased_data, V, C>
+where
+    V: ThinTrait<C> + ?Sized,
+{
+    fn header(&self) -> &RcHeader {
+        // Safety: the allocation is alive as long as there is a strong pointer.
+        unsafe { RcHeader::get(self.ptr) }
+    }
+
+    /// Get the number of [`ThinRc`] pointers to this allocation.
+    pub fn strong_count(this: &Self) -> usize {
+        this.header().strong.get()
+    }
+
+    /// Get the number of [`ThinWeak`] pointers to this allocation.
+    pub fn weak_count(this: &Self) -> usize {
+        this.header().weak.get() - 1
+    }
+
+    /// Create a new [`ThinWeak`] pointer to this allocation.
+    pub fn downgrade(this: &Self) -> ThinWeak<'erased_data, V, C> {
+        RcHeader::increment(&this.header().weak);
+        ThinWeak {
+            ptr: this.ptr,
+            _owned: PhantomData,
+        }
+    }
+
+    /// Returns `true` if the two [`ThinRc`]s point to the same allocation.
+    pub fn ptr_eq(this: &Self, other: &Self) -> bool {
+        this.ptr == other.ptr
+    }
+
+    /// Get a mutable reference to the type erased object if there are no
+    /// other [`ThinRc`] or [`ThinWeak`] pointers to the same allocation.
+    pub fn get_mut(this: &mut Self) -> Option<&mut Thin<'erased_data, V, C>> {
+        if Self::strong_count(this) == 1 && Self::weak_count(this) == 0 {
+            // Safety: this is the only pointer to the allocation.
+            Some(unsafe { &mut *this.ptr.as_ptr() })
+        } else {
+            None
+        }
+    }
+
+    /// Move the type erased object and the common data into a [`ThinBox`] if
+    /// this is the only [`ThinRc`] to the allocation. Otherwise the same
+    /// [`ThinRc`] is returned as an error.
+    ///
+    /// [`ThinWeak`] pointers to the allocation can't be upgraded after this.
+    pub fn try_unwrap(this: Self) -> Result<ThinBox<'erased_data, V, C>, Self> {
+        if Self::strong_count(&this) != 1 {
+            return Err(this);
+        }
+        let this = ManuallyDrop::new(this);
+        this.header().strong.set(0);
+        // Safety: this was the only strong pointer to the allocation so we can
+        // move the value out of it (but don't drop the value).
+        let boxed = unsafe { ThinBox::move_from_thin(this.ptr.as_ptr()) };
+        // Release the weak reference that was held by the strong pointers, this
+        // frees the memory if there are no other weak pointers:
+        drop(ThinWeak {
+            ptr: this.ptr,
+            _owned: PhantomData,
+        });
+        Ok(boxed)
+    }
+}
+impl<V, C> Clone for ThinRc<'_, V, C>
+where
+    V: ThinTrait<C> + ?Sized,
+{
+    fn clone(&self) -> Self {
+        RcHeader::increment(&self.header().strong);
+        Self {
+            ptr: self.ptr,
+            _owned: PhantomData,
+        }
+    }
+}
+impl<'erased_data, V, C> Deref for ThinRc<'erased_data, V, C>
+where
+    V: ThinTrait<C> + ?Sized,
+{
+    type Target = Thin<'erased_data, V, C>;
+
+    fn deref(&self) -> &Self::Target {
+        // Safety: the value is valid as long as there is a strong reference.
+        unsafe { self.ptr.as_ref() }
+    }
+}
+impl<V, C> fmt::Debug for ThinRc<'_, V, C>
+where
+    V: ThinTrait<C> + ?Sized,
+    C: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple(get_type_name!(ThinRc))
+            .field(&**self)
+            .finish()
+    }
+}
+impl<V, C> Drop for ThinRc<'_, V, C>
+where
+    V: ThinTrait<C> + ?Sized,
+{
+    fn drop(&mut self) {
+        let strong = &self.header().strong;
+        strong.set(strong.get() - 1);
+        if strong.get() != 0 {
+            return;
+        }
+        // Safety: this was the last strong pointer to the allocation. The vtable
+        // pointer is left intact so that the memory can be freed later.
+        unsafe { Thin::drop_in_place(self.ptr.as_ptr()) };
+        // Release the weak reference that was held by the strong pointers:
+        drop(ThinWeak {
+            ptr: self.ptr,
+            _owned: PhantomData,
+        });
+    }
+}
+
+/// A weak pointer to an allocation that is managed by [`ThinRc`].
+///
+/// A [`ThinWeak`] doesn't keep the type erased object or the common data alive,
+/// they are dropped when the last [`ThinRc`] is dropped. The allocation itself
+/// (which stores the vtable pointer and the space for the common data) is freed
+/// when the last [`ThinWeak`] is dropped.
+pub struct ThinWeak<'erased_data, V, C>
+where
+    V: ThinTrait<C> + ?Sized,
+{
+    ptr: NonNull<Thin<'erased_data, V, C>>,
+    _owned: PhantomData<Thin<'erased_data, V, C>>,
+}
+impl<'erased_data, V, C> ThinWeak<'erased_data, V, C>
+where
+    V: ThinTrait<C> + ?Sized,
+{
+    fn header(&self) -> &RcHeader {
+        // Safety: the allocation is alive as long as there is a weak pointer.
+        unsafe { RcHeader::get(self.ptr) }
+    }
+
+    /// Create a new [`ThinRc`] to the allocation if the type erased object
+    /// hasn't been dropped yet.
+    pub fn upgrade(&self) -> Option<ThinRc<'erased_data, V, C>> {
+        let strong = &self.header().strong;
+        if strong.get() == 0 {
+            return None;
+        }
+        RcHeader::increment(strong);
+        Some(ThinRc {
+            ptr: self.ptr,
+            _owned: PhantomData,
+        })
+    }
+
+    /// Get the number of [`ThinRc`] pointers to this allocation.
+    pub fn strong_count(&self) -> usize {
+        self.header().strong.get()
+    }
+
+    /// Get the number of [`ThinWeak`] pointers to this allocation or `0` if
+    /// there are no [`ThinRc`] pointers left.
+    pub fn weak_count(&self) -> usize {
+        if self.strong_count() == 0 {
+            0
+        } else {
+            self.header().weak.get() - 1
+        }
+    }
+
+    /// Returns `true` if the two [`ThinWeak`]s point to the same allocation.
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        self.ptr == other.ptr
+    }
+}
+impl<V, C> Clone for ThinWeak<'_, V, C>
+where
+    V: ThinTrait<C> + ?Sized,
+{
+    fn clone(&self) -> Self {
+        RcHeader::increment(&self.header().weak);
+        Self {
+            ptr: self.ptr,
+            _owned: PhantomData,
+        }
+    }
+}
+impl<V, C> fmt::Debug for ThinWeak<'_, V, C>
+where
+    V: ThinTrait<C> + ?Sized,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "({})", get_type_name!(ThinWeak))
+    }
+}
+impl<V, C> Drop for ThinWeak<'_, V, C>
+where
+    V: ThinTrait<C> + ?Sized,
+{
+    fn drop(&mut self) {
+        let weak = &self.header().weak;
+        weak.set(weak.get() - 1);
+        if weak.get() != 0 {
+            return;
+        }
+        // Safety: there are no pointers left to the allocation and the values in
+        // it have already been dropped or moved out. The vtable pointer is still
+        // valid so it can be used to get the layout of the allocation.
+        unsafe {
+            let raw_layout = Thin::raw_layout(self.ptr.as_ptr());
+            dealloc_raw_thin_with_header::<RcHeader>(self.ptr.as_ptr() as *mut u8, raw_layout);
+        }
+    }
+}
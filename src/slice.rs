@@ -0,0 +1,497 @@
+//! Owned slices of type erased objects that all have the same type.
+//!
+//! A [`ThinSlice`] stores a single vtable pointer, the length, the common data
+//! and then the objects next to each other in one heap allocation, so the
+//! vtable pointer is only paid for once instead of once per object.
+
+use super::{
+    auto_traits, GetThinTraitVTable, StaticVTableRef, Thin, ThinTrait, ThinTraitDyn, VTableDrop,
+};
+
+use alloc::vec::Vec;
+use core::{
+    alloc::Layout,
+    fmt,
+    iter::FusedIterator,
+    marker::PhantomData,
+    mem::ManuallyDrop,
+    ops::{Deref, Index, IndexMut, Range},
+    ptr::{self, NonNull},
+};
+
+/// The start of a [`ThinSlice`] allocation, the objects are stored right
+/// after it.
+#[repr(C)]
+struct SliceHeader<V, C> {
+    vtable: StaticVTableRef<V>,
+    len: usize,
+    common: C,
+}
+
+/// The layout of an array of `len` objects with the layout `object`.
+fn array_layout(object: Layout, len: usize) -> Layout {
+    let size = object
+        .size()
+        .checked_mul(len)
+        .expect("the size of a `ThinSlice` should be valid");
+    Layout::from_size_align(size, object.align())
+        .expect("the size of a `ThinSlice` should be valid")
+}
+
+/// The layout of a [`ThinSlice`] allocation and the offset of its first object.
+fn slice_layout<V, C>(object: Layout, len: usize) -> (Layout, usize) {
+    let (layout, offset) = Layout::new::<SliceHeader<V, C>>()
+        .extend(array_layout(object, len))
+        .expect("the size of a `ThinSlice` should be valid");
+    (layout.pad_to_align(), offset)
+}
+
+/// An owned slice of objects that all have the same type, which is erased
+/// behind the trait object type `V`. The slice also stores a single instance
+/// of the common data `C`.
+///
+/// The objects can be indexed like an ordinary slice, which gives ordinary
+/// trait object references (`&V` and `&mut V`) that are created from the
+/// shared vtable pointer. Use [`ThinSlice::get`] or [`ThinSlice::iter`] for
+/// views that also give access to the object's type, see [`Element`].
+///
+/// # Examples
+///
+/// ```
+/// use thin_trait_object::{define_v_table, ThinSlice};
+///
+/// define_v_table!(
+///     trait Counter {
+///         fn add(&mut self, amount: u32) -> u32;
+///     }
+/// );
+/// impl Counter for u32 {
+///     fn add(&mut self, amount: u32) -> u32 {
+///         *self += amount;
+///         *self
+///     }
+/// }
+///
+/// let mut counters = ThinSlice::<dyn Counter, &str>::new(vec![1_u32, 2, 3], "counters");
+/// let mut totals = Vec::new();
+/// counters.for_each_mut(|counter| totals.push(counter.add(10)));
+/// assert_eq!(totals, [11, 12, 13]);
+/// assert_eq!(counters[1].add(1), 13);
+/// assert_eq!(*counters.common(), "counters");
+/// assert_eq!(counters.get(1).unwrap().erased_type_name(), "u32");
+/// ```
+pub struct ThinSlice<'erased_data, V, C>
+where
+    V: ThinTrait<C> + ?Sized,
+{
+    ptr: NonNull<SliceHeader<<V as ThinTrait<C>>::VTable, C>>,
+    _owned: PhantomData<Thin<'erased_data, V, C>>,
+}
+// Safety: the slice owns the type erased objects and the common data.
+unsafe impl<'erased_data, V, C> Send for ThinSlice<'erased_data, V, C>
+where
+    V: ThinTrait<C> + ?Sized,
+    Thin<'erased_data, V, C>: Send,
+{
+}
+unsafe impl<'erased_data, V, C> Sync for ThinSlice<'erased_data, V, C>
+where
+    V: ThinTrait<C> + ?Sized,
+    Thin<'erased_data, V, C>: Sync,
+{
+}
+impl<'erased_data, V, C> ThinSlice<'erased_data, V, C>
+where
+    V: ThinTrait<C> + ?Sized,
+{
+    /// Move the items of a [`Vec`] and the common data into a new
+    /// [`ThinSlice`].
+    pub fn new<T>(items: Vec<T>, common: C) -> Self
+    where
+        T: auto_traits::HasAutoTraits<V::AutoTraitConfig> + 'erased_data,
+        V::VTable: GetThinTraitVTable<T>,
+    {
+        let mut items = ManuallyDrop::new(items);
+        let len = items.len();
+        let (layout, offset) = slice_layout::<V::VTable, C>(Layout::new::<T>(), len);
+        // Safety: the layout is never zero sized since it contains the header.
+        let ptr = match NonNull::new(unsafe { alloc::alloc::alloc(layout) }) {
+            Some(ptr) => ptr,
+            None => alloc::alloc::handle_alloc_error(layout),
+        };
+        // Safety: the allocation fits the header and the items. The items are
+        // moved into the new allocation so the `Vec` is freed without dropping
+        // them.
+        unsafe {
+            ptr::write(
+                ptr.cast().as_ptr(),
+                SliceHeader {
+                    vtable: <V::VTable as GetThinTraitVTable<T>>::get_vtable().vtable,
+                    len,
+                    common,
+                },
+            );
+            ptr::copy_nonoverlapping(items.as_ptr(), ptr.as_ptr().add(offset) as *mut T, len);
+            items.set_len(0);
+            ManuallyDrop::drop(&mut items);
+        }
+        Self {
+            ptr: ptr.cast(),
+            _owned: PhantomData,
+        }
+    }
+
+    fn header(&self) -> &SliceHeader<V::VTable, C> {
+        // Safety: the header is valid as long as the slice is alive.
+        unsafe { self.ptr.as_ref() }
+    }
+    fn vtable(&self) -> &V::VTable {
+        self.header().vtable.static_ref()
+    }
+    /// The layout of a single object.
+    fn object_layout(&self) -> Layout {
+        self.vtable().erased_layout()
+    }
+    /// A pointer to the first object.
+    fn objects(&self) -> *mut u8 {
+        let (_, offset) = slice_layout::<V::VTable, C>(self.object_layout(), 0);
+        self.ptr.as_ptr().cast::<u8>().wrapping_add(offset)
+    }
+    /// A pointer to the object at an index, which must be in bounds.
+    fn object(&self, index: usize) -> *mut u8 {
+        debug_assert!(index < self.len());
+        self.objects()
+            .wrapping_add(index * self.object_layout().size())
+    }
+
+    /// The number of objects in the slice.
+    pub fn len(&self) -> usize {
+        self.header().len
+    }
+    /// Returns `true` if the slice doesn't contain any objects.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    /// The common data that is stored once for all objects.
+    pub fn common(&self) -> &C {
+        &self.header().common
+    }
+    /// The common data that is stored once for all objects.
+    pub fn common_mut(&mut self) -> &mut C {
+        // Safety: the slice is borrowed mutably.
+        unsafe { &mut self.ptr.as_mut().common }
+    }
+    /// The name of the objects' type, as returned by [`core::any::type_name`].
+    pub fn erased_type_name(&self) -> &'static str {
+        self.vtable().erased_type_name()
+    }
+
+    /// Get a view of an object in the slice.
+    pub fn get(&self, index: usize) -> Option<Element<'_, 'erased_data, V, C>> {
+        if index >= self.len() {
+            return None;
+        }
+        Some(Element {
+            vtable: self.vtable(),
+            object: self.object(index),
+            _borrowed: PhantomData,
+        })
+    }
+    /// Iterate over views of the objects in the slice.
+    pub fn iter(&self) -> Iter<'_, 'erased_data, V, C> {
+        Iter {
+            vtable: self.vtable(),
+            objects: self.objects(),
+            size: self.object_layout().size(),
+            indexes: 0..self.len(),
+            _borrowed: PhantomData,
+        }
+    }
+}
+impl<'erased_data, V, C> ThinSlice<'erased_data, V, C>
+where
+    V: ThinTraitDyn<'erased_data, C> + ?Sized,
+{
+    /// Get an ordinary mutable trait object reference to an object in the
+    /// slice, returns `None` if the index is out of bounds.
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut V> {
+        if index >= self.len() {
+            return None;
+        }
+        // Safety: the object is in bounds and was created with the slice's
+        // vtable. The slice is borrowed mutably.
+        Some(unsafe { &mut *V::erased_as_dyn(self.vtable(), self.object(index)) })
+    }
+    /// Call `f` with an ordinary mutable trait object reference to each object
+    /// in the slice.
+    pub fn for_each_mut<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&mut V),
+    {
+        for index in 0..self.len() {
+            // Safety: the object is in bounds and was created with the slice's
+            // vtable. The slice is borrowed mutably.
+            f(unsafe { &mut *V::erased_as_dyn(self.vtable(), self.object(index)) });
+        }
+    }
+}
+impl<'erased_data, V, C> Index<usize> for ThinSlice<'erased_data, V, C>
+where
+    V: ThinTraitDyn<'erased_data, C> + ?Sized,
+{
+    type Output = V;
+
+    fn index(&self, index: usize) -> &V {
+        match self.get(index) {
+            Some(element) => element.as_dyn(),
+            None => panic!(
+                "index out of bounds: the len is {} but the index is {}",
+                self.len(),
+                index
+            ),
+        }
+    }
+}
+impl<'erased_data, V, C> IndexMut<usize> for ThinSlice<'erased_data, V, C>
+where
+    V: ThinTraitDyn<'erased_data, C> + ?Sized,
+{
+    fn index_mut(&mut self, index: usize) -> &mut V {
+        let len = self.len();
+        match self.get_mut(index) {
+            Some(object) => object,
+            None => panic!(
+                "index out of bounds: the len is {} but the index is {}",
+                len, index
+            ),
+        }
+    }
+}
+/// Downcasting is only possible when the type erased objects are `'static`
+/// since otherwise their lifetimes can't be checked.
+impl<V, C> ThinSlice<'static, V, C>
+where
+    V: ThinTrait<C> + ?Sized,
+{
+    /// Get the objects as an ordinary slice if they have the type `T`.
+    pub fn downcast_slice<T: 'static>(&self) -> Option<&[T]> {
+        if self.vtable().erased_type_id() != core::any::TypeId::of::<T>() {
+            return None;
+        }
+        // Safety: the objects have the type `T` and are stored next to each
+        // other.
+        Some(unsafe { core::slice::from_raw_parts(self.objects() as *const T, self.len()) })
+    }
+    /// Get the objects as an ordinary mutable slice if they have the type `T`.
+    pub fn downcast_slice_mut<T: 'static>(&mut self) -> Option<&mut [T]> {
+        if self.vtable().erased_type_id() != core::any::TypeId::of::<T>() {
+            return None;
+        }
+        // Safety: the objects have the type `T` and are stored next to each
+        // other. The slice is borrowed mutably.
+        Some(unsafe { core::slice::from_raw_parts_mut(self.objects() as *mut T, self.len()) })
+    }
+}
+impl<V, C> fmt::Debug for ThinSlice<'_, V, C>
+where
+    V: ThinTrait<C> + ?Sized,
+    C: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct(get_type_name!(ThinSlice))
+            .field("erased_type", &self.erased_type_name())
+            .field("len", &self.len())
+            .field("common", self.common())
+            .finish()
+    }
+}
+impl<V, C> Drop for ThinSlice<'_, V, C>
+where
+    V: ThinTrait<C> + ?Sized,
+{
+    fn drop(&mut self) {
+        let (layout, _) = slice_layout::<V::VTable, C>(self.object_layout(), self.len());
+        for index in 0..self.len() {
+            // Safety: the objects were created with the slice's vtable and
+            // they are never used again.
+            unsafe { self.vtable().drop_erased_object(self.object(index)) };
+        }
+        // Safety: the common data isn't used again and the allocation was
+        // created with this layout.
+        unsafe {
+            ptr::drop_in_place(&mut self.ptr.as_mut().common);
+            alloc::alloc::dealloc(self.ptr.as_ptr().cast(), layout);
+        }
+    }
+}
+
+/// A view of an object in a [`ThinSlice`].
+///
+/// The view derefs to an ordinary trait object so the trait's methods can be
+/// called on it. The vtable pointer isn't stored right before the object so
+/// the view can't be used as a [`Thin`] reference.
+pub struct Element<'a, 'erased_data, V, C>
+where
+    V: ThinTrait<C> + ?Sized,
+{
+    vtable: &'a <V as ThinTrait<C>>::VTable,
+    object: *mut u8,
+    _borrowed: PhantomData<&'a ThinSlice<'erased_data, V, C>>,
+}
+impl<V, C> Element<'_, '_, V, C>
+where
+    V: ThinTrait<C> + ?Sized,
+{
+    /// The size of the type erased object in bytes.
+    pub fn size_of_erased(&self) -> usize {
+        self.vtable.erased_layout().size()
+    }
+    /// The name of the type erased object's type, as returned by
+    /// [`core::any::type_name`].
+    pub fn erased_type_name(&self) -> &'static str {
+        self.vtable.erased_type_name()
+    }
+}
+impl<'a, 'erased_data, V, C> Element<'a, 'erased_data, V, C>
+where
+    V: ThinTraitDyn<'erased_data, C> + ?Sized,
+{
+    /// Get an ordinary trait object reference to the object.
+    pub fn as_dyn(&self) -> &'a V {
+        // Safety: the view borrows the slice and the object was created with
+        // the slice's vtable.
+        unsafe { &*V::erased_as_dyn(self.vtable, self.object) }
+    }
+}
+impl<'erased_data, V, C> Deref for Element<'_, 'erased_data, V, C>
+where
+    V: ThinTraitDyn<'erased_data, C> + ?Sized,
+{
+    type Target = V;
+
+    fn deref(&self) -> &V {
+        self.as_dyn()
+    }
+}
+impl<'a, V, C> Element<'a, 'static, V, C>
+where
+    V: ThinTrait<C> + ?Sized,
+{
+    /// Returns `true` if the type erased object has the type `T`.
+    pub fn is<T: 'static>(&self) -> bool {
+        self.vtable.erased_type_id() == core::any::TypeId::of::<T>()
+    }
+    /// Get a reference to the type erased object if it has the type `T`.
+    pub fn downcast_ref<T: 'static>(&self) -> Option<&'a T> {
+        if !self.is::<T>() {
+            return None;
+        }
+        // Safety: we just checked that the erased object has the type `T`.
+        Some(unsafe { &*(self.object as *const T) })
+    }
+}
+impl<V, C> Clone for Element<'_, '_, V, C>
+where
+    V: ThinTrait<C> + ?Sized,
+{
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+impl<V, C> Copy for Element<'_, '_, V, C> where V: ThinTrait<C> + ?Sized {}
+impl<V, C> fmt::Debug for Element<'_, '_, V, C>
+where
+    V: ThinTrait<C> + ?Sized,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct(get_type_name!(Element))
+            .field("erased_type", &self.erased_type_name())
+            .finish()
+    }
+}
+// Safety: the view only allows shared access to the object.
+unsafe impl<'erased_data, V, C> Send for Element<'_, 'erased_data, V, C>
+where
+    V: ThinTrait<C> + ?Sized,
+    Thin<'erased_data, V, C>: Sync,
+{
+}
+unsafe impl<'erased_data, V, C> Sync for Element<'_, 'erased_data, V, C>
+where
+    V: ThinTrait<C> + ?Sized,
+    Thin<'erased_data, V, C>: Sync,
+{
+}
+
+/// An iterator over views of the objects in a [`ThinSlice`], created by
+/// [`ThinSlice::iter`].
+pub struct Iter<'a, 'erased_data, V, C>
+where
+    V: ThinTrait<C> + ?Sized,
+{
+    vtable: &'a <V as ThinTrait<C>>::VTable,
+    objects: *mut u8,
+    size: usize,
+    indexes: Range<usize>,
+    _borrowed: PhantomData<&'a ThinSlice<'erased_data, V, C>>,
+}
+impl<'a, 'erased_data, V, C> Iter<'a, 'erased_data, V, C>
+where
+    V: ThinTrait<C> + ?Sized,
+{
+    fn element(&self, index: usize) -> Element<'a, 'erased_data, V, C> {
+        Element {
+            vtable: self.vtable,
+            object: self.objects.wrapping_add(index * self.size),
+            _borrowed: PhantomData,
+        }
+    }
+}
+impl<'a, 'erased_data, V, C> Iterator for Iter<'a, 'erased_data, V, C>
+where
+    V: ThinTrait<C> + ?Sized,
+{
+    type Item = Element<'a, 'erased_data, V, C>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let index = self.indexes.next()?;
+        Some(self.element(index))
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.indexes.size_hint()
+    }
+}
+impl<V, C> DoubleEndedIterator for Iter<'_, '_, V, C>
+where
+    V: ThinTrait<C> + ?Sized,
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let index = self.indexes.next_back()?;
+        Some(self.element(index))
+    }
+}
+impl<V, C> ExactSizeIterator for Iter<'_, '_, V, C> where V: ThinTrait<C> + ?Sized {}
+impl<V, C> FusedIterator for Iter<'_, '_, V, C> where V: ThinTrait<C> + ?Sized {}
+impl<V, C> fmt::Debug for Iter<'_, '_, V, C>
+where
+    V: ThinTrait<C> + ?Sized,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct(get_type_name!(Iter))
+            .field("remaining", &self.indexes.len())
+            .finish()
+    }
+}
+// Safety: the iterator only allows shared access to the objects.
+unsafe impl<'erased_data, V, C> Send for Iter<'_, 'erased_data, V, C>
+where
+    V: ThinTrait<C> + ?Sized,
+    Thin<'erased_data, V, C>: Sync,
+{
+}
+unsafe impl<'erased_data, V, C> Sync for Iter<'_, 'erased_data, V, C>
+where
+    V: ThinTrait<C> + ?Sized,
+    Thin<'erased_data, V, C>: Sync,
+{
+}
@@ -0,0 +1,218 @@
+//! Thin boxes that don't allocate for zero-sized objects.
+//!
+//! A [`RawThin`] value that stores a zero-sized object and zero-sized common
+//! data is just a vtable pointer. A [`ThinSmallBox`] stores such a value
+//! directly in its single word instead of in a heap allocation, which is
+//! useful for unit-like strategy types that are created often.
+
+use super::{auto_traits, GetThinTraitVTable, RawThin, StaticVTableRef, Thin, ThinBox, ThinTrait};
+
+use core::{
+    alloc::Layout,
+    fmt,
+    marker::PhantomData,
+    mem::{self, ManuallyDrop},
+    ops::{Deref, DerefMut},
+    ptr::NonNull,
+};
+
+/// Set in the word of a [`ThinSmallBox`] when it stores a pointer to a heap
+/// allocation. Vtable pointers and heap pointers are both aligned to at least
+/// two bytes so this bit is otherwise always zero.
+const HEAP_TAG: usize = 1;
+
+/// A thin pointer like [`ThinBox`] that stores values that only consist of a
+/// vtable pointer inline instead of allocating memory for them.
+///
+/// Objects are stored inline if both the object and the common data are zero
+/// sized and aligned to at most the alignment of a pointer, otherwise they are
+/// stored in a heap allocation just like a [`ThinBox`]. In both cases this type
+/// is a single word large.
+///
+/// Objects that fit in one word, such as small handles, are still stored in a
+/// heap allocation. The single word already holds the vtable pointer, so there
+/// is no room left next to it for the object. A [`ThinInline`] with a buffer of
+/// two words can store such objects without allocating instead.
+///
+/// [`ThinInline`]: crate::ThinInline
+///
+/// # Examples
+///
+/// ```
+/// use thin_trait_object::{define_v_table, ThinSmallBox};
+///
+/// define_v_table!(
+///     trait Strategy {
+///         fn pick(&self, values: &[u32]) -> Option<u32>;
+///     }
+/// );
+/// struct Smallest;
+/// impl Strategy for Smallest {
+///     fn pick(&self, values: &[u32]) -> Option<u32> {
+///         values.iter().copied().min()
+///     }
+/// }
+/// struct Nth(usize);
+/// impl Strategy for Nth {
+///     fn pick(&self, values: &[u32]) -> Option<u32> {
+///         values.get(self.0).copied()
+///     }
+/// }
+///
+/// let smallest = ThinSmallBox::<dyn Strategy, ()>::new(Smallest, ());
+/// let second = ThinSmallBox::<dyn Strategy, ()>::new(Nth(1), ());
+/// assert!(ThinSmallBox::is_inline(&smallest));
+/// assert!(!ThinSmallBox::is_inline(&second));
+/// assert_eq!(smallest.pick(&[3, 1, 2]), Some(1));
+/// assert_eq!(second.pick(&[3, 1, 2]), Some(1));
+/// ```
+#[repr(transparent)]
+pub struct ThinSmallBox<'erased_data, V, C>
+where
+    V: ThinTrait<C> + ?Sized,
+{
+    /// Either a vtable pointer, in which case this word is itself the
+    /// [`RawThin`] value, or a tagged pointer to a heap allocation.
+    word: NonNull<u8>,
+    _owned: PhantomData<ThinBox<'erased_data, V, C>>,
+}
+// Safety: the box owns the type erased object.
+unsafe impl<'erased_data, V, C> Send for ThinSmallBox<'erased_data, V, C>
+where
+    V: ThinTrait<C> + ?Sized,
+    Thin<'erased_data, V, C>: Send,
+{
+}
+unsafe impl<'erased_data, V, C> Sync for ThinSmallBox<'erased_data, V, C>
+where
+    V: ThinTrait<C> + ?Sized,
+    Thin<'erased_data, V, C>: Sync,
+{
+}
+impl<'erased_data, V, C> ThinSmallBox<'erased_data, V, C>
+where
+    V: ThinTrait<C> + ?Sized,
+{
+    /// Create a new [`ThinSmallBox`], this only allocates memory if the object
+    /// or the common data isn't zero sized.
+    pub fn new<T>(x: T, common: C) -> Self
+    where
+        T: auto_traits::HasAutoTraits<V::AutoTraitConfig> + 'erased_data,
+        V::VTable: GetThinTraitVTable<T>,
+    {
+        let raw_layout = Layout::new::<RawThin<'erased_data, V::VTable, C, (), T>>();
+        if raw_layout != Layout::new::<StaticVTableRef<V::VTable>>() {
+            return Self::from(ThinBox::new(x, common));
+        }
+        let vtable = <V::VTable as GetThinTraitVTable<T>>::get_vtable().vtable;
+        debug_assert_eq!(vtable.0.as_ptr() as usize & HEAP_TAG, 0);
+        // The object and the common data are zero sized so they can be
+        // recreated from the vtable pointer. Their destructors are run when the
+        // box is dropped.
+        mem::forget((x, common));
+        Self {
+            word: vtable.0.cast(),
+            _owned: PhantomData,
+        }
+    }
+
+    /// Returns `true` if the object is stored inline instead of in a heap
+    /// allocation.
+    pub fn is_inline(this: &Self) -> bool {
+        this.word.as_ptr() as usize & HEAP_TAG == 0
+    }
+    /// Convert into a [`ThinBox`], this allocates memory if the object is
+    /// stored inline.
+    pub fn into_box(this: Self) -> ThinBox<'erased_data, V, C> {
+        let mut this = ManuallyDrop::new(this);
+        if Self::is_inline(&this) {
+            // Safety: the value is stored inline and it is never used or
+            // dropped again.
+            unsafe { ThinBox::move_from_thin(&mut **this) }
+        } else {
+            // Safety: the pointer was created by `ThinBox::into_raw_ptr`.
+            unsafe { ThinBox::from_raw_ptr(this.heap_ptr()) }
+        }
+    }
+
+    /// The pointer to the heap allocation with the tag removed.
+    fn heap_ptr(&self) -> NonNull<Thin<'erased_data, V, C>> {
+        let ptr = self.word.as_ptr().wrapping_sub(HEAP_TAG);
+        // Safety: the tag was added to a non-null pointer.
+        unsafe { NonNull::new_unchecked(ptr.cast()) }
+    }
+}
+impl<'erased_data, V, C> From<ThinBox<'erased_data, V, C>> for ThinSmallBox<'erased_data, V, C>
+where
+    V: ThinTrait<C> + ?Sized,
+{
+    fn from(boxed: ThinBox<'erased_data, V, C>) -> Self {
+        let ptr = ThinBox::into_raw_ptr(boxed).cast::<u8>();
+        // Safety: the address is aligned so adding the tag can't overflow or
+        // result in a null pointer.
+        let word = unsafe { NonNull::new_unchecked(ptr.as_ptr().wrapping_add(HEAP_TAG)) };
+        Self {
+            word,
+            _owned: PhantomData,
+        }
+    }
+}
+impl<'erased_data, V, C> Deref for ThinSmallBox<'erased_data, V, C>
+where
+    V: ThinTrait<C> + ?Sized,
+{
+    type Target = Thin<'erased_data, V, C>;
+
+    fn deref(&self) -> &Self::Target {
+        if Self::is_inline(self) {
+            // Safety: the word is a vtable pointer and the common data and the
+            // object are zero sized and not more aligned than a pointer, so
+            // the word has the same layout as the `RawThin` value.
+            unsafe { &*(self as *const Self as *const Thin<'erased_data, V, C>) }
+        } else {
+            // Safety: the box owns the heap allocation.
+            unsafe { &*self.heap_ptr().as_ptr() }
+        }
+    }
+}
+impl<'erased_data, V, C> DerefMut for ThinSmallBox<'erased_data, V, C>
+where
+    V: ThinTrait<C> + ?Sized,
+{
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        if Self::is_inline(self) {
+            // Safety: see `Deref`.
+            unsafe { &mut *(self as *mut Self as *mut Thin<'erased_data, V, C>) }
+        } else {
+            // Safety: the box owns the heap allocation.
+            unsafe { &mut *self.heap_ptr().as_ptr() }
+        }
+    }
+}
+impl<V, C> fmt::Debug for ThinSmallBox<'_, V, C>
+where
+    V: ThinTrait<C> + ?Sized,
+    C: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct(get_type_name!(ThinSmallBox))
+            .field("inline", &Self::is_inline(self))
+            .field("value", &**self)
+            .finish()
+    }
+}
+impl<V, C> Drop for ThinSmallBox<'_, V, C>
+where
+    V: ThinTrait<C> + ?Sized,
+{
+    fn drop(&mut self) {
+        if Self::is_inline(self) {
+            // Safety: the value is stored inline and it is never used again.
+            unsafe { Thin::drop_in_place(&mut **self) };
+        } else {
+            // Safety: the pointer was created by `ThinBox::into_raw_ptr` and
+            // it is never used again.
+            drop(unsafe { ThinBox::from_raw_ptr(self.heap_ptr()) });
+        }
+    }
+}
@@ -0,0 +1,427 @@
+//! Thread-safe reference-counted thin pointers.
+//!
+//! See the [`rc`](crate::rc) module for a single-threaded alternative.
+
+use super::{
+    alloc_raw_thin_with_header, auto_traits, dealloc_raw_thin_with_header, GetThinTraitVTable,
+    RawThin, Thin, ThinBox, ThinTrait, ThinTraitAutoTraitsMarker,
+};
+
+use core::{
+    fmt,
+    marker::PhantomData,
+    mem::ManuallyDrop,
+    ops::Deref,
+    ptr::NonNull,
+    sync::atomic::{self, AtomicUsize, Ordering},
+};
+
+/// A soft limit on the number of references to an allocation. Going above this
+/// limit will cause a panic, see the standard library's `Arc` type for a
+/// description of why we need a limit.
+const MAX_REFCOUNT: usize = isize::MAX as usize;
+
+/// The reference counts that are stored right before the [`RawThin`] value.
+struct ArcHeader {
+    strong: AtomicUsize,
+    /// All strong pointers collectively hold one weak reference that is
+    /// released when the last strong pointer is dropped.
+    ///
+    /// This is temporarily set to `usize::MAX` by [`ThinArc::get_mut`] to
+    /// prevent new weak pointers from being created while it checks if the
+    /// allocation is unique.
+    weak: AtomicUsize,
+}
+impl ArcHeader {
+    /// Get the header that is stored right before a [`RawThin`] value.
+    ///
+    /// # Safety
+    ///
+    /// The pointer must point to an allocation that was created by [`ThinArc`]
+    /// and that hasn't been freed yet.
+    unsafe fn get<'a, V, C>(ptr: NonNull<Thin<'_, V, C>>) -> &'a Self
+    where
+        V: ThinTrait<C> + ?Sized,
+    {
+        &*(ptr.as_ptr() as *const Self).sub(1)
+    }
+
+    /// Abort an increment that made a reference count too large.
+    fn check_overflow(count: &AtomicUsize, old: usize) {
+        if old > MAX_REFCOUNT {
+            count.fetch_sub(1, Ordering::Relaxed);
+            panic!("the reference count of a `ThinArc` overflowed");
+        }
+    }
+}
+
+/// A thread-safe reference-counted thin pointer to a type erased object.
+///
+/// The reference counts, the vtable pointer, the common data and the type erased
+/// object are all stored in a single heap allocation. Cloning a [`ThinArc`]
+/// only increments the reference count. Use [`ThinArc::downgrade`] to get a
+/// [`ThinWeak`] pointer that doesn't keep the object alive.
+///
+/// The user's trait is only implemented for [`ThinArc`] if all of its methods
+/// take `&self`. Otherwise the methods can still be called on the [`Thin`]
+/// object that the pointer derefs to and [`ThinArc::get_mut`] gives mutable
+/// access while the pointer is unique.
+pub struct ThinArc<'erased_data, V, C>
+where
+    V: ThinTrait<C> + ?Sized,
+{
+    ptr: NonNull<Thin<'erased_data, V, C>>,
+    _owned: PhantomData<Thin<'erased_data, V, C>>,
+}
+// Safety: the same requirements as for the standard library's `Arc` type.
+unsafe impl<'erased_data, V, C> Send for ThinArc<'erased_data, V, C>
+where
+    V: ThinTrait<C> + ?Sized,
+    Thin<'erased_data, V, C>: Send + Sync,
+{
+}
+unsafe impl<'erased_data, V, C> Sync for ThinArc<'erased_data, V, C>
+where
+    V: ThinTrait<C> + ?Sized,
+    Thin<'erased_data, V, C>: Send + Sync,
+{
+}
+impl<'erased_data, V, C> ThinArc<'erased_data, V, C>
+where
+    V: ThinTrait<C> + ?Sized,
+{
+    /// Create a new [`ThinArc`] that stores some data in a heap allocation.
+    pub fn new<T>(x: T, common: C) -> Self
+    where
+        T: auto_traits::HasAutoTraits<V::AutoTraitConfig> + 'erased_data,
+        V::VTable: GetThinTraitVTable<T>,
+    {
+        let raw: RawThin<'erased_data, _, _, ThinTraitAutoTraitsMarker<V, C>, _> = RawThin {
+            vtable: V::VTable::get_vtable().vtable,
+            common,
+            _not_send_or_sync: PhantomData,
+            _lifetime: PhantomData,
+            _object: x,
+        };
+        let header = ArcHeader {
+            strong: AtomicUsize::new(1),
+            weak: AtomicUsize::new(1),
+        };
+        let raw = alloc_raw_thin_with_header(header, raw);
+        Self {
+            ptr: raw.cast(),
+            _owned: PhantomData,
+        }
+    }
+}
+impl<'erased_data, V, C> ThinArc<'erased_data, V, C>
+where
+    V: ThinTrait<C> + ?Sized,
+{
+    fn header(&self) -> &ArcHeader {
+        // Safety: the allocation is alive as long as there is a strong pointer.
+        unsafe { ArcHeader::get(self.ptr) }
+    }
+
+    /// Get the number of [`ThinArc`] pointers to this allocation.
+    ///
+    /// Other threads can change the count at any time.
+    pub fn strong_count(this: &Self) -> usize {
+        this.header().strong.load(Ordering::SeqCst)
+    }
+
+    /// Get the number of [`ThinWeak`] pointers to this allocation.
+    ///
+    /// Other threads can change the count at any time.
+    pub fn weak_count(this: &Self) -> usize {
+        let weak = this.header().weak.load(Ordering::SeqCst);
+        if weak == usize::MAX {
+            // Locked by `get_mut` which means there were no weak pointers.
+            0
+        } else {
+            weak - 1
+        }
+    }
+
+    /// Returns `true` if the two [`ThinArc`]s point to the same allocation.
+    pub fn ptr_eq(this: &Self, other: &Self) -> bool {
+        this.ptr == other.ptr
+    }
+
+    /// Create a new [`ThinWeak`] pointer to this allocation.
+    pub fn downgrade(this: &Self) -> ThinWeak<'erased_data, V, C> {
+        let weak = &this.header().weak;
+        let mut current = weak.load(Ordering::Relaxed);
+        loop {
+            // Wait while `get_mut` has locked the weak count:
+            if current == usize::MAX {
+                core::hint::spin_loop();
+                current = weak.load(Ordering::Relaxed);
+                continue;
+            }
+            // Acquire to synchronize with the `Release` store in `get_mut`.
+            match weak.compare_exchange_weak(
+                current,
+                current + 1,
+                Ordering::Acquire,
+                Ordering::Relaxed,
+            ) {
+                Ok(old) => {
+                    ArcHeader::check_overflow(weak, old);
+                    return ThinWeak {
+                        ptr: this.ptr,
+                        _owned: PhantomData,
+                    };
+                }
+                Err(old) => current = old,
+            }
+        }
+    }
+
+    /// Get a mutable reference to the type erased object if there are no
+    /// other [`ThinArc`] or [`ThinWeak`] pointers to the same allocation.
+    pub fn get_mut(this: &mut Self) -> Option<&mut Thin<'erased_data, V, C>> {
+        let header = this.header();
+        // Lock the weak count so that no weak pointer can be upgraded while we
+        // check the strong count. Acquire to synchronize with the `Release`
+        // decrements of weak pointers that were dropped.
+        if header
+            .weak
+            .compare_exchange(1, usize::MAX, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            return None;
+        }
+        // Acquire to synchronize with the `Release` decrements of other
+        // pointers that used the object before they were dropped.
+        let unique = header.strong.load(Ordering::Acquire) == 1;
+        header.weak.store(1, Ordering::Release);
+        if unique {
+            // Safety: this is the only pointer to the allocation.
+            Some(unsafe { &mut *this.ptr.as_ptr() })
+        } else {
+            None
+        }
+    }
+
+    /// Move the type erased object and the common data into a [`ThinBox`] if
+    /// this is the only [`ThinArc`] to the allocation. Otherwise the same
+    /// [`ThinArc`] is returned as an error.
+    ///
+    /// [`ThinWeak`] pointers to the allocation can't be upgraded after this.
+    pub fn try_unwrap(this: Self) -> Result<ThinBox<'erased_data, V, C>, Self> {
+        if this
+            .header()
+            .strong
+            .compare_exchange(1, 0, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            return Err(this);
+        }
+        let this = ManuallyDrop::new(this);
+        // Safety: this was the only strong pointer to the allocation so we can
+        // move the value out of it (but don't drop the value).
+        let boxed = unsafe { ThinBox::move_from_thin(this.ptr.as_ptr()) };
+        // Release the weak reference that was held by the strong pointers, this
+        // frees the memory if there are no other weak pointers:
+        drop(ThinWeak {
+            ptr: this.ptr,
+            _owned: PhantomData,
+        });
+        Ok(boxed)
+    }
+}
+impl<V, C> Clone for ThinArc<'_, V, C>
+where
+    V: ThinTrait<C> + ?Sized,
+{
+    fn clone(&self) -> Self {
+        // Relaxed is enough since we already have a reference to the allocation,
+        // see the standard library's `Arc` type.
+        let strong = &self.header().strong;
+        ArcHeader::check_overflow(strong, strong.fetch_add(1, Ordering::Relaxed));
+        Self {
+            ptr: self.ptr,
+            _owned: PhantomData,
+        }
+    }
+}
+impl<'erased_data, V, C> Deref for ThinArc<'erased_data, V, C>
+where
+    V: ThinTrait<C> + ?Sized,
+{
+    type Target = Thin<'erased_data, V, C>;
+
+    fn deref(&self) -> &Self::Target {
+        // Safety: the value is valid as long as there is a strong reference.
+        unsafe { self.ptr.as_ref() }
+    }
+}
+impl<V, C> fmt::Debug for ThinArc<'_, V, C>
+where
+    V: ThinTrait<C> + ?Sized,
+    C: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple(get_type_name!(ThinArc))
+            .field(&**self)
+            .finish()
+    }
+}
+impl<V, C> Drop for ThinArc<'_, V, C>
+where
+    V: ThinTrait<C> + ?Sized,
+{
+    fn drop(&mut self) {
+        if self.header().strong.fetch_sub(1, Ordering::Release) != 1 {
+            return;
+        }
+        // Synchronize with the `Release` decrements of the other pointers so
+        // that their uses of the object happen before we drop it.
+        atomic::fence(Ordering::Acquire);
+
+        // Safety: this was the last strong pointer to the allocation. The vtable
+        // pointer is left intact so that the memory can be freed later.
+        unsafe { Thin::drop_in_place(self.ptr.as_ptr()) };
+        // Release the weak reference that was held by the strong pointers:
+        drop(ThinWeak {
+            ptr: self.ptr,
+            _owned: PhantomData,
+        });
+    }
+}
+
+/// A weak pointer to an allocation that is managed by [`ThinArc`].
+///
+/// A [`ThinWeak`] doesn't keep the type erased object or the common data alive,
+/// they are dropped when the last [`ThinArc`] is dropped. The allocation itself
+/// (which stores the vtable pointer and the space for the common data) is freed
+/// when the last [`ThinWeak`] is dropped.
+pub struct ThinWeak<'erased_data, V, C>
+where
+    V: ThinTrait<C> + ?Sized,
+{
+    ptr: NonNull<Thin<'erased_data, V, C>>,
+    _owned: PhantomData<Thin<'erased_data, V, C>>,
+}
+// Safety: a weak pointer can be upgraded to a `ThinArc` so it has the same
+// requirements.
+unsafe impl<'erased_data, V, C> Send for ThinWeak<'erased_data, V, C>
+where
+    V: ThinTrait<C> + ?Sized,
+    Thin<'erased_data, V, C>: Send + Sync,
+{
+}
+unsafe impl<'erased_data, V, C> Sync for ThinWeak<'erased_data, V, C>
+where
+    V: ThinTrait<C> + ?Sized,
+    Thin<'erased_data, V, C>: Send + Sync,
+{
+}
+impl<'erased_data, V, C> ThinWeak<'erased_data, V, C>
+where
+    V: ThinTrait<C> + ?Sized,
+{
+    fn header(&self) -> &ArcHeader {
+        // Safety: the allocation is alive as long as there is a weak pointer.
+        unsafe { ArcHeader::get(self.ptr) }
+    }
+
+    /// Create a new [`ThinArc`] to the allocation if the type erased object
+    /// hasn't been dropped yet.
+    pub fn upgrade(&self) -> Option<ThinArc<'erased_data, V, C>> {
+        let strong = &self.header().strong;
+        let mut current = strong.load(Ordering::Relaxed);
+        loop {
+            if current == 0 {
+                return None;
+            }
+            // Never increment from zero since the object might already have
+            // been dropped, see the standard library's `Weak` type.
+            match strong.compare_exchange_weak(
+                current,
+                current + 1,
+                Ordering::Acquire,
+                Ordering::Relaxed,
+            ) {
+                Ok(old) => {
+                    ArcHeader::check_overflow(strong, old);
+                    return Some(ThinArc {
+                        ptr: self.ptr,
+                        _owned: PhantomData,
+                    });
+                }
+                Err(old) => current = old,
+            }
+        }
+    }
+
+    /// Get the number of [`ThinArc`] pointers to this allocation.
+    ///
+    /// Other threads can change the count at any time.
+    pub fn strong_count(&self) -> usize {
+        self.header().strong.load(Ordering::SeqCst)
+    }
+
+    /// Get the number of [`ThinWeak`] pointers to this allocation or `0` if
+    /// there are no [`ThinArc`] pointers left.
+    ///
+    /// Other threads can change the count at any time.
+    pub fn weak_count(&self) -> usize {
+        let header = self.header();
+        let weak = header.weak.load(Ordering::SeqCst);
+        if header.strong.load(Ordering::SeqCst) == 0 {
+            0
+        } else {
+            // There is a strong pointer that holds the implicit weak reference
+            // (and `get_mut` can't lock the count while this pointer exists).
+            weak - 1
+        }
+    }
+
+    /// Returns `true` if the two [`ThinWeak`]s point to the same allocation.
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        self.ptr == other.ptr
+    }
+}
+impl<V, C> Clone for ThinWeak<'_, V, C>
+where
+    V: ThinTrait<C> + ?Sized,
+{
+    fn clone(&self) -> Self {
+        // The weak count can't be locked by `get_mut` since this pointer exists.
+        let weak = &self.header().weak;
+        ArcHeader::check_overflow(weak, weak.fetch_add(1, Ordering::Relaxed));
+        Self {
+            ptr: self.ptr,
+            _owned: PhantomData,
+        }
+    }
+}
+impl<V, C> fmt::Debug for ThinWeak<'_, V, C>
+where
+    V: ThinTrait<C> + ?Sized,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "({})", get_type_name!(ThinWeak))
+    }
+}
+impl<V, C> Drop for ThinWeak<'_, V, C>
+where
+    V: ThinTrait<C> + ?Sized,
+{
+    fn drop(&mut self) {
+        if self.header().weak.fetch_sub(1, Ordering::Release) != 1 {
+            return;
+        }
+        atomic::fence(Ordering::Acquire);
+
+        // Safety: there are no pointers left to the allocation and the values in
+        // it have already been dropped or moved out. The vtable pointer is still
+        // valid so it can be used to get the layout of the allocation.
+        unsafe {
+            let raw_layout = Thin::raw_layout(self.ptr.as_ptr());
+            dealloc_raw_thin_with_header::<ArcHeader>(self.ptr.as_ptr() as *mut u8, raw_layout);
+        }
+    }
+}
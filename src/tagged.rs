@@ -0,0 +1,173 @@
+//! Thin boxes that store a small tag in the unused low bits of their pointer.
+//!
+//! The allocation of a [`ThinBox`] starts with a pointer to the vtable so its
+//! address is aligned to at least the alignment of a pointer. The low bits of
+//! the address are therefore always zero and can be used to store some flags
+//! without making the pointer bigger than a single word.
+
+use super::{Thin, ThinBox, ThinTrait, VTableClone};
+
+use core::{
+    fmt,
+    marker::PhantomData,
+    mem,
+    ops::{Deref, DerefMut},
+    ptr::NonNull,
+};
+
+/// A [`ThinBox`] that stores a tag with `BITS` bits in the low bits of its
+/// pointer.
+///
+/// `BITS` can be at most the number of trailing zeros of the alignment of
+/// [`Thin`], so at least 2 on 32-bit targets and 3 on 64-bit targets. Using
+/// more bits will fail to compile when a [`TaggedThinBox`] is created.
+///
+/// The tag is masked out when the [`Thin`] value is accessed, so a
+/// [`TaggedThinBox`] can be used just like a [`ThinBox`].
+pub struct TaggedThinBox<'erased_data, V, C, const BITS: usize>
+where
+    V: ThinTrait<C> + ?Sized,
+{
+    /// A pointer to the allocation with the tag added to its address.
+    tagged: NonNull<u8>,
+    _owned: PhantomData<ThinBox<'erased_data, V, C>>,
+}
+// Safety: this type owns a `ThinBox` and the tag is just an integer.
+unsafe impl<'erased_data, V, C, const BITS: usize> Send for TaggedThinBox<'erased_data, V, C, BITS>
+where
+    V: ThinTrait<C> + ?Sized,
+    ThinBox<'erased_data, V, C>: Send,
+{
+}
+unsafe impl<'erased_data, V, C, const BITS: usize> Sync for TaggedThinBox<'erased_data, V, C, BITS>
+where
+    V: ThinTrait<C> + ?Sized,
+    ThinBox<'erased_data, V, C>: Sync,
+{
+}
+impl<'erased_data, V, C, const BITS: usize> TaggedThinBox<'erased_data, V, C, BITS>
+where
+    V: ThinTrait<C> + ?Sized,
+{
+    /// The bits of the pointer that store the tag. Evaluating this constant
+    /// fails if the alignment of [`Thin`] doesn't leave `BITS` free bits.
+    pub const TAG_MASK: usize = {
+        assert!(
+            BITS as u32 <= mem::align_of::<Thin<'erased_data, V, C>>().trailing_zeros(),
+            "the alignment of the thin pointer doesn't leave enough bits for the tag"
+        );
+        (1 << BITS) - 1
+    };
+
+    /// Create a new [`TaggedThinBox`] that stores a thin box and a tag.
+    ///
+    /// # Panics
+    ///
+    /// If the tag doesn't fit in `BITS` bits.
+    pub fn new(boxed: ThinBox<'erased_data, V, C>, tag: usize) -> Self {
+        Self::check_tag(tag);
+        let ptr = ThinBox::into_raw_ptr(boxed).cast::<u8>();
+        // Safety: the address is aligned so adding a tag that is smaller than
+        // the alignment can't overflow or result in a null pointer.
+        let tagged = unsafe { NonNull::new_unchecked(ptr.as_ptr().wrapping_add(tag)) };
+        Self {
+            tagged,
+            _owned: PhantomData,
+        }
+    }
+    /// Get the thin box and the tag that was stored with it.
+    pub fn into_inner(this: Self) -> (ThinBox<'erased_data, V, C>, usize) {
+        let this = mem::ManuallyDrop::new(this);
+        let tag = Self::tag(&this);
+        // Safety: the pointer was created by `ThinBox::into_raw_ptr` and the
+        // `ManuallyDrop` wrapper ensures it isn't converted back again.
+        let boxed = unsafe { ThinBox::from_raw_ptr(this.untagged()) };
+        (boxed, tag)
+    }
+
+    /// The tag that is stored in the pointer.
+    pub fn tag(this: &Self) -> usize {
+        this.tagged.as_ptr() as usize & Self::TAG_MASK
+    }
+    /// Change the tag that is stored in the pointer.
+    ///
+    /// # Panics
+    ///
+    /// If the tag doesn't fit in `BITS` bits.
+    pub fn set_tag(this: &mut Self, tag: usize) {
+        Self::check_tag(tag);
+        let ptr = this.untagged().cast::<u8>();
+        // Safety: see `TaggedThinBox::new`.
+        this.tagged = unsafe { NonNull::new_unchecked(ptr.as_ptr().wrapping_add(tag)) };
+    }
+
+    fn check_tag(tag: usize) {
+        assert!(
+            tag <= Self::TAG_MASK,
+            "the tag {} doesn't fit in {} bits",
+            tag,
+            BITS
+        );
+    }
+    /// The pointer to the allocation with the tag removed.
+    fn untagged(&self) -> NonNull<Thin<'erased_data, V, C>> {
+        let ptr = self.tagged.as_ptr().wrapping_sub(Self::tag(self));
+        // Safety: the tag was added to a non-null pointer.
+        unsafe { NonNull::new_unchecked(ptr.cast()) }
+    }
+}
+impl<'erased_data, V, C, const BITS: usize> Deref for TaggedThinBox<'erased_data, V, C, BITS>
+where
+    V: ThinTrait<C> + ?Sized,
+{
+    type Target = Thin<'erased_data, V, C>;
+
+    fn deref(&self) -> &Self::Target {
+        // Safety: the pointer is owned by this type and points to a valid `Thin`.
+        unsafe { &*self.untagged().as_ptr() }
+    }
+}
+impl<'erased_data, V, C, const BITS: usize> DerefMut for TaggedThinBox<'erased_data, V, C, BITS>
+where
+    V: ThinTrait<C> + ?Sized,
+{
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        // Safety: the pointer is owned by this type and points to a valid `Thin`.
+        unsafe { &mut *self.untagged().as_ptr() }
+    }
+}
+impl<V, C, const BITS: usize> Clone for TaggedThinBox<'_, V, C, BITS>
+where
+    V: ThinTrait<C> + ?Sized,
+    <V as ThinTrait<C>>::VTable: VTableClone<C>,
+    C: Clone,
+{
+    fn clone(&self) -> Self {
+        // Safety: the `ManuallyDrop` wrapper ensures the allocation isn't
+        // freed twice.
+        let boxed = mem::ManuallyDrop::new(unsafe { ThinBox::from_raw_ptr(self.untagged()) });
+        Self::new(ThinBox::clone(&boxed), Self::tag(self))
+    }
+}
+impl<V, C, const BITS: usize> fmt::Debug for TaggedThinBox<'_, V, C, BITS>
+where
+    V: ThinTrait<C> + ?Sized,
+    C: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct(get_type_name!(TaggedThinBox))
+            .field("tag", &Self::tag(self))
+            .field("value", &**self)
+            .finish()
+    }
+}
+impl<V, C, const BITS: usize> Drop for TaggedThinBox<'_, V, C, BITS>
+where
+    V: ThinTrait<C> + ?Sized,
+{
+    fn drop(&mut self) {
+        // Safety: the pointer was created by `ThinBox::into_raw_ptr` and it
+        // won't be used after this point.
+        drop(unsafe { ThinBox::from_raw_ptr(self.untagged()) });
+    }
+}
@@ -276,3 +276,1929 @@ fn auto_traits_for_supertraits() {
     assert!(impls::impls!(Thin<'_, dyn SomeVTableSend, ()>: Send));
     assert!(impls::impls!(Thin<'_, dyn SomeVTableSend, alloc::rc::Rc<()>>: !Send & !Sync));
 }
+
+#[test]
+fn ref_counted() {
+    use super::*;
+    use core::cell::Cell;
+
+    define_v_table!(
+        trait Counter {
+            fn get(&self) -> u32;
+            fn increment(&mut self);
+            fn consume(self) -> u32;
+        }
+    );
+    struct Tracked<'a> {
+        value: u32,
+        drops: &'a Cell<u32>,
+    }
+    impl Counter for Tracked<'_> {
+        fn get(&self) -> u32 {
+            self.value
+        }
+        fn increment(&mut self) {
+            self.value += 1;
+        }
+        fn consume(self) -> u32 {
+            self.value
+        }
+    }
+    impl Drop for Tracked<'_> {
+        fn drop(&mut self) {
+            self.drops.set(self.drops.get() + 1);
+        }
+    }
+
+    assert_eq!(
+        mem::size_of::<ThinRc<'_, dyn Counter, u64>>(),
+        mem::size_of::<usize>()
+    );
+    assert_eq!(
+        mem::size_of::<ThinArc<'_, dyn Counter, u64>>(),
+        mem::size_of::<usize>()
+    );
+
+    let drops = Cell::new(0);
+
+    let mut rc = ThinRc::<dyn Counter, _>::new(
+        Tracked {
+            value: 1,
+            drops: &drops,
+        },
+        'c',
+    );
+    // `Counter` has methods that take `&mut self` and `self` so it is only
+    // implemented for the unique pointers:
+    assert!(impls::impls!(ThinRc<'_, dyn Counter, char>: !Counter));
+    assert!(impls::impls!(ThinArc<'_, dyn Counter, char>: !Counter));
+    ThinRc::get_mut(&mut rc).unwrap().increment();
+    let other = rc.clone();
+    assert_eq!(ThinRc::strong_count(&rc), 2);
+    assert!(ThinRc::ptr_eq(&rc, &other));
+    assert_eq!(other.get(), 2);
+    assert_eq!(*Thin::split_common(&other).1, 'c');
+    assert!(ThinRc::get_mut(&mut rc).is_none());
+    let rc = ThinRc::try_unwrap(rc).unwrap_err();
+    drop(other);
+    assert_eq!(drops.get(), 0);
+    assert_eq!(ThinRc::try_unwrap(rc).ok().unwrap().consume(), 2);
+    assert_eq!(drops.get(), 1);
+
+    let arc = ThinArc::<dyn Counter, _>::new(
+        Tracked {
+            value: 3,
+            drops: &drops,
+        },
+        String::from("common"),
+    );
+    let other = arc.clone();
+    assert_eq!(ThinArc::strong_count(&arc), 2);
+    drop(arc);
+    assert_eq!(drops.get(), 1);
+    assert_eq!(other.get(), 3);
+    drop(other);
+    assert_eq!(drops.get(), 2);
+
+    define_v_table!(
+        trait SendCounter: Send + Sync {}
+    );
+    impl SendCounter for u32 {}
+    assert!(impls::impls!(ThinArc<'_, dyn SendCounter, ()>: Send & Sync));
+    assert!(impls::impls!(ThinArc<'_, dyn SendCounter, alloc::rc::Rc<()>>: !Send & !Sync));
+    assert!(impls::impls!(ThinArc<'_, dyn Counter, ()>: !Send & !Sync));
+    assert!(impls::impls!(ThinRc<'_, dyn SendCounter, ()>: !Send & !Sync));
+
+    let arc = ThinArc::<dyn SendCounter, _>::new(2_u32, 5_u8);
+    let handle = {
+        let arc = arc.clone();
+        std::thread::spawn(move || *Thin::split_common(&arc).1)
+    };
+    assert_eq!(handle.join().unwrap(), 5);
+    assert_eq!(ThinArc::strong_count(&arc), 1);
+}
+
+#[test]
+fn weak_references() {
+    use super::*;
+    use core::cell::Cell;
+
+    define_v_table!(
+        trait Value {
+            fn get(&self) -> u32;
+        }
+    );
+    struct Tracked<'a> {
+        value: u32,
+        drops: &'a Cell<u32>,
+    }
+    impl Value for Tracked<'_> {
+        fn get(&self) -> u32 {
+            self.value
+        }
+    }
+    impl Drop for Tracked<'_> {
+        fn drop(&mut self) {
+            self.drops.set(self.drops.get() + 1);
+        }
+    }
+
+    let drops = Cell::new(0);
+    let common_drops = Cell::new(0);
+
+    let mut rc = ThinRc::<dyn Value, _>::new(
+        Tracked {
+            value: 1,
+            drops: &drops,
+        },
+        Tracked {
+            value: 0,
+            drops: &common_drops,
+        },
+    );
+    assert!(impls::impls!(ThinRc<'_, dyn Value, ()>: Value));
+    assert!(impls::impls!(ThinArc<'_, dyn Value, ()>: Value));
+    let weak = ThinRc::downgrade(&rc);
+    assert_eq!(ThinRc::weak_count(&rc), 1);
+    assert_eq!(weak.strong_count(), 1);
+    assert!(ThinRc::get_mut(&mut rc).is_none());
+    let upgraded = weak.upgrade().unwrap();
+    assert_eq!(upgraded.get(), 1);
+    assert_eq!(ThinRc::strong_count(&rc), 2);
+    drop(upgraded);
+    drop(rc);
+    assert_eq!((drops.get(), common_drops.get()), (1, 1));
+    assert!(weak.upgrade().is_none());
+    assert_eq!(weak.weak_count(), 0);
+    let other = weak.clone();
+    assert!(other.ptr_eq(&weak));
+    drop(weak);
+    drop(other);
+
+    let rc = ThinRc::<dyn Value, _>::new(
+        Tracked {
+            value: 2,
+            drops: &drops,
+        },
+        (),
+    );
+    let weak = ThinRc::downgrade(&rc);
+    let boxed = ThinRc::try_unwrap(rc).unwrap_or_else(|_| unreachable!());
+    assert!(weak.upgrade().is_none());
+    assert_eq!(boxed.get(), 2);
+    drop(weak);
+    assert_eq!(drops.get(), 1);
+    drop(boxed);
+    assert_eq!(drops.get(), 2);
+
+    let mut arc = ThinArc::<dyn Value, _>::new(
+        Tracked {
+            value: 3,
+            drops: &drops,
+        },
+        Tracked {
+            value: 0,
+            drops: &common_drops,
+        },
+    );
+    assert!(ThinArc::get_mut(&mut arc).is_some());
+    let weak = ThinArc::downgrade(&arc);
+    assert_eq!(ThinArc::weak_count(&arc), 1);
+    assert!(ThinArc::get_mut(&mut arc).is_none());
+    assert_eq!(weak.upgrade().unwrap().get(), 3);
+    drop(arc);
+    assert_eq!((drops.get(), common_drops.get()), (3, 2));
+    assert!(weak.upgrade().is_none());
+    assert_eq!(weak.strong_count(), 0);
+    drop(weak);
+
+    define_v_table!(
+        trait SendValue: Send + Sync {}
+    );
+    assert!(impls::impls!(sync::ThinWeak<'_, dyn SendValue, ()>: Send & Sync));
+    assert!(impls::impls!(sync::ThinWeak<'_, dyn Value, ()>: !Send & !Sync));
+    assert!(impls::impls!(rc::ThinWeak<'_, dyn SendValue, ()>: !Send & !Sync));
+}
+
+#[test]
+fn downcasting() {
+    use super::*;
+
+    define_v_table!(
+        trait Named {
+            fn name(&self) -> String;
+        }
+    );
+    impl Named for u32 {
+        fn name(&self) -> String {
+            format!("u32: {}", self)
+        }
+    }
+    impl Named for String {
+        fn name(&self) -> String {
+            format!("String: {}", self)
+        }
+    }
+    impl Named for &'_ str {
+        fn name(&self) -> String {
+            format!("str: {}", self)
+        }
+    }
+
+    let mut erased = ThinBox::<'static, dyn Named, u8>::new(2_u32, 7);
+    assert!(Thin::is::<u32>(&erased));
+    assert!(!Thin::is::<String>(&erased));
+    assert_eq!(Thin::downcast_ref::<u32>(&erased), Some(&2));
+    assert_eq!(Thin::downcast_ref::<u64>(&erased), None);
+    *Thin::downcast_mut::<u32>(&mut erased).unwrap() += 1;
+    assert_eq!(erased.name(), "u32: 3");
+    let erased = ThinBox::downcast::<String>(erased).unwrap_err();
+    assert_eq!(ThinBox::downcast::<u32>(erased).unwrap(), (3, 7));
+
+    let erased = ThinBox::<'static, dyn Named, ()>::new(String::from("a"), ());
+    let (erased, ()) = ThinBox::take_common(erased);
+    assert!(ThinWithoutCommon::is::<String>(&erased));
+    let erased = ThinBoxWithoutCommon::downcast::<u32>(erased).unwrap_err();
+    assert_eq!(
+        ThinBoxWithoutCommon::downcast::<String>(erased).unwrap(),
+        "a"
+    );
+
+    // Lifetimes are ignored by the type id so references with a 'static
+    // lifetime can be downcast:
+    let erased = ThinBox::<'static, dyn Named, ()>::new("static", ());
+    assert_eq!(Thin::downcast_ref::<&'static str>(&erased), Some(&"static"));
+
+    let rc = ThinRc::<'static, dyn Named, ()>::new(5_u32, ());
+    assert_eq!(Thin::downcast_ref::<u32>(&rc), Some(&5));
+}
+
+#[test]
+fn cloneable_thin_boxes() {
+    use super::*;
+    use core::cell::Cell;
+
+    define_v_table!(
+        trait Shape: ThinClone {
+            fn area(&self) -> u32;
+            fn scale(&mut self, factor: u32);
+        }
+    );
+    #[derive(Clone)]
+    struct Square<'a> {
+        side: u32,
+        clones: &'a Cell<u32>,
+    }
+    impl Shape for Square<'_> {
+        fn area(&self) -> u32 {
+            self.side * self.side
+        }
+        fn scale(&mut self, factor: u32) {
+            self.side *= factor;
+            self.clones.set(self.clones.get() + 1);
+        }
+    }
+
+    let clones = Cell::new(0);
+    let mut erased = ThinBox::<'_, dyn Shape, String>::new(
+        Square {
+            side: 2,
+            clones: &clones,
+        },
+        String::from("square"),
+    );
+    let mut cloned = erased.clone();
+    cloned.scale(3);
+    erased.scale(2);
+    assert_eq!((erased.area(), cloned.area()), (16, 36));
+    assert_eq!(clones.get(), 2);
+    assert_eq!(Thin::split_common(&cloned).1, "square");
+
+    let (erased, _) = ThinBox::take_common(erased);
+    let cloned = erased.clone();
+    assert_eq!(cloned.area(), 16);
+
+    // `ThinClone` is only detected as a supertrait:
+    define_v_table!(
+        trait GenericParameter<T: ThinClone> {
+            fn get(&self) -> u32;
+        }
+    );
+    struct NotClone;
+    impl GenericParameter<u8> for NotClone {
+        fn get(&self) -> u32 {
+            1
+        }
+    }
+    let erased = ThinBox::<'_, dyn GenericParameter<u8>, ()>::new(NotClone, ());
+    assert_eq!(erased.get(), 1);
+    assert!(impls::impls!(ThinBox<'_, dyn GenericParameter<u8>, ()>: !Clone));
+    assert!(impls::impls!(ThinBox<'_, dyn Shape, ()>: Clone));
+    assert!(impls::impls!(ThinBox<'_, dyn Shape, Cell<()>>: Clone));
+    assert!(impls::impls!(ThinBox<'_, dyn Shape, NotClone>: !Clone));
+
+    define_v_table!(
+        trait SendShape: Send + crate::ThinClone {}
+    );
+    impl SendShape for u32 {}
+    let erased = ThinBox::<'_, dyn SendShape, ()>::new(2_u32, ());
+    assert_eq!(Thin::downcast_ref::<u32>(&erased.clone()), Some(&2));
+}
+
+#[test]
+fn box_dyn_conversion() {
+    use super::*;
+
+    define_v_table!(
+        trait Counter {
+            fn get(&self) -> u32;
+            fn increment(&mut self);
+        }
+    );
+    struct Count(u32);
+    impl Counter for Count {
+        fn get(&self) -> u32 {
+            self.0
+        }
+        fn increment(&mut self) {
+            self.0 += 1;
+        }
+    }
+    struct Borrowed<'a>(&'a u32);
+    impl Counter for Borrowed<'_> {
+        fn get(&self) -> u32 {
+            *self.0
+        }
+        fn increment(&mut self) {}
+    }
+
+    let mut erased = ThinBox::<'_, dyn Counter + Send, u8>::new(Count(1), 3);
+    Thin::as_dyn_mut(&mut erased).increment();
+    let dyn_ref: &(dyn Counter + Send) = Thin::as_dyn(&erased);
+    assert_eq!(dyn_ref.get(), 2);
+
+    let (mut boxed, common) = ThinBox::into_box_dyn(erased);
+    assert_eq!(common, 3);
+    boxed.increment();
+    assert_eq!(boxed.get(), 3);
+
+    let mut erased = ThinBox::<'_, dyn Counter + Send, u8>::from_box_dyn(boxed, 4);
+    erased.increment();
+    assert_eq!(erased.get(), 4);
+    assert_eq!(Thin::as_dyn(&erased).get(), 4);
+
+    let (erased, _) = ThinBox::take_common(erased);
+    let boxed: Box<dyn Counter + Send> = ThinBoxWithoutCommon::into_box_dyn(erased);
+    assert_eq!(boxed.get(), 4);
+
+    let value = 7;
+    let erased = ThinBoxWithoutCommon::<'_, dyn Counter + '_, ()>::new(Borrowed(&value));
+    assert_eq!(ThinWithoutCommon::as_dyn(&erased).get(), 7);
+    let boxed = ThinBoxWithoutCommon::into_box_dyn(erased);
+    let erased = ThinBoxWithoutCommon::<'_, dyn Counter + '_, ()>::from_box_dyn(boxed);
+    assert_eq!(erased.get(), 7);
+
+    // The object can't be moved out of the box for methods that take `self`:
+    define_v_table!(
+        trait Consume {
+            fn consume(self) -> u32;
+        }
+    );
+    assert!(impls::impls!(BoxedDyn<dyn Counter>: Counter));
+    assert!(impls::impls!(BoxedDyn<dyn Consume>: !Consume));
+}
+
+#[test]
+fn layout_introspection() {
+    use super::*;
+
+    define_v_table!(
+        trait Inspect {}
+    );
+    impl Inspect for u64 {}
+    impl Inspect for [u16; 3] {}
+
+    let erased = ThinBox::<'_, dyn Inspect, u8>::new(5_u64, 1);
+    assert_eq!(Thin::size_of_erased(&erased), mem::size_of::<u64>());
+    assert_eq!(Thin::align_of_erased(&erased), mem::align_of::<u64>());
+    assert_eq!(Thin::erased_type_name(&erased), "u64");
+
+    #[repr(C)]
+    struct Expected(usize, u8, u64);
+    assert_eq!(
+        ThinBox::allocation_layout(&erased),
+        Layout::new::<Expected>()
+    );
+
+    let debug = format!("{:?}", erased);
+    assert!(debug.contains("u64"), "{}", debug);
+    assert!(debug.contains("common: 1"), "{}", debug);
+
+    let (erased, _) = ThinBox::take_common(ThinBox::<'_, dyn Inspect, u8>::new([1_u16; 3], 1));
+    assert_eq!(ThinWithoutCommon::size_of_erased(&erased), 6);
+    assert_eq!(ThinWithoutCommon::align_of_erased(&erased), 2);
+    assert!(format!("{:?}", erased).contains("[u16; 3]"));
+}
+
+#[test]
+fn sized_methods() {
+    use super::*;
+
+    define_v_table!(
+        trait Value {
+            fn get(&self) -> u32;
+            fn new(value: u32) -> Self
+            where
+                Self: Sized;
+            fn map<F: FnOnce(u32) -> u32>(self, f: F) -> Self
+            where
+                Self: Sized,
+            {
+                let value = f(self.get());
+                Self::new(value)
+            }
+            fn add<'a, T: Copy + Send>(&'a self, extra: &'a T) -> u32
+            where
+                Self: Sized,
+                u32: From<T>,
+            {
+                self.get() + u32::from(*extra)
+            }
+        }
+    );
+    impl Value for u32 {
+        fn get(&self) -> u32 {
+            *self
+        }
+        fn new(value: u32) -> Self {
+            value
+        }
+    }
+
+    assert_eq!(u32::new(2).map(|value| value * 3), 6);
+
+    let erased = ThinBox::<'_, dyn Value, ()>::new(4, ());
+    assert_eq!(erased.get(), 4);
+    // Default implementations still work since they only use the vtable:
+    assert_eq!(erased.add(&1_u8), 5);
+
+    let result = std::panic::catch_unwind(|| <ThinBox<'_, dyn Value, ()> as Value>::new(1));
+    assert!(result.is_err());
+}
+
+#[test]
+fn attribute_macro() {
+    use super::*;
+    use thin_trait_object_macros::thin_trait_object;
+
+    /// Doc comments and other attributes are allowed anywhere.
+    #[thin_trait_object(crate = crate)]
+    #[allow(clippy::needless_arbitrary_self_type)]
+    trait Shape<T: Copy + Into<f64>>: Send + Sync
+    where
+        T: Default + 'static,
+    {
+        /// The area of the shape.
+        #[must_use]
+        fn area(&self) -> f64;
+        fn scale(&mut self, (numerator, denominator): (T, T)) {
+            let _ = (numerator, denominator);
+        }
+        fn describe(self: &Self, mut prefix: String) -> String {
+            prefix.push_str("shape");
+            prefix
+        }
+        #[cfg(test)]
+        fn only_in_tests(&self) -> bool {
+            true
+        }
+        #[cfg(not(test))]
+        fn never_in_tests(&self) -> bool;
+        fn with_default<U>(&self, value: U) -> U
+        where
+            U: Clone,
+            Self: Sized,
+        {
+            value
+        }
+    }
+
+    struct Square(f64);
+    impl Shape<u8> for Square {
+        fn area(&self) -> f64 {
+            self.0 * self.0
+        }
+        fn scale(&mut self, (numerator, denominator): (u8, u8)) {
+            self.0 = self.0 * f64::from(numerator) / f64::from(denominator);
+        }
+    }
+
+    let mut erased = ThinBox::<'_, dyn Shape<u8>, ()>::new(Square(2.), ());
+    assert_eq!(erased.area(), 4.);
+    erased.scale((3, 2));
+    assert_eq!(erased.area(), 9.);
+    assert_eq!(erased.describe("a ".to_string()), "a shape");
+    assert!(erased.only_in_tests());
+    assert_eq!(erased.with_default(3), 3);
+
+    // The same code as `define_v_table` is generated so `ThinClone` works:
+    #[thin_trait_object(crate = crate)]
+    trait Counter: crate::ThinClone {
+        fn get(&self) -> u32;
+    }
+    impl Counter for u32 {
+        fn get(&self) -> u32 {
+            *self
+        }
+    }
+    let erased = ThinBox::<'_, dyn Counter, ()>::new(5, ());
+    assert_eq!(erased.clone().get(), 5);
+
+    #[thin_trait_object(crate = crate)]
+    #[thin_supertraits(Counter)]
+    trait Resettable: Counter {
+        fn reset(&mut self);
+    }
+    impl Resettable for u32 {
+        fn reset(&mut self) {
+            *self = 0;
+        }
+    }
+    let mut erased = ThinBox::<'_, dyn Resettable, ()>::new(5, ());
+    assert_eq!(erased.get(), 5);
+    erased.reset();
+    assert_eq!(erased.get(), 0);
+
+    #[thin_trait_object(crate = crate, extern "C")]
+    trait Doubled {
+        fn doubled(&self, offset: u32) -> u32;
+    }
+    impl Doubled for u32 {
+        fn doubled(&self, offset: u32) -> u32 {
+            *self * 2 + offset
+        }
+    }
+    let erased = ThinBox::<'_, dyn Doubled, ()>::new(5, ());
+    assert_eq!(erased.doubled(1), 11);
+
+    // Bounds with parenthesized arguments or associated type bindings:
+    #[thin_trait_object(crate = crate)]
+    trait Folder<F: Fn(u32) -> u32, I>
+    where
+        I: Iterator<Item = u8>,
+    {
+        fn fold(&self, f: F, items: I) -> u32;
+    }
+    impl<F: Fn(u32) -> u32, I: Iterator<Item = u8>> Folder<F, I> for u32 {
+        fn fold(&self, f: F, items: I) -> u32 {
+            items.map(u32::from).map(f).sum::<u32>() + *self
+        }
+    }
+    let erased = ThinBox::<'_, dyn Folder<fn(u32) -> u32, std::vec::IntoIter<u8>>, ()>::new(1, ());
+    assert_eq!(erased.fold(|value| value * 2, vec![1, 2].into_iter()), 7);
+
+    // `#[cfg]` attributes are forwarded so disabled methods aren't in the vtable:
+    #[thin_trait_object(crate = crate, extern "C")]
+    trait Conditional {
+        #[cfg(test)]
+        fn enabled(&self) -> u32;
+        #[cfg(not(test))]
+        fn disabled(&self) -> u32;
+    }
+    impl Conditional for u32 {
+        fn enabled(&self) -> u32 {
+            *self
+        }
+    }
+    let erased = ThinBox::<'_, dyn Conditional, ()>::new(5, ());
+    assert_eq!(erased.enabled(), 5);
+    let description =
+        <<dyn Conditional as ThinTrait<()>>::VTable as c_header::DescribeVTable>::DESCRIPTION;
+    assert_eq!(description.methods.len(), 1);
+    assert_eq!(description.methods[0].name, "enabled");
+
+    // The `define_v_table` macro forwards them as well:
+    define_v_table!(
+        trait ConditionalDefault {
+            #[cfg(not(test))]
+            fn disabled(&self) -> u32;
+            /// Enabled in tests.
+            #[cfg(test)]
+            #[must_use]
+            fn enabled(&self) -> u32;
+        }
+    );
+    impl ConditionalDefault for u32 {
+        fn enabled(&self) -> u32 {
+            *self + 1
+        }
+    }
+    let erased = ThinBox::<'_, dyn ConditionalDefault, ()>::new(5, ());
+    assert_eq!(erased.enabled(), 6);
+}
+
+#[test]
+fn forwarded_supertraits() {
+    use super::*;
+
+    define_v_table!(
+        trait Named {
+            fn name(&self) -> String;
+        }
+    );
+    define_v_table!(
+        trait Sound {
+            fn sound(&self) -> &'static str;
+        }
+    );
+    define_v_table!(
+        #[thin_supertraits(Named, Sound)]
+        trait Animal: Named + Sound {
+            fn legs(&self) -> u32;
+            fn into_legs(self) -> u32;
+        }
+    );
+
+    struct Dog;
+    impl Named for Dog {
+        fn name(&self) -> String {
+            "dog".to_string()
+        }
+    }
+    impl Sound for Dog {
+        fn sound(&self) -> &'static str {
+            "woof"
+        }
+    }
+    impl Animal for Dog {
+        fn legs(&self) -> u32 {
+            4
+        }
+        fn into_legs(self) -> u32 {
+            4
+        }
+    }
+
+    fn describe(thin: &Thin<'_, dyn Animal, u32>) -> String {
+        format!(
+            "{} says {} on {} legs",
+            thin.name(),
+            thin.sound(),
+            thin.legs()
+        )
+    }
+
+    let erased = ThinBox::<'_, dyn Animal, _>::new(Dog, 1);
+    assert_eq!(erased.name(), "dog");
+    assert_eq!(describe(&erased), "dog says woof on 4 legs");
+    assert_eq!(erased.into_legs(), 4);
+
+    let erased = ThinBoxWithoutCommon::<dyn Animal, ()>::new(Dog);
+    assert_eq!(erased.sound(), "woof");
+
+    let rc = ThinRc::<dyn Animal, _>::new(Dog, ());
+    assert_eq!(rc.name(), "dog");
+
+    // The supertraits' own vtables still work:
+    let erased = ThinBox::<'_, dyn Named, _>::new(Dog, ());
+    assert_eq!(erased.name(), "dog");
+}
+
+#[test]
+fn upcasting() {
+    use super::*;
+
+    define_v_table!(
+        trait Named {
+            fn name(&self) -> String;
+        }
+    );
+    define_v_table!(
+        trait Sound {
+            fn sound(&self) -> &'static str;
+        }
+    );
+    define_v_table!(
+        #[thin_supertraits(Named, Sound)]
+        trait Animal: Named + Sound {
+            fn legs(&self) -> u32;
+        }
+    );
+
+    struct Dog(std::rc::Rc<std::cell::Cell<bool>>);
+    impl Drop for Dog {
+        fn drop(&mut self) {
+            self.0.set(true);
+        }
+    }
+    impl Named for Dog {
+        fn name(&self) -> String {
+            "dog".to_string()
+        }
+    }
+    impl Sound for Dog {
+        fn sound(&self) -> &'static str {
+            "woof"
+        }
+    }
+    impl Animal for Dog {
+        fn legs(&self) -> u32 {
+            4
+        }
+    }
+
+    let dropped = std::rc::Rc::new(std::cell::Cell::new(false));
+    let erased = ThinBox::<'_, dyn Animal, _>::new(Dog(dropped.clone()), 2);
+    let named: &Thin<'_, dyn Named, u32> = Thin::upcast(&erased);
+    assert_eq!(named.name(), "dog");
+    assert_eq!(*Thin::split_common(named).1, 2);
+
+    let erased: ThinBox<'_, dyn Sound, _> = ThinBox::upcast(erased);
+    assert_eq!(erased.sound(), "woof");
+    assert_eq!(*Thin::split_common(&erased).1, 2);
+    assert!(!dropped.get());
+    drop(erased);
+    assert!(dropped.get());
+
+    let erased = ThinBoxWithoutCommon::<'_, dyn Animal, ()>::new(Dog(dropped.clone()));
+    let erased: ThinBoxWithoutCommon<'_, dyn Named, _> = ThinBoxWithoutCommon::upcast(erased);
+    assert_eq!(erased.name(), "dog");
+}
+
+#[test]
+fn forwarded_fmt_traits() {
+    use super::*;
+    use core::fmt;
+
+    define_v_table!(
+        #[thin_supertraits(fmt::Display, fmt::Debug)]
+        trait Shape: fmt::Debug {
+            fn area(&self) -> u32;
+        }
+    );
+    define_v_table!(
+        trait Plain {
+            fn get(&self) -> u32;
+        }
+    );
+
+    #[derive(Debug)]
+    struct Square(u32);
+    impl fmt::Display for Square {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "square with side {}", self.0)
+        }
+    }
+    impl Shape for Square {
+        fn area(&self) -> u32 {
+            self.0 * self.0
+        }
+    }
+    impl Plain for Square {
+        fn get(&self) -> u32 {
+            self.0
+        }
+    }
+
+    let erased = ThinBox::<'_, dyn Shape, u8>::new(Square(3), 1);
+    assert_eq!(erased.area(), 9);
+    assert_eq!(erased.to_string(), "square with side 3");
+    assert_eq!(format!("{}", &*erased), "square with side 3");
+    assert_eq!(
+        format!("{:?}", erased),
+        "ThinBox(Thin { erased: Square(3), common: 1 })"
+    );
+
+    let erased = ThinBoxWithoutCommon::<'_, dyn Shape, ()>::new(Square(2));
+    assert_eq!(
+        format!("{:?}", erased),
+        "ThinBoxWithoutCommon(ThinWithoutCommon { erased: Square(2) })"
+    );
+
+    let rc = ThinRc::<'_, dyn Shape, ()>::new(Square(4), ());
+    assert_eq!(rc.to_string(), "square with side 4");
+    let arc = ThinArc::<'_, dyn Shape, ()>::new(Square(5), ());
+    assert_eq!(arc.to_string(), "square with side 5");
+
+    // Traits that don't forward `Debug` still only show the type name:
+    let erased = ThinBox::<'_, dyn Plain, ()>::new(Square(1), ());
+    assert_eq!(
+        format!("{:?}", erased),
+        format!(
+            "ThinBox(Thin {{ erased_type: {:?}, common: () }})",
+            core::any::type_name::<Square>()
+        )
+    );
+}
+
+#[test]
+fn extern_c_vtable() {
+    use super::*;
+    use core::ffi::c_void;
+
+    define_v_table!(
+        #[repr(C)]
+        trait Counter: ThinClone {
+            fn get(&self) -> u32;
+            fn add(&mut self, amount: u32);
+            fn into_value(self) -> u32;
+        }
+    );
+    // The same trait defined with the vtable that C code sees:
+    define_v_table!(
+        #[repr(C)]
+        pub extern "C" trait ExternCounter: ThinClone {
+            fn get(&self) -> u32;
+            fn add(&mut self, amount: u32);
+            fn into_value(self) -> u32;
+        }
+    );
+
+    #[derive(Clone)]
+    struct Value(u32);
+    impl Counter for Value {
+        fn get(&self) -> u32 {
+            self.0
+        }
+        fn add(&mut self, amount: u32) {
+            self.0 += amount;
+        }
+        fn into_value(self) -> u32 {
+            self.0
+        }
+    }
+    impl ExternCounter for Value {
+        fn get(&self) -> u32 {
+            self.0
+        }
+        fn add(&mut self, amount: u32) {
+            self.0 += amount;
+        }
+        fn into_value(self) -> u32 {
+            self.0
+        }
+    }
+
+    let mut erased = ThinBox::<'_, dyn Counter, ()>::new(Value(1), ());
+    erased.add(2);
+    assert_eq!(erased.get(), 3);
+
+    let mut erased = ThinBox::<'_, dyn ExternCounter, u8>::new(Value(1), 7);
+    erased.add(2);
+    assert_eq!(erased.get(), 3);
+    let cloned = erased.clone();
+    assert_eq!(erased.into_value(), 3);
+
+    // Call the methods like C code would:
+    #[repr(C)]
+    struct CVTable {
+        get: extern "C" fn(*const c_void) -> u32,
+        add: extern "C" fn(*mut c_void, u32),
+        into_value: extern "C" fn(*mut c_void) -> u32,
+        drop_in_place: extern "C" fn(*mut c_void),
+    }
+    #[repr(C)]
+    struct CHeader {
+        vtable: *const CVTable,
+        common: u8,
+    }
+    let header = &*cloned as *const Thin<'_, dyn ExternCounter, u8> as *mut CHeader;
+    core::mem::forget(cloned);
+    unsafe {
+        let vtable = &*(*header).vtable;
+        assert_eq!((*header).common, 7);
+        (vtable.add)(header.cast(), 4);
+        assert_eq!((vtable.get)(header.cast()), 7);
+        assert_eq!((vtable.into_value)(header.cast()), 7);
+    }
+
+    let erased = ThinBox::<'_, dyn ExternCounter, u8>::new(Value(1), 0);
+    let layout = ThinBox::allocation_layout(&erased);
+    let header = &*erased as *const Thin<'_, dyn ExternCounter, u8> as *mut CHeader;
+    core::mem::forget(erased);
+    unsafe {
+        ((*(*header).vtable).drop_in_place)(header.cast());
+        alloc::alloc::dealloc(header.cast(), layout);
+    }
+}
+
+#[test]
+fn c_header_generation() {
+    use super::*;
+    use core::{ffi::c_int, mem::size_of};
+
+    define_v_table!(
+        extern "C" trait Named {
+            fn name(&self) -> *const u8;
+        }
+    );
+    define_v_table!(
+        #[thin_supertraits(Named)]
+        extern "C" trait Animal: Named + ThinClone {
+            fn legs(&self) -> c_int;
+            fn feed(&mut self, food: &u8, amount: *mut *const usize);
+            fn into_weight(self) -> f64;
+        }
+    );
+
+    let header = c_header::c_header::<dyn Animal, u32>(Some("uint32_t"));
+    assert_eq!(
+        header,
+        "\
+/* Generated by thin_trait_object for the `Animal` trait. */
+#include <stdbool.h>
+#include <stdint.h>
+
+typedef struct NamedVTable NamedVTable;
+
+/* The start of an allocation that stores a type erased `Named` object. */
+typedef struct Named {
+    const NamedVTable *vtable;
+    uint32_t common;
+} Named;
+
+typedef const uint8_t *(*Named_name)(const Named *self);
+typedef void (*Named_drop_in_place)(Named *self);
+
+struct NamedVTable {
+    Named_name name;
+    Named_drop_in_place drop_in_place;
+    /* Only used by Rust code: */
+    void (*rust_only[5])(void);
+};
+
+typedef struct AnimalVTable AnimalVTable;
+
+/* The start of an allocation that stores a type erased `Animal` object. */
+typedef struct Animal {
+    const AnimalVTable *vtable;
+    uint32_t common;
+} Animal;
+
+typedef int (*Animal_legs)(const Animal *self);
+typedef void (*Animal_feed)(Animal *self, const uint8_t *food, const uintptr_t **amount);
+typedef double (*Animal_into_weight)(Animal *self);
+typedef void (*Animal_drop_in_place)(Animal *self);
+typedef Animal *(*Animal_clone)(const Animal *self);
+
+struct AnimalVTable {
+    NamedVTable named_vtable;
+    Animal_legs legs;
+    Animal_feed feed;
+    Animal_into_weight into_weight;
+    Animal_drop_in_place drop_in_place;
+    Animal_clone clone;
+    /* Only used by Rust code: */
+    void (*rust_only[5])(void);
+};
+
+"
+    );
+
+    // The declared fields are the only fields of the vtables:
+    assert_eq!(
+        size_of::<<dyn Named as ThinTrait<u32>>::VTable>(),
+        size_of::<[usize; 7]>()
+    );
+    assert_eq!(
+        size_of::<<dyn Animal as ThinTrait<u32>>::VTable>(),
+        size_of::<[usize; 17]>()
+    );
+
+    // The description agrees with the actual layout of the vtables:
+    fn declared_fields(description: &c_header::VTableDescription) -> usize {
+        description
+            .supertraits
+            .iter()
+            .map(declared_fields)
+            .sum::<usize>()
+            + description.methods.len()
+            + 1
+            + usize::from(description.thin_clone)
+            + description.rust_only_fields
+    }
+    fn assert_declares_layout<V: ThinTrait<u32> + ?Sized>()
+    where
+        V::VTable: c_header::DescribeVTable,
+    {
+        let description = <V::VTable as c_header::DescribeVTable>::DESCRIPTION;
+        assert_eq!(
+            declared_fields(&description) * size_of::<usize>(),
+            size_of::<V::VTable>()
+        );
+    }
+    assert_declares_layout::<dyn Named>();
+    assert_declares_layout::<dyn Animal>();
+}
+
+#[test]
+fn raw_pointer_round_trip() {
+    use super::*;
+    use core::{
+        ptr::NonNull,
+        sync::atomic::{AtomicPtr, Ordering},
+    };
+    use std::{cell::Cell, rc::Rc};
+
+    define_v_table!(
+        trait Counter {
+            fn get(&self) -> u32;
+            fn add(&mut self, amount: u32);
+        }
+    );
+
+    struct Value(u32, Rc<Cell<u32>>);
+    impl Drop for Value {
+        fn drop(&mut self) {
+            self.1.set(self.1.get() + 1);
+        }
+    }
+    impl Counter for Value {
+        fn get(&self) -> u32 {
+            self.0
+        }
+        fn add(&mut self, amount: u32) {
+            self.0 += amount;
+        }
+    }
+
+    let drops = Rc::new(Cell::new(0));
+
+    // Store the erased object in an `AtomicPtr`:
+    let erased = ThinBox::<'_, dyn Counter, char>::new(Value(1, drops.clone()), 'a');
+    let slot = AtomicPtr::new(ThinBox::into_raw_ptr(erased).as_ptr());
+    let ptr = NonNull::new(slot.swap(core::ptr::null_mut(), Ordering::AcqRel)).unwrap();
+    let erased = unsafe { ThinBox::from_raw_ptr(ptr) };
+    assert_eq!(erased.get(), 1);
+    assert_eq!(*Thin::split_common(&erased).1, 'a');
+    assert_eq!(drops.get(), 0);
+    drop(erased);
+    assert_eq!(drops.get(), 1);
+
+    let leaked = ThinBox::leak(ThinBox::<'_, dyn Counter, ()>::new(
+        Value(2, drops.clone()),
+        (),
+    ));
+    leaked.add(3);
+    assert_eq!(leaked.get(), 5);
+    // Free the leaked allocation again:
+    drop(unsafe { ThinBox::from_raw_ptr(NonNull::from(leaked)) });
+    assert_eq!(drops.get(), 2);
+
+    let erased = ThinBoxWithoutCommon::<'_, dyn Counter, ()>::new(Value(3, drops.clone()));
+    let ptr = ThinBoxWithoutCommon::into_raw_ptr(erased);
+    let erased = unsafe { ThinBoxWithoutCommon::from_raw_ptr(ptr) };
+    let leaked = ThinBoxWithoutCommon::leak(erased);
+    assert_eq!(leaked.get(), 3);
+    drop(unsafe { ThinBoxWithoutCommon::from_raw_ptr(NonNull::from(leaked)) });
+    assert_eq!(drops.get(), 3);
+}
+
+#[test]
+fn atomic_thin_boxes() {
+    use super::*;
+    use core::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::Arc;
+
+    define_v_table!(
+        trait Counter {
+            fn get(&self) -> u32;
+        }
+    );
+
+    struct Value(u32, Arc<AtomicU32>);
+    impl Drop for Value {
+        fn drop(&mut self) {
+            self.1.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+    impl Counter for Value {
+        fn get(&self) -> u32 {
+            self.0
+        }
+    }
+
+    assert!(impls::impls!(AtomicThinBox<'_, dyn Counter + Send, ()>: Send & Sync));
+    assert!(impls::impls!(AtomicOptionThinBox<'_, dyn Counter + Send, ()>: Send & Sync));
+    assert!(impls::impls!(AtomicThinBox<'_, dyn Counter, ()>: !Send & !Sync));
+    assert!(impls::impls!(AtomicOptionThinBox<'_, dyn Counter, ()>: !Send & !Sync));
+    // The common data must be `Send` as well:
+    assert!(impls::impls!(AtomicThinBox<'_, dyn Counter + Send, *const ()>: !Send & !Sync));
+
+    let drops = Arc::new(AtomicU32::new(0));
+    let new =
+        |value| ThinBox::<'static, dyn Counter + Send, ()>::new(Value(value, drops.clone()), ());
+
+    let slot = Arc::new(AtomicThinBox::new(new(1)));
+    let thread = {
+        let slot = slot.clone();
+        let value = new(2);
+        std::thread::spawn(move || slot.swap(value).get())
+    };
+    assert_eq!(thread.join().unwrap(), 1);
+    assert_eq!(drops.load(Ordering::Relaxed), 1);
+
+    slot.store(new(3));
+    assert_eq!(drops.load(Ordering::Relaxed), 2);
+
+    let current = slot.load_ptr(Ordering::Acquire);
+    let previous = slot.compare_exchange(current, new(4)).unwrap();
+    assert_eq!(previous.get(), 3);
+    // The pointer is outdated now so the new value is given back:
+    let rejected = slot.compare_exchange(current, new(5)).unwrap_err();
+    assert_eq!(rejected.get(), 5);
+    drop((previous, rejected));
+    assert_eq!(drops.load(Ordering::Relaxed), 4);
+
+    let slot = Arc::try_unwrap(slot).unwrap();
+    assert_eq!(slot.into_inner().get(), 4);
+    assert_eq!(drops.load(Ordering::Relaxed), 5);
+
+    let slot = AtomicOptionThinBox::<'_, dyn Counter + Send, ()>::default();
+    assert!(slot.take().is_none());
+    let previous = slot
+        .compare_exchange(core::ptr::null(), Some(new(6)))
+        .unwrap();
+    assert!(previous.is_none());
+    assert_eq!(slot.swap(Some(new(7))).unwrap().get(), 6);
+    assert_eq!(drops.load(Ordering::Relaxed), 6);
+    assert_eq!(slot.take().unwrap().get(), 7);
+    slot.store(Some(new(8)));
+    drop(slot);
+    assert_eq!(drops.load(Ordering::Relaxed), 8);
+}
+
+#[test]
+fn tagged_thin_box() {
+    use super::*;
+    use core::mem;
+
+    define_v_table!(
+        trait Counter: ThinClone {
+            fn get(&self) -> u32;
+            fn add(&mut self, amount: u32);
+        }
+    );
+    impl Counter for u32 {
+        fn get(&self) -> u32 {
+            *self
+        }
+        fn add(&mut self, amount: u32) {
+            *self += amount;
+        }
+    }
+
+    assert_eq!(
+        mem::size_of::<TaggedThinBox<'_, dyn Counter, (), 2>>(),
+        mem::size_of::<usize>()
+    );
+    assert_eq!(
+        mem::size_of::<Option<TaggedThinBox<'_, dyn Counter, (), 2>>>(),
+        mem::size_of::<usize>()
+    );
+    assert_eq!(TaggedThinBox::<'_, dyn Counter, (), 2>::TAG_MASK, 0b11);
+
+    let mut tagged = TaggedThinBox::<'_, dyn Counter, char, 2>::new(ThinBox::new(1, 'a'), 3);
+    assert_eq!(TaggedThinBox::tag(&tagged), 3);
+    assert_eq!(tagged.get(), 1);
+    tagged.add(2);
+    TaggedThinBox::set_tag(&mut tagged, 1);
+    assert_eq!(TaggedThinBox::tag(&tagged), 1);
+    assert_eq!(tagged.get(), 3);
+    assert_eq!(*Thin::split_common(&tagged).1, 'a');
+
+    let cloned = tagged.clone();
+    assert_eq!(TaggedThinBox::tag(&cloned), 1);
+    assert_eq!(cloned.get(), 3);
+
+    let (boxed, tag) = TaggedThinBox::into_inner(tagged);
+    assert_eq!((boxed.get(), tag), (3, 1));
+}
+
+#[test]
+#[should_panic(expected = "doesn't fit")]
+fn tagged_thin_box_tag_too_big() {
+    use super::*;
+
+    define_v_table!(
+        trait Counter {
+            fn get(&self) -> u32;
+        }
+    );
+    impl Counter for u32 {
+        fn get(&self) -> u32 {
+            *self
+        }
+    }
+
+    TaggedThinBox::<'_, dyn Counter, (), 1>::new(ThinBox::new(1, ()), 2);
+}
+
+#[test]
+fn custom_allocator() {
+    use super::{
+        allocator::{AllocError, Allocator, Global},
+        *,
+    };
+    use core::{alloc::Layout, cell::Cell, ptr::NonNull};
+
+    define_v_table!(
+        trait Counter {
+            fn get(&self) -> u32;
+            fn add(&mut self, amount: u32);
+        }
+    );
+    impl Counter for u32 {
+        fn get(&self) -> u32 {
+            *self
+        }
+        fn add(&mut self, amount: u32) {
+            *self += amount;
+        }
+    }
+    #[repr(align(32))]
+    struct Aligned(u32);
+    impl Counter for Aligned {
+        fn get(&self) -> u32 {
+            self.0
+        }
+        fn add(&mut self, amount: u32) {
+            self.0 += amount;
+        }
+    }
+
+    /// Keeps track of the live allocations.
+    #[derive(Default)]
+    struct Tracking {
+        live: Cell<usize>,
+        bytes: Cell<usize>,
+    }
+    unsafe impl Allocator for Tracking {
+        fn allocate(&self, layout: Layout) -> Result<NonNull<u8>, AllocError> {
+            self.live.set(self.live.get() + 1);
+            self.bytes.set(self.bytes.get() + layout.size());
+            Global.allocate(layout)
+        }
+        unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+            self.live.set(self.live.get() - 1);
+            self.bytes.set(self.bytes.get() - layout.size());
+            Global.deallocate(ptr, layout)
+        }
+    }
+
+    let tracking = Tracking::default();
+
+    let mut erased = ThinBox::<'_, dyn Counter, u8, _>::new_in(1_u32, 2, &tracking);
+    assert_eq!(mem::size_of_val(&erased), mem::size_of::<usize>());
+    assert_eq!(tracking.live.get(), 1);
+    // The reference to the allocator is stored before the vtable pointer:
+    #[repr(C)]
+    struct Expected<'a>(&'a Tracking, usize, u8, u32);
+    assert_eq!(
+        ThinBox::allocation_layout(&erased),
+        Layout::new::<Expected<'_>>()
+    );
+    assert_eq!(
+        tracking.bytes.get(),
+        ThinBox::allocation_layout(&erased).size()
+    );
+    assert!(core::ptr::eq(*ThinBox::allocator(&erased), &tracking));
+    erased.add(2);
+    assert_eq!(erased.get(), 3);
+    assert_eq!(*Thin::split_common(&erased).1, 2);
+    drop(erased);
+    assert_eq!((tracking.live.get(), tracking.bytes.get()), (0, 0));
+
+    // The allocator is aligned together with the object:
+    let erased = ThinBox::<'_, dyn Counter, (), _>::new_in(Aligned(4), (), &tracking);
+    assert_eq!(erased.get(), 4);
+    let (erased, ()) = ThinBox::take_common(erased);
+    assert_eq!(erased.get(), 4);
+    drop(erased);
+    assert_eq!((tracking.live.get(), tracking.bytes.get()), (0, 0));
+
+    let erased = ThinBox::<'static, dyn Counter, char, _>::new_in(5_u32, 'a', &tracking);
+    let erased = ThinBox::downcast::<u8>(erased).unwrap_err();
+    assert_eq!(ThinBox::downcast::<u32>(erased).unwrap(), (5, 'a'));
+    assert_eq!(tracking.live.get(), 0);
+
+    let erased = ThinBoxWithoutCommon::<'_, dyn Counter, (), _>::new_in(6_u32, &tracking);
+    let erased = ThinBoxWithoutCommon::put_common(erased, ());
+    assert_eq!(erased.get(), 6);
+    drop(erased);
+    assert_eq!(tracking.live.get(), 0);
+}
+
+#[test]
+fn arena() {
+    use super::*;
+    use std::cell::{Cell, RefCell};
+
+    define_v_table!(
+        trait Counter {
+            fn get(&self) -> u32;
+            fn add(&mut self, amount: u32);
+        }
+    );
+    define_v_table!(
+        trait Named {
+            fn name(&self) -> String;
+        }
+    );
+
+    struct Logged<'a>(u32, &'a RefCell<Vec<u32>>);
+    impl Drop for Logged<'_> {
+        fn drop(&mut self) {
+            self.1.borrow_mut().push(self.0);
+        }
+    }
+    impl Counter for Logged<'_> {
+        fn get(&self) -> u32 {
+            self.0
+        }
+        fn add(&mut self, amount: u32) {
+            self.0 += amount;
+        }
+    }
+    #[repr(align(64))]
+    struct Aligned(u32);
+    impl Counter for Aligned {
+        fn get(&self) -> u32 {
+            self.0
+        }
+        fn add(&mut self, amount: u32) {
+            self.0 += amount;
+        }
+    }
+    impl Named for String {
+        fn name(&self) -> String {
+            self.clone()
+        }
+    }
+
+    let dropped = RefCell::new(Vec::new());
+    let common_drops = Cell::new(0);
+    struct Common<'a>(&'a Cell<u32>);
+    impl Drop for Common<'_> {
+        fn drop(&mut self) {
+            self.0.set(self.0.get() + 1);
+        }
+    }
+
+    {
+        let arena = ThinArena::with_capacity(64);
+        let first = arena.alloc::<dyn Counter, _, _>(Logged(1, &dropped), Common(&common_drops));
+        let aligned = arena.alloc::<dyn Counter, _, _>(Aligned(2), ());
+        assert_eq!(aligned as *const _ as usize % 64, 0);
+        let named = arena.alloc::<dyn Named, _, _>(String::from("named"), 'a');
+        // Fill a few chunks:
+        let many = (0..100)
+            .map(|value| arena.alloc::<dyn Counter, _, _>(Logged(value + 10, &dropped), ()))
+            .collect::<Vec<_>>();
+        assert!(arena.allocated_bytes() > 64);
+
+        first.add(2);
+        aligned.add(1);
+        assert_eq!((first.get(), aligned.get()), (3, 3));
+        assert_eq!(
+            (named.name(), *Thin::split_common(named).1),
+            ("named".to_string(), 'a')
+        );
+        assert!(many
+            .iter()
+            .enumerate()
+            .all(|(i, erased)| erased.get() == i as u32 + 10));
+        assert!(dropped.borrow().is_empty());
+    }
+    // The objects are dropped in reverse allocation order:
+    let mut expected = (10..110).rev().collect::<Vec<_>>();
+    expected.push(3);
+    assert_eq!(*dropped.borrow(), expected);
+    assert_eq!(common_drops.get(), 1);
+}
+
+#[test]
+fn thin_vec() {
+    use super::*;
+    use std::cell::RefCell;
+
+    define_v_table!(
+        trait Counter {
+            fn get(&self) -> u32;
+            fn add(&mut self, amount: u32);
+        }
+    );
+
+    struct Logged<'a>(u32, &'a RefCell<Vec<u32>>);
+    impl Drop for Logged<'_> {
+        fn drop(&mut self) {
+            self.1.borrow_mut().push(self.0);
+        }
+    }
+    impl Counter for Logged<'_> {
+        fn get(&self) -> u32 {
+            self.0
+        }
+        fn add(&mut self, amount: u32) {
+            self.0 += amount;
+        }
+    }
+    #[repr(align(64))]
+    struct Aligned(u32);
+    impl Counter for Aligned {
+        fn get(&self) -> u32 {
+            self.0
+        }
+        fn add(&mut self, amount: u32) {
+            self.0 += amount;
+        }
+    }
+
+    impl Counter for u8 {
+        fn get(&self) -> u32 {
+            u32::from(*self)
+        }
+        fn add(&mut self, amount: u32) {
+            *self += amount as u8;
+        }
+    }
+
+    let dropped = RefCell::new(Vec::new());
+    let mut vec = ThinVec::<dyn Counter, u8>::new();
+    assert!(vec.is_empty());
+    vec.push(Logged(1, &dropped), 1);
+    vec.push(5_u8, 2);
+    // Pushing a more aligned object reallocates the buffer:
+    vec.push(Aligned(3), 3);
+    for value in 0..20 {
+        vec.push(Logged(value + 10, &dropped), value as u8 + 10);
+    }
+    assert_eq!(vec.len(), 23);
+    assert_eq!(vec.get(2).unwrap() as *const _ as usize % 64, 0);
+
+    for erased in &mut vec {
+        erased.add(1);
+    }
+    assert_eq!(
+        vec.iter()
+            .take(3)
+            .map(|erased| erased.get())
+            .collect::<Vec<_>>(),
+        [2, 6, 4]
+    );
+    assert!(vec
+        .common()
+        .eq(vec.iter().map(|erased| Thin::split_common(erased).1)));
+    assert_eq!(vec.common().next_back(), Some(&29));
+
+    let last = vec.pop().unwrap();
+    assert_eq!((last.get(), *Thin::split_common(&last).1), (30, 29));
+    assert!(dropped.borrow().is_empty());
+    drop(last);
+    assert_eq!(*dropped.borrow(), [30]);
+
+    // The buffer can be reused after popping:
+    vec.push(Logged(100, &dropped), 100);
+    assert_eq!(vec.get(vec.len() - 1).unwrap().get(), 100);
+
+    let capacity = vec.capacity_bytes();
+    vec.clear();
+    assert!(vec.is_empty());
+    assert_eq!(vec.capacity_bytes(), capacity);
+    let mut expected = vec![30, 2];
+    expected.extend(11..30);
+    expected.push(100);
+    assert_eq!(*dropped.borrow(), expected);
+
+    vec.push(Logged(200, &dropped), 0);
+    drop(vec);
+    assert_eq!(dropped.borrow().last(), Some(&200));
+}
+
+#[test]
+fn thin_slice() {
+    use super::*;
+    use std::cell::RefCell;
+
+    define_v_table!(
+        trait Counter {
+            fn get(&self) -> u32;
+            fn add(&mut self, amount: u32);
+        }
+    );
+
+    struct Logged<'a>(u32, &'a RefCell<Vec<u32>>);
+    impl Drop for Logged<'_> {
+        fn drop(&mut self) {
+            self.1.borrow_mut().push(self.0);
+        }
+    }
+    impl Counter for Logged<'_> {
+        fn get(&self) -> u32 {
+            self.0
+        }
+        fn add(&mut self, amount: u32) {
+            self.0 += amount;
+        }
+    }
+    impl Counter for u8 {
+        fn get(&self) -> u32 {
+            u32::from(*self)
+        }
+        fn add(&mut self, amount: u32) {
+            *self += amount as u8;
+        }
+    }
+
+    let dropped = RefCell::new(Vec::new());
+    {
+        let items = (0..5).map(|value| Logged(value, &dropped)).collect();
+        let mut slice = ThinSlice::<dyn Counter + '_, String>::new(items, String::from("logged"));
+        assert_eq!(slice.len(), 5);
+        assert_eq!(slice.common(), "logged");
+        slice.common_mut().push('!');
+
+        slice.for_each_mut(|erased| erased.add(10));
+        assert_eq!(slice.get_mut(2).map(|erased| erased.get()), Some(12));
+        assert!(slice.get_mut(5).is_none());
+        slice[0].add(100);
+        assert_eq!(slice[0].get(), 110);
+        assert_eq!(slice.get(1).unwrap().get(), 11);
+        let totals: Vec<u32> = slice.iter().map(|element| element.get()).collect();
+        assert_eq!(totals, [110, 11, 12, 13, 14]);
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| slice[5].get()));
+        assert!(result.is_err());
+
+        assert!(slice
+            .iter()
+            .all(|element| element.erased_type_name().contains("Logged")));
+        assert_eq!(
+            slice.get(4).unwrap().size_of_erased(),
+            mem::size_of::<Logged<'_>>()
+        );
+        assert!(slice.get(5).is_none());
+        assert!(dropped.borrow().is_empty());
+        assert_eq!(
+            format!("{:?}", slice),
+            format!(
+                "ThinSlice {{ erased_type: {:?}, len: 5, common: \"logged!\" }}",
+                slice.erased_type_name()
+            )
+        );
+    }
+    assert_eq!(*dropped.borrow(), [110, 11, 12, 13, 14]);
+
+    // Objects with a smaller alignment than the common data:
+    let mut bytes = ThinSlice::<dyn Counter, u64>::new(vec![1_u8, 2, 3], 7);
+    bytes.for_each_mut(|erased| erased.add(1));
+    assert_eq!(bytes.downcast_slice::<u8>(), Some(&[2, 3, 4][..]));
+    assert_eq!(bytes.downcast_slice::<u16>(), None);
+    bytes.downcast_slice_mut::<u8>().unwrap()[0] = 9;
+    assert_eq!(bytes.get(0).unwrap().downcast_ref::<u8>(), Some(&9));
+    assert_eq!(*bytes.common(), 7);
+
+    let empty = ThinSlice::<dyn Counter, ()>::new(Vec::<u8>::new(), ());
+    assert!(empty.is_empty());
+    assert_eq!(empty.iter().count(), 0);
+}
+
+#[test]
+fn thin_inline() {
+    use super::*;
+    use std::cell::Cell;
+
+    define_v_table!(
+        trait Counter {
+            fn get(&self) -> u32;
+            fn add(&mut self, amount: u32);
+        }
+    );
+
+    struct Logged<'a>(u32, &'a Cell<u32>);
+    impl Drop for Logged<'_> {
+        fn drop(&mut self) {
+            self.1.set(self.1.get() + 1);
+        }
+    }
+    impl Counter for Logged<'_> {
+        fn get(&self) -> u32 {
+            self.0
+        }
+        fn add(&mut self, amount: u32) {
+            self.0 += amount;
+        }
+    }
+    impl Counter for [u32; 16] {
+        fn get(&self) -> u32 {
+            self.iter().sum()
+        }
+        fn add(&mut self, amount: u32) {
+            self[0] += amount;
+        }
+    }
+    #[repr(align(64))]
+    struct Aligned(u32);
+    impl Counter for Aligned {
+        fn get(&self) -> u32 {
+            self.0
+        }
+        fn add(&mut self, amount: u32) {
+            self.0 += amount;
+        }
+    }
+
+    let drops = Cell::new(0);
+    {
+        let mut inline = ThinInline::<dyn Counter, u8, 32>::new(Logged(1, &drops), 7);
+        inline.add(2);
+        assert_eq!((inline.get(), *Thin::split_common(&inline).1), (3, 7));
+        // Moving the inline storage moves the object with it:
+        let mut moved = [inline];
+        moved[0].add(1);
+        assert_eq!(moved[0].get(), 4);
+        assert_eq!(drops.get(), 0);
+    }
+    assert_eq!(drops.get(), 1);
+
+    let inline = ThinInline::<dyn Counter, (), 32>::new(Logged(5, &drops), ());
+    let boxed = ThinInline::into_box(inline);
+    assert_eq!((boxed.get(), drops.get()), (5, 1));
+    drop(boxed);
+    assert_eq!(drops.get(), 2);
+
+    // Objects that are too large or too aligned are given back:
+    let (array, ()) = ThinInline::<dyn Counter, (), 32>::try_new([1; 16], ()).unwrap_err();
+    assert_eq!(array.get(), 16);
+    assert!(ThinInline::<dyn Counter, (), 256>::try_new(Aligned(1), ()).is_err());
+    assert_eq!(
+        ThinInline::<dyn Counter, (), 128>::try_new([1; 16], ())
+            .unwrap()
+            .get(),
+        16
+    );
+}
+
+#[test]
+fn thin_small_box() {
+    use super::*;
+    use std::cell::Cell;
+
+    define_v_table!(
+        trait Counter {
+            fn get(&self) -> u32;
+            fn add(&mut self, amount: u32);
+        }
+    );
+
+    thread_local! {
+        static DROPS: Cell<u32> = const { Cell::new(0) };
+    }
+    struct Unit;
+    impl Drop for Unit {
+        fn drop(&mut self) {
+            DROPS.with(|drops| drops.set(drops.get() + 1));
+        }
+    }
+    impl Counter for Unit {
+        fn get(&self) -> u32 {
+            42
+        }
+        fn add(&mut self, _amount: u32) {}
+    }
+    impl Counter for u32 {
+        fn get(&self) -> u32 {
+            *self
+        }
+        fn add(&mut self, amount: u32) {
+            *self += amount;
+        }
+    }
+
+    assert_eq!(
+        mem::size_of::<ThinSmallBox<'_, dyn Counter, ()>>(),
+        mem::size_of::<usize>()
+    );
+    assert_eq!(
+        mem::size_of::<Option<ThinSmallBox<'_, dyn Counter, ()>>>(),
+        mem::size_of::<usize>()
+    );
+
+    let mut unit = ThinSmallBox::<dyn Counter, ()>::new(Unit, ());
+    assert!(ThinSmallBox::is_inline(&unit));
+    unit.add(1);
+    assert_eq!(unit.get(), 42);
+    assert!(Thin::erased_type_name(&unit).ends_with("Unit"));
+    // The object moves with the box:
+    let moved = [unit];
+    assert_eq!(moved[0].get(), 42);
+    assert_eq!(DROPS.with(Cell::get), 0);
+    drop(moved);
+    assert_eq!(DROPS.with(Cell::get), 1);
+
+    let mut number = ThinSmallBox::<dyn Counter, ()>::new(5_u32, ());
+    assert!(!ThinSmallBox::is_inline(&number));
+    number.add(2);
+    assert_eq!(number.get(), 7);
+    // Non zero sized common data is stored in a heap allocation:
+    let common = ThinSmallBox::<dyn Counter, u8>::new(Unit, 3);
+    assert!(!ThinSmallBox::is_inline(&common));
+    assert_eq!(*Thin::split_common(&common).1, 3);
+    drop(common);
+    assert_eq!(DROPS.with(Cell::get), 2);
+
+    let boxed = ThinSmallBox::into_box(ThinSmallBox::<dyn Counter, ()>::new(Unit, ()));
+    assert_eq!((boxed.get(), DROPS.with(Cell::get)), (42, 2));
+    drop(boxed);
+    assert_eq!(DROPS.with(Cell::get), 3);
+    let boxed = ThinSmallBox::into_box(number);
+    assert_eq!(boxed.get(), 7);
+    let small = ThinSmallBox::from(boxed);
+    assert_eq!(small.get(), 7);
+}
+
+#[test]
+fn thin_box_new_uninit() {
+    use super::{allocator::Global, *};
+    use std::cell::Cell;
+
+    define_v_table!(
+        trait Sum {
+            fn sum(&self) -> u64;
+        }
+    );
+    struct Large([u64; 1 << 16]);
+    impl Sum for Large {
+        fn sum(&self) -> u64 {
+            self.0.iter().sum()
+        }
+    }
+    impl Sum for u64 {
+        fn sum(&self) -> u64 {
+            *self
+        }
+    }
+    thread_local!(static DROPS: Cell<usize> = const { Cell::new(0) });
+    struct Common;
+    impl Drop for Common {
+        fn drop(&mut self) {
+            DROPS.with(|drops| drops.set(drops.get() + 1));
+        }
+    }
+
+    // Large objects are written directly into the heap allocation:
+    let mut uninit = ThinBox::<dyn Sum, Common>::new_uninit::<Large>(Common);
+    let large = uninit.as_mut_ptr();
+    for i in 0..1 << 16 {
+        unsafe { core::ptr::addr_of_mut!((*large).0[i]).write(2) };
+    }
+    let boxed = unsafe { ThinBoxUninit::assume_init(uninit) };
+    assert_eq!(boxed.sum(), 2 << 16);
+    assert_eq!(
+        ThinBox::allocation_layout(&boxed).size(),
+        mem::size_of::<usize>() + mem::size_of::<Large>()
+    );
+    drop(boxed);
+    assert_eq!(DROPS.with(Cell::get), 1);
+
+    let boxed = ThinBox::<dyn Sum, Common>::new_with(|| Large([1; 1 << 16]), Common);
+    assert_eq!(boxed.sum(), 1 << 16);
+    drop(boxed);
+    assert_eq!(DROPS.with(Cell::get), 2);
+
+    // Dropping an uninitialized handle only drops the common data:
+    let mut uninit = ThinBox::<dyn Sum, Common, _>::new_uninit_in::<u64>(Common, Global);
+    let _: &mut Common = ThinBoxUninit::common_mut(&mut uninit);
+    drop(uninit);
+    assert_eq!(DROPS.with(Cell::get), 3);
+
+    // A panic in the closure doesn't leak the common data:
+    let result = std::panic::catch_unwind(|| {
+        ThinBox::<dyn Sum, Common>::new_with::<u64, _>(|| panic!("no object"), Common)
+    });
+    assert!(result.is_err());
+    assert_eq!(DROPS.with(Cell::get), 4);
+}
+
+#[test]
+fn fallible_allocation() {
+    use super::{
+        allocator::{AllocError, Allocator, Global},
+        *,
+    };
+    use core::{alloc::Layout, cell::Cell, ptr::NonNull};
+
+    define_v_table!(
+        trait Counter {
+            fn get(&self) -> u32;
+        }
+    );
+    impl Counter for u32 {
+        fn get(&self) -> u32 {
+            *self
+        }
+    }
+
+    /// Fails once the limit on the number of allocations is reached.
+    struct Limited(Cell<usize>);
+    unsafe impl Allocator for Limited {
+        fn allocate(&self, layout: Layout) -> Result<NonNull<u8>, AllocError> {
+            if self.0.get() == 0 {
+                return Err(AllocError);
+            }
+            self.0.set(self.0.get() - 1);
+            Global.allocate(layout)
+        }
+        unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+            Global.deallocate(ptr, layout)
+        }
+    }
+
+    let limited = Limited(Cell::new(2));
+    let erased = ThinBox::<'_, dyn Counter, char, _>::try_new_in(1_u32, 'a', &limited).unwrap();
+    assert_eq!(erased.get(), 1);
+    let erased =
+        ThinBoxWithoutCommon::<'_, dyn Counter, (), _>::try_new_in(2_u32, &limited).unwrap();
+    assert_eq!(erased.get(), 2);
+    assert_eq!(
+        ThinBox::<'_, dyn Counter, char, _>::try_new_in(3_u32, 'b', &limited).unwrap_err(),
+        (3, 'b')
+    );
+    assert_eq!(
+        ThinBoxWithoutCommon::<'_, dyn Counter, (), _>::try_new_in(4_u32, &limited).unwrap_err(),
+        4
+    );
+    let raw = RawThinBox::<'_, <dyn Counter as ThinTrait<char>>::VTable, _, _, _, _>::try_new_in(
+        5_u32, 'c', &limited,
+    );
+    assert_eq!(raw.unwrap_err(), (5, 'c'));
+
+    let erased = ThinBox::<dyn Counter, ()>::try_new(6_u32, ()).unwrap();
+    assert_eq!(erased.get(), 6);
+    let erased = ThinBoxWithoutCommon::<dyn Counter, ()>::try_new(7_u32).unwrap();
+    assert_eq!(erased.get(), 7);
+}
+
+#[test]
+fn const_owned_thin() {
+    use super::*;
+
+    define_v_table!(
+        trait Named {
+            fn name(&self) -> &'static str;
+        }
+    );
+    define_v_table!(
+        #[thin_supertraits(Named)]
+        trait Handler: Named {
+            fn handle(&self, request: u32) -> u32;
+        }
+    );
+    struct Add(u32);
+    impl Named for Add {
+        fn name(&self) -> &'static str {
+            "add"
+        }
+    }
+    impl Handler for Add {
+        fn handle(&self, request: u32) -> u32 {
+            request + self.0
+        }
+    }
+    struct Square;
+    impl Named for Square {
+        fn name(&self) -> &'static str {
+            "square"
+        }
+    }
+    impl Handler for Square {
+        fn handle(&self, request: u32) -> u32 {
+            request * request
+        }
+    }
+
+    static ADD: OwnedThin<dyn Handler + Sync, u8, Add> = OwnedThin::new(Add(3), 1);
+    static SQUARE: OwnedThin<dyn Handler + Sync, u8, Square> = OwnedThin::new(Square, 2);
+    static HANDLERS: [&Thin<'static, dyn Handler + Sync, u8>; 2] =
+        [OwnedThin::as_thin(&ADD), OwnedThin::as_thin(&SQUARE)];
+
+    let results: Vec<_> = HANDLERS
+        .iter()
+        .map(|handler| {
+            (
+                handler.name(),
+                handler.handle(4),
+                *Thin::split_common(handler).1,
+            )
+        })
+        .collect();
+    assert_eq!(results, [("add", 7, 1), ("square", 16, 2)]);
+    assert!(Thin::erased_type_name(HANDLERS[1]).ends_with("Square"));
+
+    // A stack allocated wrapper can be created in a const context as well:
+    const fn new_add(amount: u32) -> OwnedThin<dyn Handler, (), Add> {
+        OwnedThin::new(Add(amount), ())
+    }
+    let add = new_add(5);
+    assert_eq!(OwnedThin::as_thin(&add).handle(1), 6);
+    assert_eq!(add.into_inner().0 .0, 5);
+}
@@ -0,0 +1,463 @@
+//! A growable buffer that stores differently typed objects next to each other.
+//!
+//! Every entry in a [`ThinVec`] is a [`RawThin`] value (the vtable pointer, the
+//! common data and the type erased object) that is aligned inside a single
+//! heap allocation. The offsets of the entries are stored separately so the
+//! common data can be accessed without any virtual calls.
+
+use super::{auto_traits, GetThinTraitVTable, RawThin, Thin, ThinBox, ThinTrait};
+
+use alloc::vec::Vec;
+use core::{
+    alloc::Layout,
+    cmp, fmt,
+    iter::FusedIterator,
+    marker::PhantomData,
+    mem,
+    ptr::{self, NonNull},
+    slice,
+};
+
+/// A pointer to the entry at an offset in the buffer.
+fn entry_ptr<'erased_data, V, C>(buf: NonNull<u8>, offset: usize) -> *mut Thin<'erased_data, V, C>
+where
+    V: ThinTrait<C> + ?Sized,
+{
+    buf.as_ptr().wrapping_add(offset).cast()
+}
+
+/// A growable buffer of type erased objects that implement the trait `V` and
+/// that all have the common data `C`.
+///
+/// Objects are moved into the buffer with [`ThinVec::push`] and they are
+/// dropped via their vtables. The buffer is aligned to the largest alignment
+/// of the objects it has stored, so it is reallocated when an object with a
+/// larger alignment is pushed.
+///
+/// # Examples
+///
+/// ```
+/// use thin_trait_object::{define_v_table, ThinVec};
+///
+/// define_v_table!(
+///     trait Area {
+///         fn area(&self) -> f64;
+///     }
+/// );
+/// struct Square(f64);
+/// impl Area for Square {
+///     fn area(&self) -> f64 { self.0 * self.0 }
+/// }
+/// struct Circle(f64);
+/// impl Area for Circle {
+///     fn area(&self) -> f64 { 3.0 * self.0 * self.0 }
+/// }
+///
+/// let mut shapes = ThinVec::<dyn Area, &str>::new();
+/// shapes.push(Square(2.0), "square");
+/// shapes.push(Circle(1.0), "circle");
+///
+/// assert_eq!(shapes.iter().map(|shape| shape.area()).sum::<f64>(), 7.0);
+/// assert_eq!(shapes.common().collect::<Vec<_>>(), [&"square", &"circle"]);
+/// ```
+pub struct ThinVec<'erased_data, V, C>
+where
+    V: ThinTrait<C> + ?Sized,
+{
+    /// The start of the buffer, dangling if nothing has been allocated.
+    buf: NonNull<u8>,
+    /// The layout of the buffer's allocation, zero sized if nothing has been
+    /// allocated.
+    buf_layout: Layout,
+    /// The number of bytes at the start of the buffer that are used.
+    used: usize,
+    /// The offset of every entry from the start of the buffer.
+    offsets: Vec<usize>,
+    _owned: PhantomData<ThinBox<'erased_data, V, C>>,
+}
+// Safety: the buffer owns the type erased objects.
+unsafe impl<'erased_data, V, C> Send for ThinVec<'erased_data, V, C>
+where
+    V: ThinTrait<C> + ?Sized,
+    Thin<'erased_data, V, C>: Send,
+{
+}
+unsafe impl<'erased_data, V, C> Sync for ThinVec<'erased_data, V, C>
+where
+    V: ThinTrait<C> + ?Sized,
+    Thin<'erased_data, V, C>: Sync,
+{
+}
+impl<'erased_data, V, C> ThinVec<'erased_data, V, C>
+where
+    V: ThinTrait<C> + ?Sized,
+{
+    /// Create an empty [`ThinVec`]. No memory is allocated until an object is
+    /// pushed.
+    pub fn new() -> Self {
+        Self::with_capacity(0)
+    }
+    /// Create an empty [`ThinVec`] whose buffer has room for `bytes` bytes.
+    pub fn with_capacity(bytes: usize) -> Self {
+        let mut this = Self {
+            buf: NonNull::dangling(),
+            buf_layout: Layout::new::<()>(),
+            used: 0,
+            offsets: Vec::new(),
+            _owned: PhantomData,
+        };
+        if bytes != 0 {
+            this.reallocate(
+                Layout::from_size_align(bytes, mem::align_of::<Thin<'erased_data, V, C>>())
+                    .expect("the capacity should be valid"),
+            );
+        }
+        this
+    }
+
+    /// The number of objects in the buffer.
+    pub fn len(&self) -> usize {
+        self.offsets.len()
+    }
+    /// Returns `true` if the buffer doesn't contain any objects.
+    pub fn is_empty(&self) -> bool {
+        self.offsets.is_empty()
+    }
+    /// The number of bytes that the buffer can store without reallocating.
+    pub fn capacity_bytes(&self) -> usize {
+        self.buf_layout.size()
+    }
+
+    /// Move an object and its common data to the end of the buffer.
+    pub fn push<T>(&mut self, x: T, common: C)
+    where
+        T: auto_traits::HasAutoTraits<V::AutoTraitConfig> + 'erased_data,
+        V::VTable: GetThinTraitVTable<T>,
+    {
+        let raw = RawThin::<'erased_data, V::VTable, C, _, T> {
+            vtable: <V::VTable as GetThinTraitVTable<T>>::get_vtable().vtable,
+            common,
+            // Safety: the `HasAutoTraits` bound ensures that the object
+            // implements the auto traits of the marker type.
+            _not_send_or_sync: PhantomData::<super::ThinTraitAutoTraitsMarker<V, C>>,
+            _lifetime: PhantomData,
+            _object: x,
+        };
+        let layout = Layout::for_value(&raw);
+        let offset = self
+            .used
+            .checked_add(layout.align() - 1)
+            .expect("the buffer of a `ThinVec` is too large")
+            & !(layout.align() - 1);
+        let end = offset
+            .checked_add(layout.size())
+            .expect("the buffer of a `ThinVec` is too large");
+        if end > self.buf_layout.size() || layout.align() > self.buf_layout.align() {
+            self.grow(end, layout.align());
+        }
+        self.offsets.reserve(1);
+
+        // Safety: the buffer was just grown so the entry fits inside it and the
+        // buffer's alignment ensures that the offset is aligned.
+        unsafe {
+            ptr::write(
+                self.buf.as_ptr().add(offset) as *mut RawThin<'erased_data, V::VTable, C, _, T>,
+                raw,
+            )
+        };
+        self.offsets.push(offset);
+        self.used = end;
+    }
+    /// Move the last object out of the buffer into a new [`ThinBox`].
+    pub fn pop(&mut self) -> Option<ThinBox<'erased_data, V, C>> {
+        let offset = self.offsets.pop()?;
+        // Safety: the entry was removed from the offsets so it won't be used
+        // or dropped again.
+        let boxed = unsafe { ThinBox::move_from_thin(entry_ptr(self.buf, offset)) };
+        self.used = offset;
+        Some(boxed)
+    }
+    /// Drop all objects in the buffer but keep its allocation.
+    pub fn clear(&mut self) {
+        self.used = 0;
+        let buf = self.buf;
+        // Objects that haven't been dropped are leaked if a destructor panics
+        // since the offsets are removed before the objects are dropped.
+        for offset in self.offsets.drain(..) {
+            // Safety: the entries are valid and they were removed from the
+            // buffer so they won't be used again.
+            unsafe { Thin::drop_in_place(entry_ptr::<V, C>(buf, offset)) };
+        }
+    }
+
+    /// Get a reference to an object in the buffer.
+    pub fn get(&self, index: usize) -> Option<&Thin<'erased_data, V, C>> {
+        let offset = *self.offsets.get(index)?;
+        // Safety: the offset points to a valid entry.
+        Some(unsafe { &*entry_ptr(self.buf, offset) })
+    }
+    /// Get a mutable reference to an object in the buffer.
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut Thin<'erased_data, V, C>> {
+        let offset = *self.offsets.get(index)?;
+        // Safety: the offset points to a valid entry.
+        Some(unsafe { &mut *entry_ptr(self.buf, offset) })
+    }
+    /// Iterate over the objects in the buffer.
+    pub fn iter(&self) -> Iter<'_, 'erased_data, V, C> {
+        Iter {
+            buf: self.buf,
+            offsets: self.offsets.iter(),
+            _borrowed: PhantomData,
+        }
+    }
+    /// Iterate mutably over the objects in the buffer.
+    pub fn iter_mut(&mut self) -> IterMut<'_, 'erased_data, V, C> {
+        IterMut {
+            buf: self.buf,
+            offsets: self.offsets.iter(),
+            _borrowed: PhantomData,
+        }
+    }
+    /// Iterate over the common data of the objects in the buffer. This doesn't
+    /// use the vtables so it is as cheap as iterating over a slice of offsets.
+    pub fn common(&self) -> impl DoubleEndedIterator<Item = &C> + ExactSizeIterator + '_ {
+        self.iter().map(|thin| Thin::split_common(thin).1)
+    }
+
+    /// Grow the buffer so that it has room for at least `size` bytes and is
+    /// aligned to at least `align`.
+    #[cold]
+    fn grow(&mut self, size: usize, align: usize) {
+        let size = cmp::max(size, self.buf_layout.size().saturating_mul(2));
+        let size = cmp::max(size, 64);
+        let align = cmp::max(align, self.buf_layout.align());
+        self.reallocate(
+            Layout::from_size_align(size, align).expect("the buffer of a `ThinVec` is too large"),
+        );
+    }
+    /// Move the used part of the buffer into a new allocation.
+    fn reallocate(&mut self, layout: Layout) {
+        // Safety: the layout is never zero sized.
+        let new = unsafe { alloc::alloc::alloc(layout) };
+        let new = match NonNull::new(new) {
+            Some(new) => new,
+            None => alloc::alloc::handle_alloc_error(layout),
+        };
+        // Safety: the new allocation is at least as large as the used bytes and
+        // at least as aligned as the old buffer, so the offsets stay aligned.
+        // Moving objects by copying their bytes is fine in Rust.
+        unsafe {
+            ptr::copy_nonoverlapping(self.buf.as_ptr(), new.as_ptr(), self.used);
+            self.dealloc();
+        }
+        self.buf = new;
+        self.buf_layout = layout;
+    }
+    /// Free the buffer without dropping the objects in it.
+    ///
+    /// # Safety
+    ///
+    /// The buffer must not be used afterwards unless it is replaced.
+    unsafe fn dealloc(&mut self) {
+        if self.buf_layout.size() != 0 {
+            alloc::alloc::dealloc(self.buf.as_ptr(), self.buf_layout);
+        }
+    }
+}
+impl<V, C> Default for ThinVec<'_, V, C>
+where
+    V: ThinTrait<C> + ?Sized,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl<V, C> fmt::Debug for ThinVec<'_, V, C>
+where
+    V: ThinTrait<C> + ?Sized,
+    C: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+impl<V, C> Drop for ThinVec<'_, V, C>
+where
+    V: ThinTrait<C> + ?Sized,
+{
+    fn drop(&mut self) {
+        self.clear();
+        // Safety: the buffer is never used again.
+        unsafe { self.dealloc() };
+    }
+}
+impl<'a, 'erased_data, V, C> IntoIterator for &'a ThinVec<'erased_data, V, C>
+where
+    V: ThinTrait<C> + ?Sized,
+{
+    type Item = &'a Thin<'erased_data, V, C>;
+    type IntoIter = Iter<'a, 'erased_data, V, C>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+impl<'a, 'erased_data, V, C> IntoIterator for &'a mut ThinVec<'erased_data, V, C>
+where
+    V: ThinTrait<C> + ?Sized,
+{
+    type Item = &'a mut Thin<'erased_data, V, C>;
+    type IntoIter = IterMut<'a, 'erased_data, V, C>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+/// An iterator over the objects in a [`ThinVec`], created by [`ThinVec::iter`].
+pub struct Iter<'a, 'erased_data, V, C>
+where
+    V: ThinTrait<C> + ?Sized,
+{
+    buf: NonNull<u8>,
+    offsets: slice::Iter<'a, usize>,
+    _borrowed: PhantomData<&'a Thin<'erased_data, V, C>>,
+}
+// Safety: the iterator only hands out references to the objects.
+unsafe impl<'erased_data, V, C> Send for Iter<'_, 'erased_data, V, C>
+where
+    V: ThinTrait<C> + ?Sized,
+    Thin<'erased_data, V, C>: Sync,
+{
+}
+unsafe impl<'erased_data, V, C> Sync for Iter<'_, 'erased_data, V, C>
+where
+    V: ThinTrait<C> + ?Sized,
+    Thin<'erased_data, V, C>: Sync,
+{
+}
+impl<'a, 'erased_data, V, C> Iter<'a, 'erased_data, V, C>
+where
+    V: ThinTrait<C> + ?Sized,
+{
+    fn entry(&self, offset: usize) -> &'a Thin<'erased_data, V, C> {
+        // Safety: the offsets point to valid entries that are borrowed for `'a`.
+        unsafe { &*entry_ptr(self.buf, offset) }
+    }
+}
+impl<'a, 'erased_data, V, C> Iterator for Iter<'a, 'erased_data, V, C>
+where
+    V: ThinTrait<C> + ?Sized,
+{
+    type Item = &'a Thin<'erased_data, V, C>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let offset = *self.offsets.next()?;
+        Some(self.entry(offset))
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.offsets.size_hint()
+    }
+}
+impl<V, C> DoubleEndedIterator for Iter<'_, '_, V, C>
+where
+    V: ThinTrait<C> + ?Sized,
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let offset = *self.offsets.next_back()?;
+        Some(self.entry(offset))
+    }
+}
+impl<V, C> ExactSizeIterator for Iter<'_, '_, V, C> where V: ThinTrait<C> + ?Sized {}
+impl<V, C> FusedIterator for Iter<'_, '_, V, C> where V: ThinTrait<C> + ?Sized {}
+impl<V, C> Clone for Iter<'_, '_, V, C>
+where
+    V: ThinTrait<C> + ?Sized,
+{
+    fn clone(&self) -> Self {
+        Self {
+            buf: self.buf,
+            offsets: self.offsets.clone(),
+            _borrowed: PhantomData,
+        }
+    }
+}
+impl<V, C> fmt::Debug for Iter<'_, '_, V, C>
+where
+    V: ThinTrait<C> + ?Sized,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct(get_type_name!(Iter))
+            .field("remaining", &self.offsets.len())
+            .finish()
+    }
+}
+
+/// A mutable iterator over the objects in a [`ThinVec`], created by
+/// [`ThinVec::iter_mut`].
+pub struct IterMut<'a, 'erased_data, V, C>
+where
+    V: ThinTrait<C> + ?Sized,
+{
+    buf: NonNull<u8>,
+    offsets: slice::Iter<'a, usize>,
+    _borrowed: PhantomData<&'a mut Thin<'erased_data, V, C>>,
+}
+// Safety: the iterator only hands out references to the objects.
+unsafe impl<'erased_data, V, C> Send for IterMut<'_, 'erased_data, V, C>
+where
+    V: ThinTrait<C> + ?Sized,
+    Thin<'erased_data, V, C>: Send,
+{
+}
+unsafe impl<'erased_data, V, C> Sync for IterMut<'_, 'erased_data, V, C>
+where
+    V: ThinTrait<C> + ?Sized,
+    Thin<'erased_data, V, C>: Sync,
+{
+}
+impl<'a, 'erased_data, V, C> IterMut<'a, 'erased_data, V, C>
+where
+    V: ThinTrait<C> + ?Sized,
+{
+    fn entry(&mut self, offset: usize) -> &'a mut Thin<'erased_data, V, C> {
+        // Safety: the offsets point to valid entries that are mutably borrowed
+        // for `'a` and every offset is only yielded once.
+        unsafe { &mut *entry_ptr(self.buf, offset) }
+    }
+}
+impl<'a, 'erased_data, V, C> Iterator for IterMut<'a, 'erased_data, V, C>
+where
+    V: ThinTrait<C> + ?Sized,
+{
+    type Item = &'a mut Thin<'erased_data, V, C>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let offset = *self.offsets.next()?;
+        Some(self.entry(offset))
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.offsets.size_hint()
+    }
+}
+impl<V, C> DoubleEndedIterator for IterMut<'_, '_, V, C>
+where
+    V: ThinTrait<C> + ?Sized,
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let offset = *self.offsets.next_back()?;
+        Some(self.entry(offset))
+    }
+}
+impl<V, C> ExactSizeIterator for IterMut<'_, '_, V, C> where V: ThinTrait<C> + ?Sized {}
+impl<V, C> FusedIterator for IterMut<'_, '_, V, C> where V: ThinTrait<C> + ?Sized {}
+impl<V, C> fmt::Debug for IterMut<'_, '_, V, C>
+where
+    V: ThinTrait<C> + ?Sized,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct(get_type_name!(IterMut))
+            .field("remaining", &self.offsets.len())
+            .finish()
+    }
+}
@@ -0,0 +1,43 @@
+use core::{alloc::Layout, ptr::NonNull};
+use thin_trait_object::allocator::{AllocError, Allocator, Global};
+use thin_trait_object::prelude::*;
+
+define_v_table!(
+    trait Foo {
+        fn foo(&self) -> u32;
+    }
+);
+impl Foo for u32 {
+    fn foo(&self) -> u32 {
+        *self
+    }
+}
+
+struct A;
+unsafe impl Allocator for A {
+    fn allocate(&self, layout: Layout) -> Result<NonNull<u8>, AllocError> {
+        Global.allocate(layout)
+    }
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        Global.deallocate(ptr, layout)
+    }
+}
+
+fn leak_box() -> &'static mut Thin<'static, dyn Foo, ()> {
+    let a = A;
+    let b: ThinBox<'static, dyn Foo, (), &A> = ThinBox::new_in(5u32, (), &a);
+    // Fails: the leaked memory is owned by an allocator on the stack.
+    ThinBox::leak(b)
+}
+
+fn leak_box_without_common() -> &'static mut ThinWithoutCommon<'static, dyn Foo, ()> {
+    let a = A;
+    let b: ThinBoxWithoutCommon<'static, dyn Foo, (), &A> = ThinBoxWithoutCommon::new_in(5u32, &a);
+    // Fails:
+    ThinBoxWithoutCommon::leak(b)
+}
+
+fn main() {
+    assert_eq!(leak_box().foo(), 5);
+    assert_eq!(leak_box_without_common().foo(), 5);
+}